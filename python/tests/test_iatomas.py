@@ -0,0 +1,131 @@
+"""Tests of the `iatomas` Python module.
+
+Build the module first, without network access needed:
+
+    cargo build --release --no-default-features --features python
+    cp target/release/libiatomas.so python/iatomas.so
+    python3 -m unittest discover python/tests
+"""
+
+import copy
+import os
+import sys
+import unittest
+
+sys.path.insert(0, os.path.join(os.path.dirname(__file__), ".."))
+
+import iatomas
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+
+class GameStateTest(unittest.TestCase):
+    def test_start(self):
+        state = iatomas.GameState(seed=1)
+        self.assertEqual(state.atoms, ["H", "H", "H", "H"])
+        self.assertEqual(state.score, 0)
+        self.assertFalse(state.is_over())
+        self.assertTrue(state.legal_moves())
+
+    def test_seed_determines_game(self):
+        a = iatomas.GameState(seed=7)
+        b = iatomas.GameState(seed=7)
+        for _ in range(30):
+            if a.is_over():
+                break
+            mv = a.legal_moves()[0]
+            a.apply(mv)
+            b.apply(mv)
+            self.assertEqual(a.position(), b.position())
+
+    def test_illegal_move(self):
+        state = iatomas.GameState.from_position("H,He Li 0 2")
+        with self.assertRaises(ValueError):
+            state.apply(5)
+        with self.assertRaises(ValueError):
+            state.apply(iatomas.ACTIONS)
+
+    def test_clone_is_independent(self):
+        state = iatomas.GameState(seed=2)
+        for other in (state.clone(), copy.copy(state), copy.deepcopy(state)):
+            other.apply(other.legal_moves()[0])
+            self.assertNotEqual(other.position(), state.position())
+
+    def test_position_round_trip(self):
+        text = "H,He,+,He,D+ Li 42 17 m"
+        state = iatomas.GameState.from_position(text)
+        self.assertEqual(state.position(), text)
+        self.assertEqual(state.atoms, ["H", "He", "+", "He", "D+"])
+        self.assertEqual(state.incoming, "Li")
+        self.assertEqual(state.score, 42)
+        self.assertEqual(state.time, 17)
+        self.assertIn(2 * 18, state.legal_moves())
+
+    def test_reaction(self):
+        state = iatomas.GameState.from_position("+,H H 0 2")
+        state.apply(0)
+        self.assertEqual(state.atoms, ["He"])
+        self.assertGreater(state.score, 0)
+
+    def test_bad_position(self):
+        for text in ("", "H,Xx H 0 0", "H H zero 0", "H H 0 0 z"):
+            with self.assertRaises(ValueError):
+                iatomas.GameState.from_position(text)
+
+
+class SpawnModelTest(unittest.TestCase):
+    def test_seeded_draws(self):
+        for name in ("simple", "drawing"):
+            a = iatomas.SpawnModel(name, seed=3)
+            b = iatomas.SpawnModel(name, seed=3)
+            self.assertEqual([a.draw(t, 0) for t in range(50)],
+                             [b.draw(t, 0) for t in range(50)])
+            b.seed(3)
+            a.seed(3)
+            self.assertEqual(a.draw(0, 0), b.draw(0, 0))
+
+    def test_unknown_model(self):
+        with self.assertRaises(ValueError):
+            iatomas.SpawnModel("nope")
+
+
+@unittest.skipIf(numpy is None, "NumPy is not installed")
+class BatchEnvTest(unittest.TestCase):
+    def play(self, threads):
+        env = iatomas.BatchEnv(8, seed=4, threads=threads)
+        obs = env.reset()
+        self.assertEqual(obs.shape, (8, iatomas.OBSERVATION_SIZE))
+        self.assertEqual(obs.dtype, numpy.float32)
+        rng = numpy.random.default_rng(0)
+        masks = env.action_masks()
+        rewards = []
+        for _ in range(200):
+            actions = [int(rng.choice(numpy.flatnonzero(m))) for m in masks]
+            obs, reward, done, info = env.step(actions)
+            self.assertEqual(reward.shape, (8,))
+            self.assertEqual(done.dtype, numpy.bool_)
+            masks = info["action_mask"]
+            self.assertEqual(masks.shape, (8, iatomas.ACTIONS))
+            rewards.append(reward)
+        return obs, numpy.array(rewards)
+
+    def test_threads_do_not_change_games(self):
+        obs1, rewards1 = self.play(1)
+        obs4, rewards4 = self.play(4)
+        numpy.testing.assert_array_equal(obs1, obs4)
+        numpy.testing.assert_array_equal(rewards1, rewards4)
+
+    def test_illegal_action(self):
+        env = iatomas.BatchEnv(2)
+        env.reset()
+        with self.assertRaises(ValueError):
+            env.step([iatomas.ACTIONS, 0])
+        with self.assertRaises(ValueError):
+            env.step([0])
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,189 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::{Agent, sort_ranking};
+use crate::game::{AtomType, GameState, Move};
+use crate::invalid_data;
+
+/// Number of features the heuristic looks at.
+pub const FEATURES: usize = 9;
+
+/// Names of the features, as written in weights files:
+///
+/// - `score`: score gained by the move
+///
+/// - `atoms`: number of atoms on the ring
+///
+/// - `pluses`: number of pluses (and dark pluses) on the ring
+///
+/// - `pairs`: number of neighbouring equal atoms
+///
+/// - `chain`: length of the longest chain a Plus could trigger
+///
+/// - `smooth`: number of neighbouring atoms whose atomic numbers differ by one
+///
+/// - `top`: highest atom on the ring
+///
+/// - `spread`: number of different atoms on the ring
+///
+/// - `crowding`: square of the number of atoms above 12
+pub const FEATURE_NAMES: [&str; FEATURES] = [
+    "score", "atoms", "pluses", "pairs", "chain", "smooth", "top", "spread",
+    "crowding" ];
+
+/// Value given to the moves that end the game.
+const GAME_OVER_VALUE: f64 = -1e9;
+
+/// Weights of the features, the value of a position being their weighted sum.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights {
+    pub values: [f64; FEATURES],
+}
+
+/// One-ply agent that plays the move leading to the best weighted sum of
+/// features.
+pub struct HeuristicAgent {
+    pub weights: Weights,
+}
+
+impl Default for Weights {
+    /// Hand-picked weights, a reasonable starting point for tuning
+    fn default() -> Self {
+        Self {
+            values: [1.0, -5.0, 2.0, 3.0, 8.0, 1.0, 2.0, -1.0, -10.0],
+        }
+    }
+}
+
+impl Weights {
+    /// Reads weights from a file holding one `<feature> <weight>` pair per
+    /// line. Features that are not given keep their default weight, and
+    /// lines starting with `#` are ignored.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a weights file, see `Weights::load`
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut weights = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let i = FEATURE_NAMES.iter().position(|n| *n == name)
+                .ok_or_else(|| {
+                    invalid_data(format!("unknown feature `{}`", name))
+                })?;
+            weights.values[i] = words.next()
+                .and_then(|w| w.parse::<f64>().ok())
+                .ok_or_else(|| {
+                    invalid_data(format!("bad weight in `{}`", line))
+                })?;
+        }
+        Ok(weights)
+    }
+
+    /// Writes the weights in a file that `Weights::load` can read
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "# iatomas heuristic weights")?;
+        for (name, w) in FEATURE_NAMES.iter().zip(self.values.iter()) {
+            writeln!(file, "{} {}", name, w)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the features of `after`, reached from `before` by one move.
+pub fn features(before: &GameState, after: &GameState) -> [f64; FEATURES] {
+    let atoms = &after.atoms;
+    let n = atoms.len();
+    let mut f = [0.0; FEATURES];
+
+    f[0] = after.score.saturating_sub(before.score) as f64;
+    f[1] = n as f64;
+
+    let mut seen = Vec::new();
+    for i in 0..n {
+        let next = &atoms[(i + 1) % n];
+        match atoms[i].t {
+            AtomType::Plus | AtomType::DarkPlus => f[2] += 1.0,
+            AtomType::Atom(z) => {
+                if let AtomType::Atom(z_next) = next.t {
+                    if n > 1 && z == z_next { f[3] += 1.0; }
+                    if n > 1 && (z as i32 - z_next as i32).abs() == 1 {
+                        f[5] += 1.0;
+                    }
+                }
+                if z as f64 > f[6] { f[6] = z as f64; }
+                if !seen.contains(&z) { seen.push(z); }
+            },
+            _ => {}
+        }
+        f[4] = f[4].max(chain_at_gap(after, i) as f64);
+    }
+    f[7] = seen.len() as f64;
+    let over = n.saturating_sub(12) as f64;
+    f[8] = over * over;
+    f
+}
+
+/// Number of reactions a Plus shot just before the `i`-th atom would trigger
+pub fn chain_at_gap(state: &GameState, i: usize) -> usize {
+    let atoms = &state.atoms;
+    let n = atoms.len();
+    let mut length = 0;
+    while 2 * (length + 1) <= n {
+        let left = &atoms[(i + 2 * n - 1 - length) % n];
+        let right = &atoms[(i + length) % n];
+        match (&left.t, &right.t) {
+            (AtomType::Atom(z1), AtomType::Atom(z2)) if z1 == z2 => {
+                length += 1;
+            },
+            _ => break
+        }
+    }
+    length
+}
+
+impl HeuristicAgent {
+    /// Creates an agent using the given weights
+    pub fn new(weights: Weights) -> Self {
+        Self {
+            weights,
+        }
+    }
+
+    /// Value of the position `after`, reached from `before` by one move
+    pub fn evaluate(&self, before: &GameState, after: &GameState) -> f64 {
+        if after.is_over() {
+            return GAME_OVER_VALUE;
+        }
+        features(before, after).iter().zip(self.weights.values.iter())
+                               .map(|(f, w)| f * w)
+                               .sum()
+    }
+}
+
+impl Default for HeuristicAgent {
+    fn default() -> Self {
+        Self::new(Weights::default())
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn rank_moves(&mut self, state: &GameState) -> Vec<(Move, f64)> {
+        let mut moves: Vec<(Move, f64)> = state.legal_moves().into_iter()
+            .map(|mv| {
+                let (after, _) = state.afterstate(mv);
+                (mv, self.evaluate(state, &after))
+            })
+            .collect();
+        sort_ranking(&mut moves);
+        moves
+    }
+}
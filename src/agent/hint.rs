@@ -0,0 +1,90 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use super::{Agent, SearchLimit};
+use crate::game::{GameState, Move};
+
+/// Moves kept in a hint
+pub const HINT_MOVES: usize = 3;
+
+/// The best moves an agent found for a state, best first, with their
+/// estimated values.
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub moves: Vec<(Move, f64)>,
+}
+
+/// Asks an agent for hints on a background thread, so that the caller never
+/// waits for its search.
+///
+/// Every request cancels the previous ones: the thread skips those it has
+/// not started yet, stops the search of the one it is on, and the results of
+/// the others are dropped.
+pub struct Hinter {
+    requests: Sender<(u64, GameState, SearchLimit)>,
+    hints: Receiver<(u64, Hint)>,
+    /// Number of the last request, shared with the thread
+    current: Arc<AtomicU64>,
+    /// Flag stopping the search of the last request
+    cancel: Arc<AtomicBool>,
+}
+
+impl Hinter {
+    /// Starts the thread of `agent`. It ends once the `Hinter` is dropped
+    /// and its last search is over.
+    pub fn new(mut agent: Box<dyn Agent>) -> Self {
+        let (requests, inbox) =
+            mpsc::channel::<(u64, GameState, SearchLimit)>();
+        let (outbox, hints) = mpsc::channel();
+        let current = Arc::new(AtomicU64::new(0));
+        let latest = Arc::clone(&current);
+        thread::spawn(move || {
+            while let Ok(mut request) = inbox.recv() {
+                while let Ok(newer) = inbox.try_recv() {
+                    request = newer;
+                }
+                let (id, state, limit) = request;
+                if id != latest.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let mut moves = agent.rank_moves_within(&state, &limit);
+                moves.truncate(HINT_MOVES);
+                if outbox.send((id, Hint { moves })).is_err() {
+                    break;
+                }
+            }
+        });
+        let cancel = Arc::new(AtomicBool::new(false));
+        Self { requests, hints, current, cancel }
+    }
+
+    /// Asks for a hint on `state`, cancelling the pending one if any
+    pub fn request(&mut self, state: &GameState) {
+        self.cancel();
+        self.cancel = Arc::new(AtomicBool::new(false));
+        let limit = SearchLimit::cancelled_by(Arc::clone(&self.cancel));
+        let id = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        // The thread only stops once the `Hinter` is dropped
+        let _ = self.requests.send((id, state.clone(), limit));
+    }
+
+    /// Cancels the pending hint, if any, stopping its search
+    pub fn cancel(&mut self) {
+        self.cancel.store(true, Ordering::SeqCst);
+        self.current.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// The hint on the last state requested, once found
+    pub fn poll(&mut self) -> Option<Hint> {
+        let current = self.current.load(Ordering::SeqCst);
+        let mut found = None;
+        while let Ok((id, hint)) = self.hints.try_recv() {
+            if id == current {
+                found = Some(hint);
+            }
+        }
+        found
+    }
+}
@@ -0,0 +1,184 @@
+use super::{Agent, SearchLimit};
+use crate::game::{ACTIONS, AtomType, ENCODING_SIZE, GameState, Move,
+                  action_mask, encode};
+use crate::nn::PolicyValueNet;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Scores are divided by this value to get the values the networks learn.
+pub const VALUE_SCALE: f64 = 1000.0;
+
+/// Monte Carlo tree search guided by a policy/value network: the policy
+/// gives the priors of the moves, the value replaces rollouts.
+///
+/// The future atoms being unknown, every simulation draws them anew, and a
+/// move leads to one child per incoming atom met after it.
+pub struct MctsAgent {
+    pub net: PolicyValueNet,
+    pub simulations: usize,
+    pub c_puct: f64,
+    rng: ChaCha8Rng,
+}
+
+/// Statistics of a move of a searched node
+struct Edge {
+    mv: Move,
+    prior: f64,
+    visits: u32,
+    total: f64,
+    children: Vec<(AtomType, Node)>,
+}
+
+#[derive(Default)]
+struct Node {
+    edges: Vec<Edge>,
+    expanded: bool,
+    visits: u32,
+}
+
+/// Lowest and highest mean values met in a search, to normalise them
+struct Bounds {
+    min: f64,
+    max: f64,
+}
+
+impl Bounds {
+    fn update(&mut self, q: f64) {
+        self.min = self.min.min(q);
+        self.max = self.max.max(q);
+    }
+
+    fn normalise(&self, q: f64) -> f64 {
+        if self.max > self.min { (q - self.min) / (self.max - self.min) }
+        else                   { 0.5 }
+    }
+}
+
+/// Value of `state` (in score units) and priors of its legal moves, as given
+/// by `net`
+pub fn evaluate(net: &PolicyValueNet, state: &GameState)
+                                            -> (f64, Vec<(Move, f64)>) {
+    let mut x = vec![0.0; ENCODING_SIZE];
+    let mut mask = vec![false; ACTIONS];
+    encode(state, &mut x);
+    action_mask(state, &mut mask);
+    let (value, policy) = net.predict(&x, &mask);
+    let priors = state.legal_moves().into_iter()
+                      .map(|mv| (mv, policy[mv.index()] as f64))
+                      .collect();
+    (value as f64 * VALUE_SCALE, priors)
+}
+
+impl MctsAgent {
+    /// Creates an agent running `simulations` simulations per move
+    pub fn new(net: PolicyValueNet, simulations: usize, seed: u64) -> Self {
+        Self {
+            net,
+            simulations,
+            c_puct: 1.5,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Searches `state`, which must not be over.
+    ///
+    /// Returns: the legal moves with their number of visits and mean value
+    pub fn search(&mut self, state: &GameState) -> Vec<(Move, u32, f64)> {
+        self.search_within(state, &SearchLimit::default())
+    }
+
+    /// Searches `state` as `search` does, stopping early once `limit` is
+    /// reached. The first simulation is always run, for the legal moves to
+    /// be known.
+    pub fn search_within(&mut self, state: &GameState, limit: &SearchLimit)
+                                                    -> Vec<(Move, u32, f64)> {
+        let mut root = Node::default();
+        let mut bounds = Bounds { min: f64::INFINITY,
+                                  max: f64::NEG_INFINITY };
+        for i in 0..self.simulations.max(1) {
+            if i > 0 && limit.reached() {
+                break;
+            }
+            let mut sample = state.clone();
+            sample.rng = ChaCha8Rng::seed_from_u64(self.rng.gen());
+            simulate(&mut root, &mut sample, &self.net, self.c_puct,
+                     &mut bounds);
+        }
+        root.edges.iter().map(|e| {
+            let q = if e.visits > 0 { e.total / e.visits as f64 }
+                    else            { 0.0 };
+            (e.mv, e.visits, q)
+        }).collect()
+    }
+}
+
+/// Runs one simulation from `node`, whose position is `state`.
+///
+/// Returns: the score gained from `state` on, the end being estimated by the
+/// network
+fn simulate(node: &mut Node, state: &mut GameState, net: &PolicyValueNet,
+            c_puct: f64, bounds: &mut Bounds) -> f64 {
+    if state.is_over() {
+        return 0.0;
+    }
+    if !node.expanded {
+        let (value, priors) = evaluate(net, state);
+        node.edges = priors.into_iter().map(|(mv, prior)| Edge {
+            mv,
+            prior,
+            visits: 0,
+            total: 0.0,
+            children: Vec::new(),
+        }).collect();
+        node.expanded = true;
+        node.visits += 1;
+        return value;
+    }
+
+    let sqrt_visits = (node.visits as f64).sqrt();
+    let mut best = 0;
+    let mut best_score = f64::NEG_INFINITY;
+    for (i, e) in node.edges.iter().enumerate() {
+        let q = if e.visits > 0 { bounds.normalise(e.total / e.visits as f64) }
+                else            { 0.0 };
+        let u = c_puct * e.prior * sqrt_visits / (1.0 + e.visits as f64);
+        if q + u > best_score {
+            best_score = q + u;
+            best = i;
+        }
+    }
+
+    let edge = &mut node.edges[best];
+    let score = state.score;
+    state.apply(edge.mv);
+    let reward = state.score.saturating_sub(score) as f64;
+
+    let i = match edge.children.iter().position(|(t, _)| *t == state.incoming.t) {
+        Some(i) => i,
+        None => {
+            edge.children.push((state.incoming.t.clone(), Node::default()));
+            edge.children.len() - 1
+        }
+    };
+    let g = reward + simulate(&mut edge.children[i].1, state, net, c_puct,
+                              bounds);
+    edge.visits += 1;
+    edge.total += g;
+    bounds.update(edge.total / edge.visits as f64);
+    node.visits += 1;
+    g
+}
+
+impl Agent for MctsAgent {
+    fn rank_moves(&mut self, state: &GameState) -> Vec<(Move, f64)> {
+        self.rank_moves_within(state, &SearchLimit::default())
+    }
+
+    fn rank_moves_within(&mut self, state: &GameState, limit: &SearchLimit)
+                                                    -> Vec<(Move, f64)> {
+        let mut moves = self.search_within(state, limit);
+        moves.sort_by_key(|m| std::cmp::Reverse(m.1));
+        moves.into_iter().map(|(mv, _, q)| (mv, q)).collect()
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+
+use super::{MAX_GAME_MOVES, MctsAgent, VALUE_SCALE, game_seed};
+use crate::game::{ACTIONS, ENCODING_SIZE, GameState, SpawnModel,
+                  action_mask, encode};
+use crate::nn::{Adam, Losses, PolicyValueNet};
+use crate::invalid_data;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Parameters of the training of a policy/value network by self-play.
+#[derive(Clone, Debug)]
+pub struct MlpConfig {
+    pub iterations: usize,
+    /// Games played by each iteration
+    pub games: usize,
+    /// Simulations per move of the searches of the self-play games
+    pub simulations: usize,
+    /// Sizes of the hidden layers of a new network
+    pub hidden: Vec<usize>,
+    /// Optimiser steps made by each iteration
+    pub steps: usize,
+    pub batch: usize,
+    pub rate: f32,
+    /// Number of positions kept to train on
+    pub buffer: usize,
+    pub seed: u64,
+    pub spawn: SpawnModel,
+    pub threads: usize,
+    pub out: PathBuf,
+}
+
+/// A position met in self-play, with what the network should predict
+#[derive(Clone)]
+pub struct Sample {
+    pub x: Vec<f32>,
+    pub mask: Vec<bool>,
+    /// Proportion of the visits of every move by the search
+    pub policy: Vec<f32>,
+    /// Score gained from the position to the end of the game, scaled
+    pub value: f32,
+}
+
+impl Default for MlpConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 20,
+            games: 16,
+            simulations: 32,
+            hidden: vec![128, 128],
+            steps: 200,
+            batch: 64,
+            rate: 1e-3,
+            buffer: 50_000,
+            seed: 0,
+            spawn: SpawnModel::default(),
+            threads: thread::available_parallelism()
+                            .map(|n| n.get()).unwrap_or(1),
+            out: PathBuf::from("mlp.bin"),
+        }
+    }
+}
+
+/// New network fitting the game encoding, with the layers of `config`
+pub fn new_network(config: &MlpConfig) -> PolicyValueNet {
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    PolicyValueNet::new(ENCODING_SIZE, &config.hidden, ACTIONS, &mut rng)
+}
+
+/// Checks that `net` takes the game encoding and returns one logit per move
+pub fn check_network(net: &PolicyValueNet) -> io::Result<()> {
+    if net.inputs() != ENCODING_SIZE || net.actions() != ACTIONS {
+        return Err(invalid_data(format!(
+            "network made for {} inputs and {} moves, the game has {} and {}",
+            net.inputs(), net.actions(), ENCODING_SIZE, ACTIONS)));
+    }
+    Ok(())
+}
+
+/// Trains `net` on games it plays against itself through MCTS: the policy
+/// learns the visits of the searches, and the value the score still gained.
+///
+/// The network is saved in `config.out` after each iteration.
+pub fn train_mlp(net: &mut PolicyValueNet, config: &MlpConfig)
+                                                        -> io::Result<()> {
+    check_network(net)?;
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut adam = Adam::new(config.rate);
+    let mut buffer: VecDeque<Sample> = VecDeque::new();
+
+    for it in 0..config.iterations {
+        let seeds: Vec<u64> = (0..config.games as u64)
+            .map(|g| game_seed(config.seed, (it * config.games) as u64 + g))
+            .collect();
+        let games = self_play(net, &seeds, config);
+        let mean = games.iter().map(|g| g.0 as f64).sum::<f64>()
+                   / games.len().max(1) as f64;
+        for (_, samples) in games {
+            buffer.extend(samples);
+        }
+        while buffer.len() > config.buffer {
+            buffer.pop_front();
+        }
+
+        let mut losses = Losses::default();
+        for _ in 0..config.steps {
+            let l = train_step(net, &mut adam, &buffer, config, &mut rng);
+            losses.value += l.value / config.steps as f32;
+            losses.policy += l.policy / config.steps as f32;
+        }
+        net.save(&config.out)?;
+        println!("iteration {}: mean score {:.1}, value loss {:.4}, \
+                  policy loss {:.4}", it, mean, losses.value, losses.policy);
+    }
+    Ok(())
+}
+
+/// Plays the games of `seeds`, split between threads.
+///
+/// Returns: the final score and the samples of every game
+fn self_play(net: &PolicyValueNet, seeds: &[u64], config: &MlpConfig)
+                                                -> Vec<(u32, Vec<Sample>)> {
+    let threads = config.threads.max(1);
+    let chunk = seeds.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = seeds.chunks(chunk).map(|seeds| {
+            scope.spawn(move || {
+                seeds.iter().map(|&s| play_recorded(net, s, config))
+                            .collect::<Vec<_>>()
+            })
+        }).collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Plays one game, choosing moves in proportion to their visits
+fn play_recorded(net: &PolicyValueNet, seed: u64, config: &MlpConfig)
+                                                    -> (u32, Vec<Sample>) {
+    let mut agent = MctsAgent::new(net.clone(), config.simulations, !seed);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed ^ 0x5eed);
+    let mut state = GameState::start_game_seeded(seed, config.spawn.clone());
+    let mut samples = Vec::new();
+    let mut scores = Vec::new();
+    let mut moves = 0;
+
+    while !state.is_over() && moves < MAX_GAME_MOVES {
+        let visits = agent.search(&state);
+        let total: u32 = visits.iter().map(|v| v.1).sum();
+        let mut sample = Sample {
+            x: vec![0.0; ENCODING_SIZE],
+            mask: vec![false; ACTIONS],
+            policy: vec![0.0; ACTIONS],
+            value: 0.0,
+        };
+        encode(&state, &mut sample.x);
+        action_mask(&state, &mut sample.mask);
+        for (mv, n, _) in &visits {
+            sample.policy[mv.index()] = *n as f32 / total.max(1) as f32;
+        }
+
+        let mut r = rng.gen_range(0..total.max(1));
+        let mut mv = visits[0].0;
+        for (m, n, _) in &visits {
+            if r < *n {
+                mv = *m;
+                break;
+            }
+            r -= n;
+        }
+        samples.push(sample);
+        scores.push(state.score);
+        state.apply(mv);
+        moves += 1;
+    }
+
+    for (sample, score) in samples.iter_mut().zip(scores) {
+        sample.value = ((state.score - score) as f64 / VALUE_SCALE) as f32;
+    }
+    (state.score, samples)
+}
+
+/// Makes one optimiser step on a batch drawn from `buffer`
+fn train_step(net: &mut PolicyValueNet, adam: &mut Adam,
+              buffer: &VecDeque<Sample>, config: &MlpConfig,
+              rng: &mut ChaCha8Rng) -> Losses {
+    if buffer.is_empty() {
+        return Losses::default();
+    }
+    let batch = config.batch.min(buffer.len());
+    let mut x = Vec::with_capacity(batch * ENCODING_SIZE);
+    let mut masks = Vec::with_capacity(batch * ACTIONS);
+    let mut policies = Vec::with_capacity(batch * ACTIONS);
+    let mut values = Vec::with_capacity(batch);
+    for _ in 0..batch {
+        let sample = &buffer[rng.gen_range(0..buffer.len())];
+        x.extend_from_slice(&sample.x);
+        masks.extend_from_slice(&sample.mask);
+        policies.extend_from_slice(&sample.policy);
+        values.push(sample.value);
+    }
+    net.train_batch(adam, &x, &masks, &policies, &values, batch)
+}
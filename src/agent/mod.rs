@@ -0,0 +1,182 @@
+mod heuristic;
+mod hint;
+mod mcts;
+mod mlp;
+mod ntuple;
+mod td;
+mod tune;
+
+pub use heuristic::*;
+pub use hint::*;
+pub use mcts::*;
+pub use mlp::*;
+pub use ntuple::*;
+pub use td::*;
+pub use tune::*;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::game::{GameState, Move, SpawnModel};
+use crate::nn::PolicyValueNet;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Number of moves after which a self-play game is stopped, even if it is not
+/// over.
+pub const MAX_GAME_MOVES: u32 = 20_000;
+
+/// When a search must stop, however many simulations it was set to run.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimit {
+    pub deadline: Option<Instant>,
+    /// Set from another thread to stop the search, e.g. when its result is
+    /// no longer wanted
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl SearchLimit {
+    /// A search stopping once `budget` has elapsed from now
+    pub fn within(budget: Duration) -> Self {
+        Self { deadline: Some(Instant::now() + budget), cancel: None }
+    }
+
+    /// A search stopping once `cancel` is set
+    pub fn cancelled_by(cancel: Arc<AtomicBool>) -> Self {
+        Self { deadline: None, cancel: Some(cancel) }
+    }
+
+    /// Whether the search must stop now
+    pub fn reached(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            || self.cancel.as_ref()
+                   .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+    }
+}
+
+/// Something that can choose the moves of a game.
+pub trait Agent: Send {
+    /// Estimates the value of every legal move of `state`, best move first.
+    fn rank_moves(&mut self, state: &GameState) -> Vec<(Move, f64)>;
+
+    /// As `rank_moves`, cutting the search short once `limit` is reached.
+    /// Agents that do not search answer at once anyway.
+    fn rank_moves_within(&mut self, state: &GameState, _limit: &SearchLimit)
+                                                    -> Vec<(Move, f64)> {
+        self.rank_moves(state)
+    }
+
+    /// Chooses the move to play in `state`, which must not be over.
+    fn choose(&mut self, state: &GameState) -> Move {
+        self.rank_moves(state)[0].0
+    }
+}
+
+/// Plays legal moves at random.
+pub struct RandomAgent {
+    rng: ChaCha8Rng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: ChaCha8Rng::seed_from_u64(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn rank_moves(&mut self, state: &GameState) -> Vec<(Move, f64)> {
+        let mut moves: Vec<(Move, f64)> = state.legal_moves().into_iter()
+            .map(|mv| (mv, self.rng.gen::<f64>()))
+            .collect();
+        sort_ranking(&mut moves);
+        moves
+    }
+}
+
+/// Simulations per move of an MCTS agent whose spec does not give them
+pub const DEFAULT_SIMULATIONS: usize = 100;
+
+/// Builds the agent described by `spec`, `seed` seeding its own random
+/// choices if any:
+///
+/// - `random`
+///
+/// - `heuristic` or `heuristic:<weights file>`
+///
+/// - `ntuple:<network file>`
+///
+/// - `mcts:<network file>` or `mcts:<network file>:<simulations>`
+pub fn from_spec(spec: &str, seed: u64) -> Result<Box<dyn Agent>, String> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (spec, None)
+    };
+    let load_error = |e: std::io::Error| format!("{}: {}", spec, e);
+    match (name, arg) {
+        ("random", None) => Ok(Box::new(RandomAgent::new(seed))),
+        ("heuristic", None) => Ok(Box::new(HeuristicAgent::default())),
+        ("heuristic", Some(path)) => {
+            let weights = Weights::load(path).map_err(load_error)?;
+            Ok(Box::new(HeuristicAgent::new(weights)))
+        },
+        ("ntuple", Some(path)) => {
+            let network = NTupleNetwork::load(path).map_err(load_error)?;
+            Ok(Box::new(NTupleAgent::new(network)))
+        },
+        ("mcts", Some(arg)) => {
+            let split = arg.rsplit_once(':')
+                           .map(|(path, n)| (path, n.parse::<usize>()));
+            let (path, simulations) = match split {
+                Some((path, Ok(n))) => (path, n),
+                _ => (arg, DEFAULT_SIMULATIONS)
+            };
+            let net = PolicyValueNet::load(path).map_err(load_error)?;
+            check_network(&net).map_err(load_error)?;
+            Ok(Box::new(MctsAgent::new(net, simulations, seed)))
+        },
+        _ => Err(format!("unknown agent `{}`", spec))
+    }
+}
+
+/// Sorts moves by decreasing value.
+pub fn sort_ranking(moves: &mut [(Move, f64)]) {
+    moves.sort_by(|a, b| b.1.partial_cmp(&a.1)
+                            .unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// Summary of a self-played game.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub score: u32,
+    pub best: u8,
+    pub moves: u32,
+}
+
+/// Lets `agent` play a whole game, determined by `seed` and `spawn`.
+pub fn play_game(agent: &mut dyn Agent, seed: u64, spawn: &SpawnModel)
+                                                            -> GameRecord {
+    let mut state = GameState::start_game_seeded(seed, spawn.clone());
+    let mut moves = 0;
+    while !state.is_over() && moves < MAX_GAME_MOVES {
+        let mv = agent.choose(&state);
+        state.apply(mv);
+        moves += 1;
+    }
+    GameRecord {
+        score: state.score,
+        best: state.best,
+        moves,
+    }
+}
+
+/// Derives the seed of the `i`-th game of a series from the series' seed.
+pub fn game_seed(seed: u64, i: u64) -> u64 {
+    // splitmix64 step, so that close series don't share games
+    let mut z = seed.wrapping_add(i.wrapping_add(1)
+                                   .wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,349 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use super::{Agent, sort_ranking};
+use crate::game::{AtomType, GameState, MAX_ATOMS, Move};
+use crate::invalid_data;
+
+/// Number of different codes a ring slot can take in a tuple.
+const BASE: usize = 12;
+/// Codes of the regular atoms go from `0` (lowest atom of the window) up to
+/// this value, higher atoms sharing the last code.
+const MAX_RELATIVE: u8 = 7;
+const CODE_PLUS: usize = 8;
+const CODE_DARK_PLUS: usize = 9;
+const CODE_OTHER: usize = 10;
+const CODE_EMPTY: usize = 11;
+
+/// Number of plus counts distinguished by the size table
+const SIZE_PLUSES: usize = 5;
+
+/// Most weights a tuple table read from a file may hold, that is windows of
+/// up to seven slots
+const MAX_TABLE: usize = 1 << 26;
+
+const MAGIC: &[u8; 4] = b"IATN";
+const VERSION: u32 = 1;
+
+/// Value given to the moves that end the game.
+const GAME_OVER_VALUE: f64 = -1e9;
+
+/// Where the windows of a tuple are laid on the ring.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    /// Around every Plus (or Dark Plus) of the ring
+    Plus,
+    /// At every slot of the ring
+    Every,
+}
+
+/// A window of ring slots, given by their offsets relative to the anchor,
+/// with its table of weights.
+///
+/// As the ring has no beginning, the window is laid at every anchor, and
+/// both ways round, the value of the tuple being the sum of the weights
+/// looked up.
+#[derive(Clone, Debug)]
+pub struct Tuple {
+    pub anchor: Anchor,
+    pub offsets: Vec<i32>,
+    pub weights: Vec<f32>,
+}
+
+/// N-tuple network estimating the score still to come after an afterstate
+/// (the ring once a move has been played, before the next atom is drawn).
+#[derive(Clone, Debug)]
+pub struct NTupleNetwork {
+    pub tuples: Vec<Tuple>,
+    /// Weights indexed by the number of atoms and of pluses on the ring
+    pub size: Vec<f32>,
+}
+
+/// Entry of a table looked up when evaluating a position: the index of the
+/// tuple (the size table being `tuples.len()`) and of the weight.
+pub type Lookup = (usize, usize);
+
+impl Tuple {
+    /// Creates a tuple with all weights set to zero
+    pub fn new(anchor: Anchor, offsets: Vec<i32>) -> Self {
+        let len = BASE.pow(offsets.len() as u32);
+        Self {
+            anchor,
+            offsets,
+            weights: vec![0.0; len],
+        }
+    }
+
+    /// Pushes the indices of the weights looked up in `state` into `out`
+    fn lookups(&self, state: &GameState, out: &mut Vec<usize>) {
+        for (k, atom) in state.atoms.iter().enumerate() {
+            if self.anchor == Anchor::Plus &&
+               atom.t != AtomType::Plus && atom.t != AtomType::DarkPlus {
+                continue;
+            }
+            for &way in &[1, -1] {
+                let slots: Vec<Option<&AtomType>> = self.offsets.iter()
+                    .map(|&o| slot(state, k, way * o, self.anchor))
+                    .collect();
+                out.push(window_index(&slots));
+            }
+        }
+    }
+}
+
+/// Atom `offset` slots away from the `k`-th one, if it can be seen from the
+/// anchor without going round the ring
+fn slot(state: &GameState, k: usize, offset: i32, anchor: Anchor)
+                                                    -> Option<&AtomType> {
+    let n = state.atoms.len() as i32;
+    let visible = match anchor {
+        Anchor::Plus => if offset < 0 { -offset <= (n - 1) / 2 }
+                        else          { offset <= n / 2 },
+        Anchor::Every => offset.abs() < n,
+    };
+    if !visible {
+        return None;
+    }
+    let i = (k as i32 + offset).rem_euclid(n) as usize;
+    Some(&state.atoms[i].t)
+}
+
+/// Index in a tuple's table of a window of slots, regular atoms being coded
+/// relatively to the lowest one
+fn window_index(slots: &[Option<&AtomType>]) -> usize {
+    let lowest = slots.iter().filter_map(|s| match s {
+        Some(AtomType::Atom(z)) => Some(*z),
+        _ => None
+    }).min().unwrap_or(0);
+
+    slots.iter().rev().fold(0, |index, s| {
+        let code = match s {
+            Some(AtomType::Atom(z)) => (z - lowest).min(MAX_RELATIVE) as usize,
+            Some(AtomType::Plus) => CODE_PLUS,
+            Some(AtomType::DarkPlus) => CODE_DARK_PLUS,
+            Some(_) => CODE_OTHER,
+            None => CODE_EMPTY,
+        };
+        index * BASE + code
+    })
+}
+
+impl Default for NTupleNetwork {
+    /// Windows of two and three slots on each side of the pluses, and of four
+    /// consecutive slots anywhere on the ring
+    fn default() -> Self {
+        Self::new(vec![
+            Tuple::new(Anchor::Plus, vec![-2, -1, 1, 2]),
+            Tuple::new(Anchor::Plus, vec![-3, -2, -1, 1, 2, 3]),
+            Tuple::new(Anchor::Plus, vec![1, 2, 3, 4]),
+            Tuple::new(Anchor::Every, vec![0, 1, 2, 3]),
+        ])
+    }
+}
+
+impl NTupleNetwork {
+    /// Creates a network made of `tuples`, and of the size table
+    pub fn new(tuples: Vec<Tuple>) -> Self {
+        Self {
+            tuples,
+            size: vec![0.0; (MAX_ATOMS + 2) * SIZE_PLUSES],
+        }
+    }
+
+    /// Weights looked up to evaluate `state`
+    pub fn lookups(&self, state: &GameState) -> Vec<Lookup> {
+        let mut out = Vec::new();
+        let mut indices = Vec::new();
+        for (t, tuple) in self.tuples.iter().enumerate() {
+            indices.clear();
+            tuple.lookups(state, &mut indices);
+            out.extend(indices.iter().map(|&i| (t, i)));
+        }
+
+        let pluses = state.atoms.iter()
+            .filter(|a| a.t == AtomType::Plus || a.t == AtomType::DarkPlus)
+            .count();
+        let n = state.atoms.len().min(MAX_ATOMS + 1);
+        out.push((self.tuples.len(),
+                  n * SIZE_PLUSES + pluses.min(SIZE_PLUSES - 1)));
+        out
+    }
+
+    /// Sum of the weights of some lookups
+    pub fn value_of(&self, lookups: &[Lookup]) -> f64 {
+        lookups.iter().map(|&(t, i)| self.weight(t, i) as f64).sum()
+    }
+
+    /// Estimated score still to come from afterstate `state`
+    pub fn value(&self, state: &GameState) -> f64 {
+        if state.is_over() {
+            return 0.0;
+        }
+        self.value_of(&self.lookups(state))
+    }
+
+    /// Moves the value of `lookups` by `delta`, shared between the weights.
+    ///
+    /// A weight can be looked up several times (e.g. for a ring holding only
+    /// Hydrogen), so the step is normalised by the sum of the squared counts
+    /// for the value to move by `delta` exactly.
+    pub fn update(&mut self, lookups: &[Lookup], delta: f64) {
+        if lookups.is_empty() {
+            return;
+        }
+        let mut sorted = lookups.to_vec();
+        sorted.sort_unstable();
+        let mut norm = 0.0;
+        let mut count = 0.0;
+        for i in 0..sorted.len() {
+            count += 1.0;
+            if i + 1 == sorted.len() || sorted[i + 1] != sorted[i] {
+                norm += count * count;
+                count = 0.0;
+            }
+        }
+        let step = (delta / norm) as f32;
+        for &(t, i) in lookups {
+            if t == self.tuples.len() {
+                self.size[i] += step;
+            } else {
+                self.tuples[t].weights[i] += step;
+            }
+        }
+    }
+
+    fn weight(&self, t: usize, i: usize) -> f32 {
+        if t == self.tuples.len() { self.size[i] }
+        else                      { self.tuples[t].weights[i] }
+    }
+
+    /// Writes the network in a binary file: a magic number and a version,
+    /// then every table with its layout, all numbers being little endian
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.tuples.len() as u32).to_le_bytes())?;
+        for tuple in &self.tuples {
+            let anchor: u8 = match tuple.anchor {
+                Anchor::Plus => 0,
+                Anchor::Every => 1,
+            };
+            out.write_all(&[anchor, tuple.offsets.len() as u8])?;
+            for o in &tuple.offsets {
+                out.write_all(&o.to_le_bytes())?;
+            }
+            write_table(&mut out, &tuple.weights)?;
+        }
+        write_table(&mut out, &self.size)?;
+        out.flush()
+    }
+
+    /// Reads a network written by `NTupleNetwork::save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut input = io::BufReader::new(fs::File::open(path)?);
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an n-tuple network file"));
+        }
+        let version = read_u32(&mut input)?;
+        if version != VERSION {
+            return Err(invalid_data("unsupported n-tuple network version"));
+        }
+        let count = read_u32(&mut input)?;
+        let mut tuples = Vec::new();
+        for _ in 0..count {
+            let mut header = [0; 2];
+            input.read_exact(&mut header)?;
+            let anchor = match header[0] {
+                0 => Anchor::Plus,
+                1 => Anchor::Every,
+                _ => return Err(invalid_data("unknown tuple anchor"))
+            };
+            // A window is at most the whole ring
+            let len = header[1] as usize;
+            let table = BASE.checked_pow(len as u32);
+            if len == 0 || len > MAX_ATOMS + 1 ||
+               table.is_none_or(|t| t > MAX_TABLE) {
+                return Err(invalid_data("bad tuple length"));
+            }
+            let mut offsets = Vec::new();
+            for _ in 0..len {
+                let offset = read_u32(&mut input)? as i32;
+                if offset.unsigned_abs() as usize > MAX_ATOMS {
+                    return Err(invalid_data("tuple offset out of the ring"));
+                }
+                offsets.push(offset);
+            }
+            let mut tuple = Tuple::new(anchor, offsets);
+            read_table(&mut input, &mut tuple.weights)?;
+            tuples.push(tuple);
+        }
+        let mut network = Self::new(tuples);
+        read_table(&mut input, &mut network.size)?;
+        Ok(network)
+    }
+}
+
+fn write_table<W: Write>(out: &mut W, table: &[f32]) -> io::Result<()> {
+    out.write_all(&(table.len() as u32).to_le_bytes())?;
+    for w in table {
+        out.write_all(&w.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Fills `table` from `input`, checking that the lengths match
+fn read_table<R: Read>(input: &mut R, table: &mut [f32]) -> io::Result<()> {
+    if read_u32(input)? as usize != table.len() {
+        return Err(invalid_data("table length does not match its tuple"));
+    }
+    let mut bytes = [0; 4];
+    for w in table.iter_mut() {
+        input.read_exact(&mut bytes)?;
+        *w = f32::from_le_bytes(bytes);
+    }
+    Ok(())
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Plays the move maximising the score it gains plus the value of its
+/// afterstate.
+pub struct NTupleAgent {
+    pub network: NTupleNetwork,
+}
+
+impl NTupleAgent {
+    pub fn new(network: NTupleNetwork) -> Self {
+        Self { network }
+    }
+}
+
+/// Values of the legal moves of `state` according to `network`: the score
+/// gained plus the value of the afterstate.
+pub fn afterstate_values(network: &NTupleNetwork, state: &GameState)
+                                                    -> Vec<(Move, f64)> {
+    state.legal_moves().into_iter().map(|mv| {
+        let (after, _) = state.afterstate(mv);
+        if after.is_over() {
+            return (mv, GAME_OVER_VALUE);
+        }
+        let reward = after.score.saturating_sub(state.score) as f64;
+        (mv, reward + network.value(&after))
+    }).collect()
+}
+
+impl Agent for NTupleAgent {
+    fn rank_moves(&mut self, state: &GameState) -> Vec<(Move, f64)> {
+        let mut moves = afterstate_values(&self.network, state);
+        sort_ranking(&mut moves);
+        moves
+    }
+}
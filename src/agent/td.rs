@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use std::io;
+
+use super::{Lookup, MAX_GAME_MOVES, NTupleNetwork, afterstate_values,
+            game_seed};
+use crate::game::{GameState, SpawnModel};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Parameters of the temporal-difference training of an n-tuple network.
+#[derive(Clone, Debug)]
+pub struct TdConfig {
+    pub games: usize,
+    /// Learning rate, shared between the weights looked up
+    pub alpha: f64,
+    /// Trace decay: `0` learns from the next afterstate only, `1` from the
+    /// final score of the game
+    pub lambda: f64,
+    /// Chance of playing a random move instead of the best one
+    pub epsilon: f64,
+    pub seed: u64,
+    pub spawn: SpawnModel,
+    /// Number of games between two progress reports and saves
+    pub report: usize,
+    pub out: PathBuf,
+}
+
+/// One step of a self-played game: the score gained by the move, and the
+/// afterstate it led to.
+struct Step {
+    reward: f64,
+    lookups: Vec<Lookup>,
+    over: bool,
+}
+
+impl Default for TdConfig {
+    fn default() -> Self {
+        Self {
+            games: 10_000,
+            alpha: 0.1,
+            lambda: 0.5,
+            epsilon: 0.0,
+            seed: 0,
+            spawn: SpawnModel::default(),
+            report: 500,
+            out: PathBuf::from("ntuple.bin"),
+        }
+    }
+}
+
+/// Trains `network` by TD(λ) on self-played games, where moves are chosen by
+/// the network itself.
+///
+/// The network is saved in `config.out` every `config.report` games and at
+/// the end.
+pub fn train_td(network: &mut NTupleNetwork, config: &TdConfig)
+                                                        -> io::Result<()> {
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut total = 0.0;
+    let mut best = 0;
+    for g in 0..config.games {
+        let seed = game_seed(config.seed, g as u64);
+        let steps = self_play(network, seed, config, &mut rng);
+        learn(network, &steps, config);
+
+        let score: f64 = steps.iter().map(|s| s.reward).sum();
+        total += score;
+        best = best.max(score as u32);
+        if (g + 1) % config.report.max(1) == 0 || g + 1 == config.games {
+            let games = (g % config.report.max(1)) + 1;
+            println!("{} games: mean score {:.1}, best {}", g + 1,
+                     total / games as f64, best);
+            network.save(&config.out)?;
+            total = 0.0;
+            best = 0;
+        }
+    }
+    Ok(())
+}
+
+/// Plays a game choosing moves with `network`, recording every step.
+fn self_play(network: &NTupleNetwork, seed: u64, config: &TdConfig,
+             rng: &mut ChaCha8Rng) -> Vec<Step> {
+    let mut state = GameState::start_game_seeded(seed, config.spawn.clone());
+    let mut steps = Vec::new();
+    while !state.is_over() && steps.len() < MAX_GAME_MOVES as usize {
+        let values = afterstate_values(network, &state);
+        let mv = if rng.gen::<f64>() < config.epsilon {
+            values[rng.gen_range(0..values.len())].0
+        } else {
+            values.iter().fold(values[0], |best, v| {
+                if v.1 > best.1 { *v } else { best }
+            }).0
+        };
+
+        let (after, _) = state.afterstate(mv);
+        let score = state.score;
+        state.apply(mv);
+        steps.push(Step {
+            reward: state.score.saturating_sub(score) as f64,
+            lookups: network.lookups(&after),
+            over: after.is_over(),
+        });
+    }
+    steps
+}
+
+/// Updates the values of the afterstates of a game towards their λ-returns,
+/// computed backwards from the end of the game.
+///
+/// The afterstates of a game share most of their weights, so the values used
+/// in the returns are looked up as the sweep goes rather than before it:
+/// returns built on stale values make the weights diverge.
+fn learn(network: &mut NTupleNetwork, steps: &[Step], config: &TdConfig) {
+    let value = |network: &NTupleNetwork, step: &Step| {
+        if step.over { 0.0 } else { network.value_of(&step.lookups) }
+    };
+
+    // the game may have been cut before its end, in which case the last
+    // afterstate is its own target
+    let mut target = match steps.last() {
+        Some(step) => value(network, step),
+        None => return
+    };
+    for t in (0..steps.len()).rev() {
+        if t + 1 < steps.len() {
+            target = steps[t + 1].reward +
+                     (1.0 - config.lambda) * value(network, &steps[t + 1]) +
+                     config.lambda * target;
+        }
+        if steps[t].over {
+            continue;
+        }
+        let delta = target - network.value_of(&steps[t].lookups);
+        network.update(&steps[t].lookups, config.alpha * delta);
+    }
+}
@@ -0,0 +1,341 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use super::{FEATURES, GameRecord, HeuristicAgent, Weights, game_seed,
+            play_game};
+use crate::game::{ATOMS_SYMBOLS, SpawnModel};
+use crate::invalid_data;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal};
+
+/// What the tuning maximises over the games played by an individual.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Objective {
+    /// Mean final score
+    Mean,
+    /// Given percentile (between `0` and `100`) of the final scores
+    Percentile(f64),
+}
+
+/// Parameters of the genetic algorithm tuning the heuristic weights.
+#[derive(Clone, Debug)]
+pub struct TuneConfig {
+    pub population: usize,
+    pub generations: usize,
+    pub games: usize,
+    pub elites: usize,
+    pub tournament: usize,
+    pub sigma: f64,
+    pub seed: u64,
+    pub objective: Objective,
+    pub threads: usize,
+    pub spawn: SpawnModel,
+    pub out_dir: PathBuf,
+}
+
+/// A set of weights, and how well it did.
+#[derive(Clone, Debug)]
+pub struct Individual {
+    pub weights: Weights,
+    pub fitness: f64,
+}
+
+/// Population of a generation, as saved in checkpoints.
+#[derive(Clone, Debug)]
+pub struct Generation {
+    pub index: usize,
+    pub individuals: Vec<Individual>,
+}
+
+impl Default for TuneConfig {
+    fn default() -> Self {
+        Self {
+            population: 24,
+            generations: 50,
+            games: 32,
+            elites: 2,
+            tournament: 3,
+            sigma: 0.3,
+            seed: 0,
+            objective: Objective::Mean,
+            threads: thread::available_parallelism()
+                            .map(|n| n.get()).unwrap_or(1),
+            spawn: SpawnModel::default(),
+            out_dir: PathBuf::from("tune"),
+        }
+    }
+}
+
+impl Objective {
+    /// Parses `mean` or `p<percentile>`, such as `p90`
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "mean" {
+            return Some(Objective::Mean);
+        }
+        let p = name.strip_prefix('p')?.parse::<f64>().ok()?;
+        if (0.0..=100.0).contains(&p) { Some(Objective::Percentile(p)) }
+        else                          { None }
+    }
+
+    /// Value of the objective over some played games
+    pub fn measure(&self, games: &[GameRecord]) -> f64 {
+        if games.is_empty() {
+            return 0.0;
+        }
+        let mut scores: Vec<f64> = games.iter().map(|g| g.score as f64)
+                                                .collect();
+        match *self {
+            Objective::Mean => scores.iter().sum::<f64>() / scores.len() as f64,
+            Objective::Percentile(p) => {
+                scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let i = (p / 100.0 * (scores.len() - 1) as f64).round();
+                scores[i as usize]
+            }
+        }
+    }
+}
+
+/// Tunes the heuristic weights, starting from `init` or from the last
+/// checkpoint found in `config.out_dir` when `resume` is raised.
+///
+/// Each generation is saved in `out_dir/gen-XXXX.txt`, and its best weights
+/// in `out_dir/best.weights`.
+///
+/// Returns: the best weights of the last generation evaluated, those of the
+/// checkpoint when resuming a run that is already over
+pub fn tune(config: &TuneConfig, init: &Weights, resume: bool)
+                                                    -> io::Result<Weights> {
+    fs::create_dir_all(&config.out_dir)?;
+
+    let checkpoint = if resume {
+        last_checkpoint(&config.out_dir)?
+    } else {
+        None
+    };
+    let (mut generation, mut best) = match checkpoint {
+        Some(path) => {
+            let g = Generation::load(&path)?;
+            println!("resuming from {}", path.display());
+            (next_generation(config, &g), g.best().weights.clone())
+        },
+        None => (first_generation(config, init), init.clone())
+    };
+
+    while generation.index < config.generations {
+        let (top, length) = evaluate(config, &mut generation);
+        generation.individuals.sort_by(|a, b| b.fitness
+                                               .partial_cmp(&a.fitness)
+                                               .unwrap());
+        best = generation.individuals[0].weights.clone();
+        generation.save(config.out_dir.join(
+            format!("gen-{:04}.txt", generation.index)))?;
+        best.save(config.out_dir.join("best.weights"))?;
+
+        let n = generation.individuals.len() as f64;
+        let mean = generation.individuals.iter().map(|i| i.fitness)
+                                                .sum::<f64>() / n;
+        println!("generation {}: best {:.1}, mean {:.1}, top atom {}, \
+                  {:.0} moves per game", generation.index,
+                 generation.individuals[0].fitness, mean,
+                 ATOMS_SYMBOLS[top as usize], length);
+        generation = next_generation(config, &generation);
+    }
+    Ok(best)
+}
+
+/// Generator of the genetic operators for the generation following
+/// `index`, so that resuming gives the same results.
+fn operators_rng(config: &TuneConfig, index: usize) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(game_seed(config.seed ^ 0x7475_6e65,
+                                        index as u64))
+}
+
+/// Builds the first generation: `init` and mutations of it
+fn first_generation(config: &TuneConfig, init: &Weights) -> Generation {
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut individuals = vec![Individual { weights: init.clone(),
+                                            fitness: 0.0 }];
+    while individuals.len() < config.population {
+        individuals.push(Individual {
+            weights: mutate(init, config.sigma, &mut rng),
+            fitness: 0.0,
+        });
+    }
+    Generation { index: 0, individuals }
+}
+
+/// Builds the generation following `g`, whose fitnesses are known: the elites
+/// are kept, the others are children of parents selected by tournament
+fn next_generation(config: &TuneConfig, g: &Generation) -> Generation {
+    let mut rng = operators_rng(config, g.index);
+    let mut sorted = g.individuals.clone();
+    sorted.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+
+    let mut individuals: Vec<Individual> = sorted.iter()
+                                                 .take(config.elites)
+                                                 .cloned()
+                                                 .collect();
+    // mutations get smaller as generations go
+    let sigma = config.sigma * 0.97_f64.powi(g.index as i32);
+    while individuals.len() < config.population {
+        let a = tournament(&sorted, config.tournament, &mut rng);
+        let b = tournament(&sorted, config.tournament, &mut rng);
+        let child = crossover(&a.weights, &b.weights, &mut rng);
+        individuals.push(Individual {
+            weights: mutate(&child, sigma, &mut rng),
+            fitness: 0.0,
+        });
+    }
+    Generation { index: g.index + 1, individuals }
+}
+
+/// Best of `size` individuals picked at random
+fn tournament<'a, R: Rng>(individuals: &'a [Individual], size: usize,
+                          rng: &mut R) -> &'a Individual {
+    let mut best = &individuals[rng.gen_range(0..individuals.len())];
+    for _ in 1..size {
+        let other = &individuals[rng.gen_range(0..individuals.len())];
+        if other.fitness > best.fitness {
+            best = other;
+        }
+    }
+    best
+}
+
+/// Blend of two sets of weights, with a random proportion for each weight
+fn crossover<R: Rng>(a: &Weights, b: &Weights, rng: &mut R) -> Weights {
+    let mut child = a.clone();
+    for i in 0..FEATURES {
+        let alpha = rng.gen::<f64>();
+        child.values[i] = alpha * a.values[i] + (1.0 - alpha) * b.values[i];
+    }
+    child
+}
+
+/// Adds a gaussian noise to each weight, proportional to its magnitude
+fn mutate<R: Rng>(w: &Weights, sigma: f64, rng: &mut R) -> Weights {
+    let normal = Normal::new(0.0, sigma).unwrap();
+    let mut mutated = w.clone();
+    for v in mutated.values.iter_mut() {
+        *v += normal.sample(rng) * v.abs().max(1.0);
+    }
+    mutated
+}
+
+/// Computes the fitness of every individual of `g`, all of them playing the
+/// same games, split between `config.threads` threads.
+///
+/// Returns: the highest atom reached, and the mean length of the games
+fn evaluate(config: &TuneConfig, g: &mut Generation) -> (u8, f64) {
+    let seeds: Vec<u64> = (0..config.games as u64)
+        .map(|i| game_seed(game_seed(config.seed, g.index as u64), i))
+        .collect();
+    let threads = config.threads.max(1);
+    let chunk = g.individuals.len().div_ceil(threads);
+
+    let records: Vec<Vec<GameRecord>> = thread::scope(|scope| {
+        let handles: Vec<_> = g.individuals.chunks_mut(chunk.max(1))
+                               .map(|individuals| {
+            let seeds = &seeds;
+            scope.spawn(move || {
+                let mut records = Vec::new();
+                for individual in individuals {
+                    let mut agent = HeuristicAgent::new(
+                        individual.weights.clone());
+                    let games: Vec<GameRecord> = seeds.iter()
+                        .map(|s| play_game(&mut agent, *s, &config.spawn))
+                        .collect();
+                    individual.fitness = config.objective.measure(&games);
+                    records.extend(games);
+                }
+                records
+            })
+        }).collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let games: Vec<&GameRecord> = records.iter().flatten().collect();
+    let top = games.iter().map(|g| g.best).max().unwrap_or(0);
+    let length = games.iter().map(|g| g.moves as f64).sum::<f64>()
+                 / games.len().max(1) as f64;
+    (top, length)
+}
+
+/// Path of the checkpoint of the latest generation in `dir`, if any
+fn last_checkpoint(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut last = None;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if name.starts_with("gen-") && name.ends_with(".txt") &&
+           last.as_ref().is_none_or(|l: &PathBuf| path > *l) {
+            last = Some(path);
+        }
+    }
+    Ok(last)
+}
+
+impl Generation {
+    /// The individual of highest fitness, the first one if several are
+    pub fn best(&self) -> &Individual {
+        self.individuals.iter()
+            .min_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap())
+            .expect("empty generation")
+    }
+
+    /// Writes the generation: a `generation <index>` line, then one
+    /// `<fitness> <weights...>` line per individual
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "# iatomas tune checkpoint")?;
+        writeln!(file, "generation {}", self.index)?;
+        for individual in &self.individuals {
+            write!(file, "{}", individual.fitness)?;
+            for w in individual.weights.values.iter() {
+                write!(file, " {}", w)?;
+            }
+            writeln!(file)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a generation written by `Generation::save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let bad = || invalid_data("malformed checkpoint");
+        let mut index = None;
+        let mut individuals = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(i) = line.strip_prefix("generation ") {
+                index = Some(i.trim().parse::<usize>().map_err(|_| bad())?);
+                continue;
+            }
+            let numbers = line.split_whitespace()
+                              .map(|w| w.parse::<f64>())
+                              .collect::<Result<Vec<f64>, _>>()
+                              .map_err(|_| bad())?;
+            if numbers.len() != FEATURES + 1 {
+                return Err(bad());
+            }
+            let mut weights = Weights::default();
+            weights.values.copy_from_slice(&numbers[1..]);
+            individuals.push(Individual {
+                weights,
+                fitness: numbers[0]
+            });
+        }
+        if individuals.is_empty() {
+            return Err(bad());
+        }
+        Ok(Generation { index: index.ok_or_else(bad)?,
+                        individuals })
+    }
+}
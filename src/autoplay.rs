@@ -0,0 +1,181 @@
+use crate::agent::{self, Agent, Hinter, MAX_GAME_MOVES};
+use crate::game::{GameState, Move, SpawnModel};
+
+/// Seconds between two moves at speed 1, once the previous one is shown
+pub const MOVE_DELAY: f32 = 0.3;
+/// Seconds a finished game stays on screen at speed 1
+pub const RESTART_DELAY: f32 = 2.0;
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 16.0;
+
+/// What the player can ask of the autoplay, whatever the keys of the front
+/// end
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    /// Pauses or resumes
+    Pause,
+    /// Plays a single move while paused
+    Step,
+    /// Doubles the speed
+    Faster,
+    /// Halves the speed
+    Slower,
+}
+
+/// What the front end has to do to the game it shows
+#[derive(Clone, Debug)]
+pub enum Turn {
+    Play(Move),
+    /// Shows the next game, which starts from this state
+    Restart(Box<GameState>),
+}
+
+/// Lets an agent play games one after the other, in the window or in the
+/// terminal: the front end shows the game, and asks what to do next.
+///
+/// The agent thinks on a background thread (see `Hinter`).
+pub struct Autoplay {
+    hinter: Hinter,
+    spawn: SpawnModel,
+    seed: u64,
+    /// Number of the game being played
+    game: u64,
+    /// Moves played in the game
+    moves: u32,
+    /// Version of the game the agent is thinking about, if any
+    thinking: Option<u64>,
+    /// Whether the agent found no move to play in the game, which then ends
+    stuck: bool,
+    /// Seconds since the last move, or since the end of the game
+    idle: f32,
+    /// Whether the score of the game over is counted
+    counted: bool,
+    paused: bool,
+    /// Whether a single move is to be played while paused
+    step: bool,
+    speed: f32,
+    scores: Vec<u32>,
+}
+
+impl Autoplay {
+    /// Lets `agent` play games with `spawn`, their seeds derived from `seed`
+    pub fn new(agent: Box<dyn Agent>, spawn: SpawnModel, seed: u64) -> Self {
+        Self {
+            hinter: Hinter::new(agent),
+            spawn,
+            seed,
+            game: 0,
+            moves: 0,
+            thinking: None,
+            stuck: false,
+            idle: 0.0,
+            counted: false,
+            paused: false,
+            step: false,
+            speed: 1.0,
+            scores: Vec::new(),
+        }
+    }
+
+    /// The state the game being played starts from
+    pub fn start(&self) -> GameState {
+        GameState::start_game_seeded(self.seed(), self.spawn.clone())
+    }
+
+    /// Seed of the game being played
+    pub fn seed(&self) -> u64 {
+        agent::game_seed(self.seed, self.game)
+    }
+
+    /// Speed of the game, as a factor of the time elapsed, for the front
+    /// ends that animate the moves
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Does what the player asks
+    pub fn control(&mut self, control: Control) {
+        match control {
+            Control::Pause => self.paused = !self.paused,
+            Control::Step => self.step = self.paused,
+            Control::Faster => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            Control::Slower => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+        }
+    }
+
+    /// Moves the games `seconds` forward, `state` being the game shown at
+    /// `version` (see `Board::version`): tells the move of the agent once it
+    /// is found, and the next game a while after the end of one. A game in
+    /// which the agent finds no move ends there. Front ends that animate the
+    /// moves only call it once the last one is shown.
+    pub fn update(&mut self, state: &GameState, version: u64, seconds: f32)
+                                                            -> Option<Turn> {
+        self.idle += seconds * self.speed;
+
+        if state.is_over() || self.moves >= MAX_GAME_MOVES || self.stuck {
+            if !self.counted {
+                if self.thinking.take().is_some() {
+                    self.hinter.cancel();
+                }
+                self.scores.push(state.score);
+                self.counted = true;
+                self.idle = 0.0;
+            }
+            if self.idle >= RESTART_DELAY && !self.paused {
+                self.game += 1;
+                self.moves = 0;
+                self.stuck = false;
+                self.counted = false;
+                return Some(Turn::Restart(Box::new(self.start())));
+            }
+            return None;
+        }
+
+        match self.thinking {
+            None => {
+                let ready = if self.paused { self.step }
+                            else           { self.idle >= MOVE_DELAY };
+                if ready {
+                    self.hinter.request(state);
+                    self.thinking = Some(version);
+                }
+                None
+            },
+            Some(thought) if thought != version => {
+                self.hinter.cancel();
+                self.thinking = None;
+                None
+            },
+            Some(_) => {
+                let hint = self.hinter.poll()?;
+                self.thinking = None;
+                self.idle = 0.0;
+                self.step = false;
+                match hint.moves.first() {
+                    Some((mv, _)) => {
+                        self.moves += 1;
+                        Some(Turn::Play(*mv))
+                    },
+                    None => {
+                        self.stuck = true;
+                        None
+                    },
+                }
+            }
+        }
+    }
+
+    /// Lines describing the games so far
+    pub fn status(&self) -> Vec<String> {
+        let paused = if self.paused { "  paused" } else { "" };
+        let mut lines = vec![format!("game {}  x{}{}", self.game + 1,
+                                     self.speed, paused)];
+        if let Some(best) = self.scores.iter().max() {
+            let mean = self.scores.iter().map(|s| *s as f64).sum::<f64>()
+                       / self.scores.len() as f64;
+            lines.push(format!("{} games: mean {:.0}, best {}",
+                               self.scores.len(), mean, best));
+        }
+        lines
+    }
+}
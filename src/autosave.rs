@@ -0,0 +1,199 @@
+//! The game in progress, saved in the data directory (see
+//! `history::data_dir`) while it is played so that it can be resumed once the
+//! window has been closed.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{AtomType, GameState, Move, SpawnModel, atom_symbol,
+                  is_known, move_notation, parse_atom, parse_move,
+                  parse_position, position};
+use crate::history::write_atomically;
+use crate::invalid_data;
+
+/// Version of the format of the save files, written on their first line.
+/// Files of another version are not loaded.
+pub const SAVE_VERSION: u32 = 1;
+
+const SAVE_FILE: &str = "autosave.txt";
+const MAGIC: &str = "iatomas-save";
+
+/// A game in progress, as saved.
+///
+/// Contains:
+///
+/// - `state`: its position, with the generator of its next atoms
+///
+/// - `spawn`: the spawn model it is played with, as given on the command line
+///   (see `SpawnModel::parse`)
+///
+/// - `seed`: the seed its generator was created with
+///
+/// - `edited`: whether its position has been changed by other means than
+///   moves, in which case `moves` do not lead to it
+///
+/// - `moves`: the moves played since it started, or since it was last edited
+#[derive(Clone, Debug)]
+pub struct SavedGame {
+    pub state: GameState,
+    pub spawn: String,
+    pub seed: u64,
+    pub edited: bool,
+    pub moves: Vec<Move>,
+}
+
+/// FNV-1a hash of `text`, to tell a save file that has been damaged
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl SavedGame {
+    /// The content of the save file: one `<name> <value>` line per field,
+    /// between a line giving the version and one giving the checksum of the
+    /// others, e.g.
+    ///
+    /// ```text
+    /// iatomas-save 1
+    /// spawn simple
+    /// seed 42
+    /// word 96
+    /// edited no
+    /// best Li
+    /// position H,He,Li + 12 7
+    /// moves place:0 place:2 pick:1
+    /// checksum 5f0c2b3e9d41a8c7
+    /// ```
+    ///
+    /// `word` is the position of the generator in its stream.
+    pub fn to_text(&self) -> String {
+        let moves: Vec<String> = self.moves.iter()
+                                           .map(|&mv| move_notation(mv))
+                                           .collect();
+        let text = format!("{} {}\nspawn {}\nseed {}\nword {}\nedited {}\n\
+                            best {}\nposition {}\nmoves {}\n",
+                           MAGIC, SAVE_VERSION, self.spawn, self.seed,
+                           self.state.rng.get_word_pos(),
+                           if self.edited { "yes" } else { "no" },
+                           atom_symbol(&AtomType::Atom(self.state.best)),
+                           position(&self.state),
+                           moves.join(" "));
+        format!("{}checksum {:016x}\n", text, checksum(&text))
+    }
+
+    /// Reads and checks a save written by `to_text`.
+    ///
+    /// Unless the position has been edited, the moves are replayed from the
+    /// start of the game, and must lead to the position and generator
+    /// saved.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let version = text.lines().next()
+            .and_then(|line| line.strip_prefix(MAGIC))
+            .ok_or_else(|| invalid_data("not a saved game"))?;
+        match version.trim().parse::<u32>() {
+            Ok(SAVE_VERSION) => {},
+            Ok(v) => return Err(invalid_data(format!(
+                "saved by version {} of the format, not {}", v,
+                SAVE_VERSION))),
+            Err(_) => return Err(invalid_data(format!(
+                "bad version `{}`", version.trim())))
+        }
+        let end = text.rfind("\nchecksum ")
+                      .ok_or_else(|| invalid_data("no checksum"))? + 1;
+        let (body, sum) = text.split_at(end);
+        let sum = sum["checksum ".len()..].trim();
+        if u64::from_str_radix(sum, 16).ok() != Some(checksum(body)) {
+            return Err(invalid_data("wrong checksum, the file is damaged"));
+        }
+
+        let (mut spawn, mut seed, mut word, mut edited, mut best, mut pos,
+             mut moves) = (None, None, None, None, None, None, None);
+        for line in body.lines().skip(1) {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            let bad = || invalid_data(format!("bad {} `{}`", name, value));
+            match name {
+                "spawn" => spawn = Some(value.to_string()),
+                "seed" => seed = Some(value.parse::<u64>()
+                                           .map_err(|_| bad())?),
+                "word" => word = Some(value.parse::<u128>()
+                                           .map_err(|_| bad())?),
+                "edited" => edited = Some(match value {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(bad())
+                }),
+                "best" => best = Some(match parse_atom(value) {
+                    Some(AtomType::Atom(z)) => z,
+                    _ => return Err(bad())
+                }),
+                "position" => pos = Some(value.to_string()),
+                "moves" => moves = Some(value.split_whitespace().map(|m| {
+                    parse_move(m).ok_or_else(bad)
+                }).collect::<io::Result<Vec<Move>>>()?),
+                _ => return Err(invalid_data(format!("unknown field `{}`",
+                                                     name)))
+            }
+        }
+        let missing = |field: &str| invalid_data(format!("no {}", field));
+        let spawn = spawn.ok_or_else(|| missing("spawn"))?;
+        let seed = seed.ok_or_else(|| missing("seed"))?;
+        let word = word.ok_or_else(|| missing("word"))?;
+        let edited = edited.ok_or_else(|| missing("edited"))?;
+        let best = best.ok_or_else(|| missing("best"))?;
+        let pos = pos.ok_or_else(|| missing("position"))?;
+        let moves = moves.ok_or_else(|| missing("moves"))?;
+
+        let model = SpawnModel::parse(&spawn).map_err(invalid_data)?;
+        let mut state = parse_position(&pos, seed, model.clone())
+                            .map_err(invalid_data)?;
+        state.rng.set_word_pos(word);
+        state.best = best;
+        if !is_known(&AtomType::Atom(best)) ||
+           !state.atoms.iter().all(|a| is_known(&a.t)) ||
+           !is_known(&state.incoming.t) {
+            return Err(invalid_data("unknown element in the position"));
+        }
+        if !edited {
+            let mut replay = GameState::start_game_seeded(seed, model);
+            for &mv in &moves {
+                if !replay.legal_moves().contains(&mv) {
+                    return Err(invalid_data(format!("illegal move {}",
+                                                    move_notation(mv))));
+                }
+                replay.apply(mv);
+            }
+            if position(&replay) != pos || replay.best != best ||
+               replay.rng.get_word_pos() != word {
+                return Err(invalid_data("the moves do not lead to the \
+                                         position"));
+            }
+        }
+        Ok(Self { state, spawn, seed, edited, moves })
+    }
+
+    /// Reads the game saved in `dir`, if any
+    pub fn load<P: AsRef<Path>>(dir: P) -> io::Result<Option<Self>> {
+        match fs::read_to_string(dir.as_ref().join(SAVE_FILE)) {
+            Ok(text) => Self::parse(&text).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Writes the game in `dir`, creating it if needed, in place of the one
+    /// saved before
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+        write_atomically(&dir.as_ref().join(SAVE_FILE), &self.to_text())
+    }
+}
+
+/// Removes the game saved in `dir`, if any, e.g. once it is over
+pub fn remove<P: AsRef<Path>>(dir: P) -> io::Result<()> {
+    match fs::remove_file(dir.as_ref().join(SAVE_FILE)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(())
+    }
+}
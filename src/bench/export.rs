@@ -0,0 +1,277 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::AgentRun;
+use crate::game::{ATOMS_COLORS, AtomType, atom_symbol};
+
+/// Colours of the agents in the charts comparing them, in order
+const SERIES_COLORS: [&str; 6] = [
+    "#1f77b4", "#d62728", "#2ca02c", "#ff7f0e", "#9467bd", "#8c564b",
+];
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 400.0;
+/// Margins around the plot area: left, right, top, bottom
+const MARGINS: (f64, f64, f64, f64) = (60.0, 20.0, 40.0, 50.0);
+
+/// Writes one row per game of `runs`, with a header, as CSV: seed, agent,
+/// score, best atom (see `game::atom_symbol`), moves and time in
+/// milliseconds.
+pub fn write_csv<W: Write>(runs: &[AgentRun], mut out: W) -> io::Result<()> {
+    writeln!(out, "seed,agent,score,best,moves,time_ms")?;
+    for run in runs {
+        let agent = csv_field(&run.agent);
+        for game in &run.games {
+            writeln!(out, "{},{},{},{},{},{:.3}", game.seed, agent, game.score,
+                     atom_symbol(&AtomType::Atom(game.best)), game.moves,
+                     game.time.as_secs_f64() * 1000.0)?;
+        }
+    }
+    out.flush()
+}
+
+/// Quotes a CSV field if needed
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Escapes text put in an SVG document
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// A round step for about five ticks between 0 and `max`
+fn tick_step(max: f64) -> f64 {
+    let raw = (max / 5.0).max(f64::MIN_POSITIVE);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter().map(|m| m * magnitude)
+                         .find(|s| *s >= raw)
+                         .unwrap_or(10.0 * magnitude)
+}
+
+/// An SVG document holding a chart, with axes from 0 to `x_max` and 0 to
+/// `y_max`.
+struct Chart {
+    svg: String,
+    x_max: f64,
+    y_max: f64,
+}
+
+impl Chart {
+    /// Starts a chart with its title, axes, axis labels and the ticks of the
+    /// y axis
+    fn new(title: &str, x_label: &str, y_label: &str, x_max: f64, y_max: f64)
+                                                                    -> Self {
+        let x_max = if x_max > 0.0 { x_max } else { 1.0 };
+        let y_max = if y_max > 0.0 { y_max } else { 1.0 };
+        let mut chart = Self { svg: String::new(), x_max, y_max };
+        let (left, right, top, bottom) = MARGINS;
+        let svg = &mut chart.svg;
+        let _ = writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+                               width=\"{w}\" height=\"{h}\" \
+                               viewBox=\"0 0 {w} {h}\" \
+                               font-family=\"sans-serif\" font-size=\"12\">",
+                         w = CHART_WIDTH, h = CHART_HEIGHT);
+        let _ = writeln!(svg, "<rect width=\"100%\" height=\"100%\" \
+                               fill=\"white\"/>");
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"24\" text-anchor=\"middle\" \
+                               font-size=\"16\">{}</text>",
+                         CHART_WIDTH / 2.0, xml_escape(title));
+        let _ = writeln!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">\
+                               {}</text>",
+                         left + (CHART_WIDTH - left - right) / 2.0,
+                         CHART_HEIGHT - 12.0, xml_escape(x_label));
+        let _ = writeln!(svg, "<text x=\"16\" y=\"{y}\" text-anchor=\"middle\" \
+                               transform=\"rotate(-90 16 {y})\">{}</text>",
+                         xml_escape(y_label),
+                         y = top + (CHART_HEIGHT - top - bottom) / 2.0);
+
+        let (x0, y0) = chart.point(0.0, 0.0);
+        let (x1, y1) = chart.point(x_max, y_max);
+        let mut axes = format!("<path d=\"M {} {} V {} H {}\" fill=\"none\" \
+                                stroke=\"black\"/>\n", x0, y1, y0, x1);
+        let step = tick_step(y_max);
+        let mut y = 0.0;
+        while y <= y_max + step * 1e-9 {
+            let (px, py) = chart.point(0.0, y);
+            let _ = writeln!(axes, "<path d=\"M {px:.2} {py:.2} h -5\" \
+                                    stroke=\"black\"/><text x=\"{:.2}\" \
+                                    y=\"{:.2}\" text-anchor=\"end\">{}</text>",
+                             px - 8.0, py + 4.0, (y * 1e6).round() / 1e6,
+                             px = px, py = py);
+            y += step;
+        }
+        chart.svg.push_str(&axes);
+        chart
+    }
+
+    /// Adds round ticks to the x axis
+    fn x_ticks(&mut self) {
+        let step = tick_step(self.x_max);
+        let mut x = 0.0;
+        while x <= self.x_max + step * 1e-9 {
+            let (px, py) = self.point(x, 0.0);
+            let _ = writeln!(self.svg, "<path d=\"M {px:.2} {py:.2} v 5\" \
+                                        stroke=\"black\"/><text x=\"{px:.2}\" \
+                                        y=\"{:.2}\" text-anchor=\"middle\">\
+                                        {}</text>",
+                             py + 18.0, (x * 1e6).round() / 1e6,
+                             px = px, py = py);
+            x += step;
+        }
+    }
+
+    /// Position in the document of the point `(x, y)` of the chart
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        let (left, right, top, bottom) = MARGINS;
+        let width = CHART_WIDTH - left - right;
+        let height = CHART_HEIGHT - top - bottom;
+        (left + x / self.x_max * width,
+         CHART_HEIGHT - bottom - y / self.y_max * height)
+    }
+
+    /// Draws the rectangle from `(x0, 0)` to `(x1, y)`
+    fn bar(&mut self, x0: f64, x1: f64, y: f64, style: &str) {
+        let (px0, py) = self.point(x0, y);
+        let (px1, base) = self.point(x1, 0.0);
+        let _ = writeln!(self.svg, "<rect x=\"{:.2}\" y=\"{:.2}\" \
+                                    width=\"{:.2}\" height=\"{:.2}\" {}/>",
+                         px0, py, px1 - px0, base - py, style);
+    }
+
+    /// Writes the names of the agents, in their colours, at the top right
+    fn legend(&mut self, runs: &[AgentRun]) {
+        let (_, right, top, _) = MARGINS;
+        for (i, run) in runs.iter().enumerate() {
+            let y = top + 14.0 + 16.0 * i as f64;
+            let _ = writeln!(self.svg, "<text x=\"{}\" y=\"{}\" \
+                                        text-anchor=\"end\" fill=\"{}\">\
+                                        {}</text>",
+                             CHART_WIDTH - right - 8.0, y, series_color(i),
+                             xml_escape(&run.agent));
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.svg.push_str("</svg>\n");
+        self.svg
+    }
+}
+
+fn series_color(i: usize) -> &'static str {
+    SERIES_COLORS[i % SERIES_COLORS.len()]
+}
+
+/// Histogram of the scores of every agent, in `bins` bins, as fractions of
+/// their games so that series of different lengths compare.
+pub fn score_histogram(runs: &[AgentRun], bins: usize) -> String {
+    let bins = bins.max(1);
+    let max = runs.iter().flat_map(|r| r.games.iter().map(|g| g.score))
+                  .max().unwrap_or(0) as f64;
+    let width = (max / bins as f64).max(1.0);
+    let fractions: Vec<Vec<f64>> = runs.iter().map(|run| {
+        let mut counts = vec![0.0; bins];
+        for game in &run.games {
+            let b = ((game.score as f64 / width) as usize).min(bins - 1);
+            counts[b] += 1.0;
+        }
+        let n = run.games.len().max(1) as f64;
+        counts.iter().map(|c| c / n).collect()
+    }).collect();
+    let y_max = fractions.iter().flatten().cloned().fold(0.0, f64::max);
+
+    let mut chart = Chart::new("Scores", "score", "fraction of games",
+                               width * bins as f64, y_max);
+    chart.x_ticks();
+    for (i, series) in fractions.iter().enumerate() {
+        let style = format!("fill=\"{}\" fill-opacity=\"0.45\" \
+                             stroke=\"{}\"", series_color(i), series_color(i));
+        for (b, f) in series.iter().enumerate() {
+            if *f > 0.0 {
+                chart.bar(b as f64 * width, (b + 1) as f64 * width, *f,
+                          &style);
+            }
+        }
+    }
+    chart.legend(runs);
+    chart.finish()
+}
+
+/// Survival curves: the fraction of the games of every agent still going on
+/// after `t` moves, against `t`.
+pub fn survival_curves(runs: &[AgentRun]) -> String {
+    let max = runs.iter().flat_map(|r| r.games.iter().map(|g| g.moves))
+                  .max().unwrap_or(0) as f64;
+    let mut chart = Chart::new("Survival", "moves", "fraction of games alive",
+                               max, 1.0);
+    chart.x_ticks();
+    for (i, run) in runs.iter().enumerate() {
+        let mut lengths: Vec<u32> = run.games.iter().map(|g| g.moves)
+                                       .collect();
+        lengths.sort_unstable();
+        let n = lengths.len().max(1) as f64;
+        let (x, y) = chart.point(0.0, 1.0);
+        let mut path = format!("M {:.2} {:.2}", x, y);
+        for (k, length) in lengths.iter().enumerate() {
+            let (x, _) = chart.point(*length as f64, 0.0);
+            let (_, y) = chart.point(0.0, (lengths.len() - k - 1) as f64 / n);
+            let _ = write!(path, " H {:.2} V {:.2}", x, y);
+        }
+        let _ = writeln!(chart.svg, "<path d=\"{}\" fill=\"none\" \
+                                     stroke=\"{}\" stroke-width=\"2\"/>",
+                         path, series_color(i));
+    }
+    chart.legend(runs);
+    chart.finish()
+}
+
+/// Bar chart of the best atoms reached in the games of `run`, as fractions
+/// of its games, each bar in the colour of its atom.
+pub fn best_atoms_chart(run: &AgentRun) -> String {
+    let lowest = run.games.iter().map(|g| g.best).min().unwrap_or(0);
+    let highest = run.games.iter().map(|g| g.best).max().unwrap_or(0);
+    let bars = (highest - lowest) as usize + 1;
+    let mut counts = vec![0.0; bars];
+    for game in &run.games {
+        counts[(game.best - lowest) as usize] += 1.0;
+    }
+    let n = run.games.len().max(1) as f64;
+    let y_max = counts.iter().cloned().fold(0.0, f64::max) / n;
+
+    let title = format!("Best atoms: {}", run.agent);
+    let mut chart = Chart::new(&title, "best atom", "fraction of games",
+                               bars as f64, y_max);
+    for (k, count) in counts.iter().enumerate() {
+        let z = lowest as usize + k;
+        let color = ATOMS_COLORS.get(z).cloned().unwrap_or("#808080");
+        let style = format!("fill=\"{}\" stroke=\"black\"", color);
+        chart.bar(k as f64 + 0.1, k as f64 + 0.9, count / n, &style);
+        let (x, y) = chart.point(k as f64 + 0.5, 0.0);
+        let _ = writeln!(chart.svg, "<text x=\"{:.2}\" y=\"{}\" \
+                                     text-anchor=\"middle\">{}</text>",
+                         x, y + 18.0,
+                         xml_escape(&atom_symbol(&AtomType::Atom(z as u8))));
+    }
+    chart.finish()
+}
+
+/// Writes the charts of `runs` in `dir`: `scores.svg`, `survival.svg` and
+/// `best-atoms-<i>.svg` for the `i`-th agent.
+pub fn save_charts<P: AsRef<Path>>(runs: &[AgentRun], dir: P)
+                                                        -> io::Result<()> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("scores.svg"), score_histogram(runs, 20))?;
+    fs::write(dir.join("survival.svg"), survival_curves(runs))?;
+    for (i, run) in runs.iter().enumerate() {
+        fs::write(dir.join(format!("best-atoms-{}.svg", i)),
+                  best_atoms_chart(run))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+mod export;
+mod stats;
+
+pub use export::*;
+pub use stats::*;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::agent::{self, game_seed};
+use crate::game::SpawnModel;
+
+/// Parameters of a benchmark.
+#[derive(Clone, Debug)]
+pub struct BenchConfig {
+    /// Games played by every agent
+    pub games: u64,
+    /// The `i`-th game of every agent is seeded by `game_seed(seed, i)`
+    pub seed: u64,
+    pub spawn: SpawnModel,
+    pub threads: usize,
+    /// Resamples of the bootstrap confidence intervals
+    pub resamples: usize,
+    /// Level of the confidence intervals, in `]0, 1[`
+    pub confidence: f64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            games: 1000,
+            seed: 0,
+            spawn: SpawnModel::default(),
+            threads: thread::available_parallelism()
+                            .map(|n| n.get()).unwrap_or(1),
+            resamples: 10_000,
+            confidence: 0.95,
+        }
+    }
+}
+
+/// A game played in a benchmark.
+#[derive(Clone, Debug)]
+pub struct BenchGame {
+    /// Index of the game in the series, the same for every agent
+    pub index: u64,
+    pub seed: u64,
+    pub score: u32,
+    pub best: u8,
+    pub moves: u32,
+    pub time: Duration,
+}
+
+/// The games played by an agent in a benchmark, ordered by index.
+#[derive(Clone, Debug)]
+pub struct AgentRun {
+    /// Spec of the agent (see `agent::from_spec`)
+    pub agent: String,
+    pub games: Vec<BenchGame>,
+    /// Wall-clock time of the whole series
+    pub elapsed: Duration,
+}
+
+/// Lets the agent of `spec` play `config.games` games, split between
+/// `config.threads` threads, each of them building its own agent.
+pub fn run_agent(spec: &str, config: &BenchConfig)
+                                                -> Result<AgentRun, String> {
+    // Checks the spec once, before starting the threads
+    agent::from_spec(spec, config.seed)?;
+
+    let next = AtomicU64::new(0);
+    let games = Mutex::new(Vec::new());
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for t in 0..config.threads.max(1) as u64 {
+            let (next, games) = (&next, &games);
+            scope.spawn(move || {
+                let mut player = agent::from_spec(spec, game_seed(!config.seed,
+                                                                  t))
+                                       .expect("the spec has been checked");
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+                    let seed = game_seed(config.seed, index);
+                    let begin = Instant::now();
+                    let record = agent::play_game(player.as_mut(), seed,
+                                                  &config.spawn);
+                    let game = BenchGame {
+                        index,
+                        seed,
+                        score: record.score,
+                        best: record.best,
+                        moves: record.moves,
+                        time: begin.elapsed(),
+                    };
+                    games.lock().unwrap().push(game);
+                }
+            });
+        }
+    });
+    let mut games = games.into_inner().unwrap();
+    games.sort_by_key(|g| g.index);
+    Ok(AgentRun {
+        agent: spec.to_string(),
+        games,
+        elapsed: start.elapsed(),
+    })
+}
@@ -0,0 +1,114 @@
+use super::{AgentRun, BenchConfig};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Statistics of the games of an agent.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub games: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p10: f64,
+    pub p90: f64,
+    pub max: f64,
+    pub mean_moves: f64,
+    /// Percentiles of the lengths of the games, in moves
+    pub median_moves: f64,
+    pub p10_moves: f64,
+    pub p90_moves: f64,
+    pub max_moves: f64,
+    pub games_per_second: f64,
+    /// Number of games per best atom reached, by increasing atom
+    pub best_atoms: Vec<(u8, usize)>,
+}
+
+/// Mean difference of score between two agents over the same seeds, with its
+/// bootstrap confidence interval.
+#[derive(Clone, Debug)]
+pub struct Difference {
+    pub mean: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// The `p`-th percentile (`p` in `[0, 100]`) of `sorted`, which must be
+/// sorted and not empty, taking the nearest value.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let i = (p / 100.0 * (sorted.len() - 1) as f64).round();
+    sorted[i as usize]
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() { 0.0 }
+    else                 { values.iter().sum::<f64>() / values.len() as f64 }
+}
+
+/// Statistics of the games of `run`.
+pub fn summarise(run: &AgentRun) -> Summary {
+    let mut scores: Vec<f64> = run.games.iter().map(|g| g.score as f64)
+                                  .collect();
+    scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut moves: Vec<f64> = run.games.iter().map(|g| g.moves as f64)
+                                 .collect();
+    moves.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut best_atoms: Vec<(u8, usize)> = Vec::new();
+    for game in &run.games {
+        match best_atoms.iter_mut().find(|(z, _)| *z == game.best) {
+            Some((_, n)) => *n += 1,
+            None => best_atoms.push((game.best, 1)),
+        }
+    }
+    best_atoms.sort();
+
+    let seconds = run.elapsed.as_secs_f64();
+    let stat = |values: &[f64], p: f64| {
+        if values.is_empty() { 0.0 } else { percentile(values, p) }
+    };
+    Summary {
+        games: scores.len(),
+        mean: mean(&scores),
+        median: stat(&scores, 50.0),
+        p10: stat(&scores, 10.0),
+        p90: stat(&scores, 90.0),
+        max: stat(&scores, 100.0),
+        mean_moves: mean(&moves),
+        median_moves: stat(&moves, 50.0),
+        p10_moves: stat(&moves, 10.0),
+        p90_moves: stat(&moves, 90.0),
+        max_moves: stat(&moves, 100.0),
+        games_per_second: if seconds > 0.0 { scores.len() as f64 / seconds }
+                          else             { 0.0 },
+        best_atoms,
+    }
+}
+
+/// Difference of the scores of `b` and `a` over the games they both played
+/// with the same seeds. The confidence interval is the percentile bootstrap
+/// of `config.resamples` resamples of those pairs.
+pub fn paired_difference(a: &AgentRun, b: &AgentRun, config: &BenchConfig)
+                                                            -> Difference {
+    let diffs: Vec<f64> = a.games.iter().zip(&b.games)
+        .filter(|(ga, gb)| ga.seed == gb.seed)
+        .map(|(ga, gb)| gb.score as f64 - ga.score as f64)
+        .collect();
+    if diffs.is_empty() {
+        return Difference { mean: 0.0, low: 0.0, high: 0.0 };
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+    let mut means: Vec<f64> = (0..config.resamples.max(1)).map(|_| {
+        let total: f64 = (0..diffs.len())
+            .map(|_| diffs[rng.gen_range(0..diffs.len())])
+            .sum();
+        total / diffs.len() as f64
+    }).collect();
+    means.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    let alpha = (1.0 - config.confidence) / 2.0 * 100.0;
+    Difference {
+        mean: mean(&diffs),
+        low: percentile(&means, alpha),
+        high: percentile(&means, 100.0 - alpha),
+    }
+}
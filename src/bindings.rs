@@ -0,0 +1,139 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use iatomas::invalid_data;
+
+use sfml::window::Key;
+
+/// What the keys of the window do, outside of the editor and the autoplay
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Selects the previous gap, or atom to pick
+    Previous,
+    /// Selects the next gap, or atom to pick
+    Next,
+    /// Plays the move selected
+    Play,
+    /// Turns the atom taken with a Minus into a Plus
+    Convert,
+    Hint,
+    /// Starts or stops editing the position
+    Editor,
+    /// Shows or hides the high scores
+    Scores,
+    /// Starts a new game
+    NewGame,
+    /// Draws the window with the next theme
+    Theme,
+}
+
+/// Names of the actions in a bindings file
+const ACTIONS: [(&str, Action); 9] = [
+    ("previous", Action::Previous),
+    ("next", Action::Next),
+    ("play", Action::Play),
+    ("convert", Action::Convert),
+    ("hint", Action::Hint),
+    ("editor", Action::Editor),
+    ("scores", Action::Scores),
+    ("new", Action::NewGame),
+    ("theme", Action::Theme),
+];
+
+/// Names of the keys in a bindings file
+const KEYS: [(&str, Key); 63] = [
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D),
+    ("E", Key::E), ("F", Key::F), ("G", Key::G), ("H", Key::H),
+    ("I", Key::I), ("J", Key::J), ("K", Key::K), ("L", Key::L),
+    ("M", Key::M), ("N", Key::N), ("O", Key::O), ("P", Key::P),
+    ("Q", Key::Q), ("R", Key::R), ("S", Key::S), ("T", Key::T),
+    ("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X),
+    ("Y", Key::Y), ("Z", Key::Z),
+    ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3),
+    ("4", Key::Num4), ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7),
+    ("8", Key::Num8), ("9", Key::Num9),
+    ("Left", Key::Left), ("Right", Key::Right), ("Up", Key::Up),
+    ("Down", Key::Down), ("Space", Key::Space), ("Enter", Key::Return),
+    ("Backspace", Key::BackSpace), ("Tab", Key::Tab),
+    ("Escape", Key::Escape), ("Home", Key::Home), ("End", Key::End),
+    ("PageUp", Key::PageUp), ("PageDown", Key::PageDown),
+    ("Insert", Key::Insert), ("Delete", Key::Delete),
+    ("F1", Key::F1), ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4),
+    ("F5", Key::F5), ("F6", Key::F6), ("F7", Key::F7), ("F8", Key::F8),
+    ("F9", Key::F9), ("F10", Key::F10), ("F11", Key::F11), ("F12", Key::F12),
+];
+
+/// The keys bound to each action.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: Vec<(Key, Action)>,
+}
+
+impl Default for Bindings {
+    /// The arrows select, Enter or Space plays, C converts, H asks for a
+    /// hint, E opens the editor, Tab shows the high scores, N starts a new
+    /// game and T changes the theme
+    fn default() -> Self {
+        Self {
+            keys: vec![
+                (Key::Left, Action::Previous),
+                (Key::Right, Action::Next),
+                (Key::Return, Action::Play),
+                (Key::Space, Action::Play),
+                (Key::C, Action::Convert),
+                (Key::H, Action::Hint),
+                (Key::E, Action::Editor),
+                (Key::Tab, Action::Scores),
+                (Key::N, Action::NewGame),
+                (Key::T, Action::Theme),
+            ],
+        }
+    }
+}
+
+impl Bindings {
+    /// Reads bindings from a file holding one `<action> <key> [<key> ...]`
+    /// line per action, e.g. `play Enter Space`. Actions that are not given
+    /// keep their default keys, and lines starting with `#` are ignored.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a bindings file, see `Bindings::load`
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut bindings = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let action = ACTIONS.iter().find(|(n, _)| *n == name)
+                .map(|(_, a)| *a)
+                .ok_or_else(|| {
+                    invalid_data(format!("unknown action `{}`", name))
+                })?;
+            let keys = words.map(|word| {
+                KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(word))
+                    .map(|(_, k)| *k)
+                    .ok_or_else(|| {
+                        invalid_data(format!("unknown key `{}`", word))
+                    })
+            }).collect::<io::Result<Vec<Key>>>()?;
+            if keys.is_empty() {
+                return Err(invalid_data(format!("no key in `{}`", line)));
+            }
+            // The keys given are taken from the actions they were bound to
+            bindings.keys.retain(|(k, a)| *a != action && !keys.contains(k));
+            bindings.keys.extend(keys.into_iter().map(|k| (k, action)));
+        }
+        Ok(bindings)
+    }
+
+    /// The action `key` is bound to, if any
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.keys.iter().find(|(k, _)| *k == key).map(|(_, a)| *a)
+    }
+}
@@ -0,0 +1,460 @@
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use iatomas::agent::{self, MlpConfig, NTupleAgent, NTupleNetwork, Objective,
+                    TdConfig, TuneConfig, Weights};
+use iatomas::bench::{self, BenchConfig};
+use iatomas::history::{self, History};
+use iatomas::nn::PolicyValueNet;
+use iatomas::game::{AtomType, SPAWN_PARAM_NAMES, SpawnModel, SpawnParams,
+                    atom_symbol};
+use iatomas::protocol::{self, MatchConfig};
+use iatomas::spawn;
+
+/// Options given on the command line after the sub-command, as
+/// `--name value` pairs or `--flag`s.
+pub struct Options {
+    pairs: Vec<(String, Option<String>)>,
+}
+
+impl Options {
+    /// Collects the options from the arguments following the sub-command
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut pairs = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let name = args[i].strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument `{}`", args[i]))?;
+            let value = args.get(i + 1).filter(|v| !v.starts_with("--"));
+            if value.is_some() { i += 1; }
+            pairs.push((name.to_string(), value.cloned()));
+            i += 1;
+        }
+        Ok(Self { pairs })
+    }
+
+    /// Value of option `name`, if given
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().find(|(n, _)| n == name)
+                         .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Values of all the `--name` options given
+    pub fn all(&self, name: &str) -> Vec<&str> {
+        self.pairs.iter().filter(|(n, _)| n == name)
+                         .filter_map(|(_, v)| v.as_deref())
+                         .collect()
+    }
+
+    /// Returns `true` if `--name` is given
+    pub fn flag(&self, name: &str) -> bool {
+        self.pairs.iter().any(|(n, _)| n == name)
+    }
+
+    /// Parses the value of option `name`, or returns `default` if not given
+    pub fn parse_or<T: FromStr>(&self, name: &str, default: T)
+                                                    -> Result<T, String> {
+        match self.get(name) {
+            Some(v) => v.parse::<T>()
+                        .map_err(|_| format!("bad value for --{}: `{}`",
+                                             name, v)),
+            None => Ok(default)
+        }
+    }
+
+    /// The spawn model given by `--spawn`, the default one if not given
+    pub fn spawn(&self) -> Result<SpawnModel, String> {
+        match self.get("spawn") {
+            Some(spec) => SpawnModel::parse(spec),
+            None => Ok(SpawnModel::default())
+        }
+    }
+}
+
+/// `tune`: optimises the heuristic weights by self-play.
+///
+/// Options: `--out <dir>`, `--population <n>`, `--generations <n>`,
+/// `--games <n>`, `--sigma <x>`, `--seed <n>`, `--objective mean|p<n>`,
+/// `--threads <n>`, `--spawn <model>`, `--init <weights file>`, `--resume`.
+pub fn tune(options: &Options) -> Result<(), String> {
+    let default = TuneConfig::default();
+    let objective = match options.get("objective") {
+        Some(name) => Objective::from_name(name)
+            .ok_or_else(|| format!("unknown objective `{}`", name))?,
+        None => default.objective
+    };
+    let config = TuneConfig {
+        population: options.parse_or("population", default.population)?,
+        generations: options.parse_or("generations", default.generations)?,
+        games: options.parse_or("games", default.games)?,
+        sigma: options.parse_or("sigma", default.sigma)?,
+        seed: options.parse_or("seed", default.seed)?,
+        threads: options.parse_or("threads", default.threads)?,
+        objective,
+        spawn: options.spawn()?,
+        out_dir: options.parse_or("out", default.out_dir.clone())?,
+        ..default
+    };
+    let init = match options.get("init") {
+        Some(path) => Weights::load(path).map_err(|e| e.to_string())?,
+        None => Weights::default()
+    };
+
+    let best = agent::tune(&config, &init, options.flag("resume"))
+                      .map_err(|e| e.to_string())?;
+    let path: PathBuf = config.out_dir.join("best.weights");
+    println!("best weights: {:?}, saved in {}", best.values, path.display());
+    Ok(())
+}
+
+/// `train-ntuple`: trains an n-tuple network by TD(λ) on self-play, then
+/// measures the resulting agent.
+///
+/// Options: `--out <file>`, `--games <n>`, `--alpha <x>`, `--lambda <x>`,
+/// `--epsilon <x>`, `--seed <n>`, `--spawn <model>`, `--report <n>`,
+/// `--eval <n>` (games played by the trained agent), and `--resume` to start
+/// from the network already in `<file>`.
+pub fn train_ntuple(options: &Options) -> Result<(), String> {
+    let default = TdConfig::default();
+    let config = TdConfig {
+        games: options.parse_or("games", default.games)?,
+        alpha: options.parse_or("alpha", default.alpha)?,
+        lambda: options.parse_or("lambda", default.lambda)?,
+        epsilon: options.parse_or("epsilon", default.epsilon)?,
+        seed: options.parse_or("seed", default.seed)?,
+        spawn: options.spawn()?,
+        report: options.parse_or("report", default.report)?,
+        out: options.parse_or("out", default.out.clone())?,
+    };
+    let mut network = if options.flag("resume") {
+        NTupleNetwork::load(&config.out).map_err(|e| e.to_string())?
+    } else {
+        NTupleNetwork::default()
+    };
+    agent::train_td(&mut network, &config).map_err(|e| e.to_string())?;
+
+    let eval: u64 = options.parse_or("eval", 100)?;
+    let mut player = NTupleAgent::new(network);
+    let total: f64 = (0..eval)
+        .map(|i| {
+            let seed = agent::game_seed(!config.seed, i);
+            agent::play_game(&mut player, seed, &config.spawn).score as f64
+        })
+        .sum();
+    if eval > 0 {
+        println!("trained agent: mean score {:.1} over {} games",
+                 total / eval as f64, eval);
+    }
+    Ok(())
+}
+
+/// `train-mlp`: trains a policy/value network on games it plays through MCTS.
+///
+/// Options: `--out <file>`, `--iterations <n>`, `--games <n>`,
+/// `--simulations <n>`, `--hidden <n,n,...>`, `--steps <n>`, `--batch <n>`,
+/// `--rate <x>`, `--buffer <n>`, `--seed <n>`, `--spawn <model>`,
+/// `--threads <n>`, and `--resume` to start from the network in `<file>`.
+pub fn train_mlp(options: &Options) -> Result<(), String> {
+    let default = MlpConfig::default();
+    let hidden = match options.get("hidden") {
+        Some(sizes) => sizes.split(',')
+            .map(|s| s.trim().parse::<usize>()
+                      .map_err(|_| format!("bad layer size `{}`", s)))
+            .collect::<Result<Vec<usize>, String>>()?,
+        None => default.hidden.clone()
+    };
+    let config = MlpConfig {
+        iterations: options.parse_or("iterations", default.iterations)?,
+        games: options.parse_or("games", default.games)?,
+        simulations: options.parse_or("simulations", default.simulations)?,
+        hidden,
+        steps: options.parse_or("steps", default.steps)?,
+        batch: options.parse_or("batch", default.batch)?,
+        rate: options.parse_or("rate", default.rate)?,
+        buffer: options.parse_or("buffer", default.buffer)?,
+        seed: options.parse_or("seed", default.seed)?,
+        spawn: options.spawn()?,
+        threads: options.parse_or("threads", default.threads)?,
+        out: options.parse_or("out", default.out.clone())?,
+    };
+    let mut net = if options.flag("resume") {
+        PolicyValueNet::load(&config.out).map_err(|e| e.to_string())?
+    } else {
+        agent::new_network(&config)
+    };
+    agent::train_mlp(&mut net, &config).map_err(|e| e.to_string())
+}
+
+/// `engine`: runs the reference engine of the external agent protocol on the
+/// standard input and output.
+pub fn engine(_options: &Options) -> Result<(), String> {
+    protocol::run_engine(io::stdin().lock(), io::stdout().lock())
+             .map_err(|e| e.to_string())
+}
+
+/// `play-engine`: lets an engine speaking the external agent protocol play
+/// games.
+///
+/// Options: `--engine <command>`, `--games <n>`, `--seed <n>`,
+/// `--spawn <model>`, `--budget <ms>` (per move), and `--option name=value`
+/// (any number of times) to set options of the engine.
+pub fn play_engine(options: &Options) -> Result<(), String> {
+    let command = options.get("engine")
+                         .ok_or_else(|| "missing --engine".to_string())?;
+    let engine_options = options.all("option").into_iter()
+        .map(|o| o.split_once('=')
+                  .map(|(n, v)| (n.to_string(), v.to_string()))
+                  .ok_or_else(|| format!("bad engine option `{}`", o)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let config = MatchConfig {
+        games: options.parse_or("games", 10)?,
+        seed: options.parse_or("seed", 0)?,
+        spawn: options.spawn()?,
+        budget: Duration::from_millis(options.parse_or("budget", 1000)?),
+        options: engine_options,
+    };
+    let records = protocol::play_engine(command, &config)
+                           .map_err(|e| e.to_string())?;
+    for (i, record) in records.iter().enumerate() {
+        println!("game {}: score {}, {} moves", i, record.score,
+                 record.moves);
+    }
+    if !records.is_empty() {
+        let total: f64 = records.iter().map(|r| r.score as f64).sum();
+        println!("mean score {:.1} over {} games",
+                 total / records.len() as f64, records.len());
+    }
+    Ok(())
+}
+
+/// `bench`: plays the same seeded games with every agent, and compares them.
+///
+/// Options: `--agent <spec>` (any number of times, see `agent::from_spec`),
+/// `--games <n>`, `--seed <n>`, `--spawn <model>`, `--threads <n>`,
+/// `--resamples <n>` and `--confidence <x>` for the confidence intervals of
+/// the differences with the first agent. `--csv <file>` writes the games
+/// played, and `--svg <dir>` charts of them (see `bench::save_charts`).
+pub fn bench(options: &Options) -> Result<(), String> {
+    let specs = options.all("agent");
+    if specs.is_empty() {
+        return Err("no --agent given".to_string());
+    }
+    let default = BenchConfig::default();
+    let config = BenchConfig {
+        games: options.parse_or("games", default.games)?,
+        seed: options.parse_or("seed", default.seed)?,
+        spawn: options.spawn()?,
+        threads: options.parse_or("threads", default.threads)?,
+        resamples: options.parse_or("resamples", default.resamples)?,
+        confidence: options.parse_or("confidence", default.confidence)?,
+    };
+    if !(config.confidence > 0.0 && config.confidence < 1.0) {
+        return Err("--confidence must be between 0 and 1".to_string());
+    }
+
+    let mut runs = Vec::new();
+    for spec in specs {
+        let run = bench::run_agent(spec, &config)?;
+        let s = bench::summarise(&run);
+        println!("{}: {} games in {:.1} s ({:.1} games/s)", run.agent,
+                 s.games, run.elapsed.as_secs_f64(), s.games_per_second);
+        println!("  score: mean {:.1}, median {}, p10 {}, p90 {}, max {}",
+                 s.mean, s.median, s.p10, s.p90, s.max);
+        println!("  moves: mean {:.1}, median {}, p10 {}, p90 {}, max {}",
+                 s.mean_moves, s.median_moves, s.p10_moves, s.p90_moves,
+                 s.max_moves);
+        let atoms: Vec<String> = s.best_atoms.iter().map(|(z, n)| {
+            format!("{} {:.1}%", atom_symbol(&AtomType::Atom(*z)),
+                    100.0 * *n as f64 / s.games as f64)
+        }).collect();
+        println!("  best atom: {}", atoms.join(", "));
+        runs.push(run);
+    }
+
+    if let Some(path) = options.get("csv") {
+        let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        bench::write_csv(&runs, io::BufWriter::new(file))
+              .map_err(|e| e.to_string())?;
+    }
+    if let Some(dir) = options.get("svg") {
+        bench::save_charts(&runs, dir).map_err(|e| e.to_string())?;
+    }
+
+    for run in &runs[1..] {
+        let d = bench::paired_difference(&runs[0], run, &config);
+        println!("{} - {}: mean difference {:+.1}, {:.0}% interval \
+                  [{:+.1}, {:+.1}]", run.agent, runs[0].agent, d.mean,
+                 config.confidence * 100.0, d.low, d.high);
+    }
+    Ok(())
+}
+
+/// `fit`: estimates the parameters of the `drawing` spawn model from spawn
+/// logs, and writes them in a file that `--spawn drawing:<file>` loads.
+///
+/// Options: `--log <file>` (any number of times, see
+/// `spawn::read_spawn_log`) and `--out <file>` (`spawn.params` by default).
+pub fn fit(options: &Options) -> Result<(), String> {
+    let logs = options.all("log");
+    if logs.is_empty() {
+        return Err("no --log given".to_string());
+    }
+    let out: PathBuf = options.parse_or("out", PathBuf::from("spawn.params"))?;
+
+    let mut records = Vec::new();
+    for log in &logs {
+        records.extend(spawn::read_spawn_log(log)
+                            .map_err(|e| format!("{}: {}", log, e))?);
+    }
+    let fit = spawn::fit_spawn(&records)?;
+
+    println!("{} atoms from {} logs", fit.atoms, logs.len());
+    println!("{:<16} {:>12} {:>12}", "parameter", "value", "std. error");
+    for ((name, value), error) in SPAWN_PARAM_NAMES.iter()
+                                                   .zip(fit.params.values())
+                                                   .zip(fit.std_errors.values()) {
+        println!("{:<16} {:>12.6} {:>12.6}", name, value, error);
+    }
+    println!("log-likelihood: {:.2} (default parameters: {:.2})",
+             fit.log_likelihood,
+             spawn::log_likelihood(&SpawnParams::default(), &records));
+    fit.params.save(&out).map_err(|e| e.to_string())?;
+    println!("parameters written to {}", out.display());
+    Ok(())
+}
+
+/// `spawn-posterior`: follows the posterior of the spawn parameters along
+/// spawn logs, as atom after atom comes.
+///
+/// Options: `--log <file>` (repeated), `--particles <n>`, `--prior <file>`,
+/// `--spread <x>`, `--seed <n>`, `--every <n>` atoms between reports.
+pub fn spawn_posterior(options: &Options) -> Result<(), String> {
+    let logs = options.all("log");
+    if logs.is_empty() {
+        return Err("no --log given".to_string());
+    }
+    let defaults = spawn::PosteriorConfig::default();
+    let prior = match options.get("prior") {
+        Some(path) => SpawnParams::load(path)
+                          .map_err(|e| format!("{}: {}", path, e))?,
+        None => defaults.prior,
+    };
+    let config = spawn::PosteriorConfig {
+        particles: options.parse_or("particles", defaults.particles)?,
+        prior,
+        spread: options.parse_or("spread", defaults.spread)?,
+        seed: options.parse_or("seed", defaults.seed)?,
+    };
+    let every: usize = options.parse_or("every", 100)?;
+
+    let mut records = Vec::new();
+    for log in &logs {
+        records.extend(spawn::read_spawn_log(log)
+                            .map_err(|e| format!("{}: {}", log, e))?);
+    }
+    let mut posterior = spawn::SpawnPosterior::new(&config);
+    println!("{:>6} {:>7}  {}", "atoms", "ESS", SPAWN_PARAM_NAMES.join(" "));
+    for (i, record) in records.iter().enumerate() {
+        posterior.observe(record);
+        if (i + 1) % every.max(1) == 0 || i + 1 == records.len() {
+            let mean = posterior.mean().values().map(|v| format!("{:.4}", v));
+            println!("{:>6} {:>7.0}  {}", i + 1, posterior.effective_size(),
+                     mean.join(" "));
+        }
+    }
+
+    println!("{:<16} {:>12} {:>12}", "parameter", "mean", "std. dev.");
+    for ((name, mean), std) in SPAWN_PARAM_NAMES.iter()
+                                   .zip(posterior.mean().values())
+                                   .zip(posterior.std_dev().values()) {
+        println!("{:<16} {:>12.6} {:>12.6}", name, mean, std);
+    }
+    if let Some(last) = records.last() {
+        println!("next atom at time {}:", last.time + 1);
+        let score = last.score.unwrap_or(u32::MAX);
+        for (atom, chance) in posterior.predictive(last.time + 1, score) {
+            if chance >= 0.01 {
+                println!("  {:<3} {:.3}", atom_symbol(&atom), chance);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `spawn-check`: reports how well a spawn model fits recorded spawn logs.
+///
+/// Options: `--log <file>` (repeated), `--spawn <model>`, `--window <n>`,
+/// `--samples <n>`, `--alpha <x>`, `--seed <n>`, `--strict` to fail when the
+/// model is off.
+pub fn spawn_check(options: &Options) -> Result<(), String> {
+    let logs = options.all("log");
+    if logs.is_empty() {
+        return Err("no --log given".to_string());
+    }
+    let defaults = spawn::CheckConfig::default();
+    let config = spawn::CheckConfig {
+        window: options.parse_or("window", defaults.window)?,
+        samples: options.parse_or("samples", defaults.samples)?,
+        seed: options.parse_or("seed", defaults.seed)?,
+        alpha: options.parse_or("alpha", defaults.alpha)?,
+    };
+    let model = options.spawn()?;
+
+    let mut records = Vec::new();
+    for log in &logs {
+        records.extend(spawn::read_spawn_log(log)
+                            .map_err(|e| format!("{}: {}", log, e))?);
+    }
+    let check = spawn::check_spawn(&model, &records, &config);
+    let flag = |off: bool| if off { "  OFF" } else { "" };
+
+    println!("{} atoms from {} logs", records.len(), logs.len());
+    println!("{:>11} {:>6} {:>10} {:>4} {:>9} {:>7}",
+             "times", "atoms", "chi2", "df", "p-value", "KS");
+    for w in &check.windows {
+        println!("{:>5}-{:<5} {:>6} {:>10.2} {:>4} {:>9.4} {:>7.3}{}",
+                 w.start, w.end - 1, w.atoms, w.chi_square, w.degrees,
+                 w.p_value, w.ks_distance, flag(w.off));
+    }
+    println!("KS distance on regular atoms: {:.4} (critical {:.4}){}",
+             check.ks_distance, check.ks_critical,
+             flag(check.ks_distance > check.ks_critical));
+    println!("{:<4} {:>8} {:>8} {:>10} {:>9} {:>9} {:>7}",
+             "atom", "eligible", "observed", "expected", "obs. rate",
+             "exp. rate", "z");
+    for s in &check.specials {
+        let rate = |n: f64| if s.eligible > 0 { n / s.eligible as f64 }
+                            else              { 0.0 };
+        println!("{:<4} {:>8} {:>8} {:>10.1} {:>9.4} {:>9.4} {:>7.2}{}",
+                 atom_symbol(&s.atom), s.eligible, s.observed, s.expected,
+                 rate(s.observed as f64), rate(s.expected), s.z, flag(s.off));
+    }
+
+    if options.flag("strict") && check.is_off() {
+        return Err(format!("the spawn model is off at level {}",
+                           config.alpha));
+    }
+    Ok(())
+}
+
+/// `scores`: prints the high scores kept in `history::data_dir()`.
+///
+/// Options: `--history` prints every game finished instead, in the order
+/// they ended.
+pub fn scores(options: &Options) -> Result<(), String> {
+    let dir = history::data_dir();
+    let history = History::open(&dir).map_err(|e| {
+        format!("cannot read the scores in {}: {}", dir.display(), e)
+    })?;
+    let games = if options.flag("history") { &history.games }
+                else                       { &history.top };
+    if games.is_empty() {
+        println!("no game finished yet");
+    }
+    for line in history::table(games) {
+        println!("{}", line);
+    }
+    Ok(())
+}
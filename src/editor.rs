@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::PathBuf;
+
+use iatomas::game::{ATOMS_NAMES, Atom, AtomType, Board, GameState, MAX_ATOMS,
+                    atom_symbol, is_known, parse_position, position};
+
+use sfml::window::{Key, mouse::Button};
+
+/// Atoms in the order scrolling goes through them: the special ones, then
+/// the elements
+fn palette() -> Vec<AtomType> {
+    let mut atoms = vec![AtomType::Plus, AtomType::Minus, AtomType::DarkPlus,
+                         AtomType::Neutrino];
+    atoms.extend((0..ATOMS_NAMES.len()).map(|z| AtomType::Atom(z as u8)));
+    atoms
+}
+
+/// The atom `steps` after `atom` in the palette, wrapping around
+fn scrolled(atom: &AtomType, steps: i32) -> AtomType {
+    let atoms = palette();
+    let i = atoms.iter().position(|a| a == atom).unwrap_or(0) as i32;
+    let n = atoms.len() as i32;
+    atoms[(i + steps).rem_euclid(n) as usize].clone()
+}
+
+/// Edits the position on the board, to set up tests and puzzles.
+///
+/// A left click in a gap of the ring inserts an atom there, of the last
+/// element chosen, and a right click on an atom deletes it. Scrolling over
+/// an atom of the ring, or over the incoming one, changes its element.
+/// Digits followed by S or T set the score or the time. W writes the
+/// position to the file, in the position notation (see
+/// `iatomas::game::position`), and L loads it back.
+pub struct Editor {
+    path: PathBuf,
+    active: bool,
+    /// Atom inserted by a click
+    brush: AtomType,
+    /// Digits typed so far
+    number: String,
+    /// Outcome of the last action, shown under the help
+    message: String,
+}
+
+impl Editor {
+    /// An editor saving positions to and loading them from `path`
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            active: false,
+            brush: AtomType::Atom(0),
+            number: String::new(),
+            message: String::new(),
+        }
+    }
+
+    /// Returns `true` while the position is being edited
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Starts or stops editing the position on `board`
+    pub fn toggle(&mut self, board: &mut Board) {
+        self.active = !self.active;
+        self.number.clear();
+        self.message.clear();
+        board.set_cursor(None);
+        self.show(board);
+    }
+
+    /// Replaces the position on `board` by `state` once edited by `edit`
+    fn edit<F: FnOnce(&mut GameState)>(&mut self, board: &mut Board, edit: F) {
+        let mut state = board.state().clone();
+        edit(&mut state);
+        state.best = state.atoms.iter().map(|a| a.value()).max().unwrap_or(0);
+        board.restart(state);
+    }
+
+    /// Inserts or deletes an atom where `button` has been clicked
+    pub fn click(&mut self, board: &mut Board, button: Button, x: i32,
+                 y: i32) {
+        let (x, y) = (x as f32, y as f32);
+        let n = board.state().atoms.len();
+        match button {
+            Button::Left if !board.layout().in_centre(x, y) => {
+                if let Some(i) = board.layout().gap_at(x, y, n) {
+                    if n < MAX_ATOMS {
+                        let atom = Atom::from_type(self.brush.clone());
+                        self.edit(board, |state| state.atoms.insert(i, atom));
+                    }
+                }
+            },
+            Button::Right => {
+                if let Some(i) = board.layout().atom_at(x, y, n) {
+                    self.edit(board, |state| { state.atoms.remove(i); });
+                }
+            },
+            _ => {}
+        }
+        self.show(board);
+    }
+
+    /// Changes the element of the atom scrolled over by `delta` steps
+    pub fn scroll(&mut self, board: &mut Board, delta: f32, x: i32, y: i32) {
+        let (x, y) = (x as f32, y as f32);
+        let steps = delta.signum() as i32;
+        let n = board.state().atoms.len();
+        if board.layout().in_centre(x, y) {
+            let atom = scrolled(&board.state().incoming.t, steps);
+            self.edit(board, |state| {
+                state.incoming = Atom::from_type(atom);
+                state.minused = false;
+            });
+        } else if let Some(i) = board.layout().atom_at(x, y, n) {
+            let atom = scrolled(&board.state().atoms[i].t, steps);
+            self.brush = atom.clone();
+            self.edit(board, |state| state.atoms[i] = Atom::from_type(atom));
+        }
+        self.show(board);
+    }
+
+    /// Reacts to `key`, ignoring those that are not the editor's
+    pub fn key(&mut self, board: &mut Board, key: Key) {
+        let digit = match key {
+            Key::Num0 | Key::Numpad0 => Some('0'),
+            Key::Num1 | Key::Numpad1 => Some('1'),
+            Key::Num2 | Key::Numpad2 => Some('2'),
+            Key::Num3 | Key::Numpad3 => Some('3'),
+            Key::Num4 | Key::Numpad4 => Some('4'),
+            Key::Num5 | Key::Numpad5 => Some('5'),
+            Key::Num6 | Key::Numpad6 => Some('6'),
+            Key::Num7 | Key::Numpad7 => Some('7'),
+            Key::Num8 | Key::Numpad8 => Some('8'),
+            Key::Num9 | Key::Numpad9 => Some('9'),
+            _ => None
+        };
+        match (key, digit) {
+            (_, Some(digit)) => self.number.push(digit),
+            (Key::BackSpace, _) => { self.number.pop(); },
+            (Key::S, _) | (Key::T, _) => {
+                match self.number.parse::<u32>() {
+                    Ok(value) if key == Key::S => {
+                        self.edit(board, |state| state.score = value);
+                    },
+                    Ok(value) => self.edit(board, |state| state.time = value),
+                    Err(_) => self.message = "type a number first".to_string()
+                }
+                self.number.clear();
+            },
+            (Key::W, _) => self.message = match self.save(board.state()) {
+                Ok(()) => format!("saved to {}", self.path.display()),
+                Err(e) => e
+            },
+            (Key::L, _) => self.message = match self.load(board.state()) {
+                Ok(state) => {
+                    board.restart(state);
+                    format!("loaded {}", self.path.display())
+                },
+                Err(e) => e
+            },
+            _ => {}
+        }
+        self.show(board);
+    }
+
+    /// Writes `state` to the file
+    fn save(&self, state: &GameState) -> Result<(), String> {
+        fs::write(&self.path, format!("{}\n", position(state))).map_err(|e| {
+            format!("cannot write {}: {}", self.path.display(), e)
+        })
+    }
+
+    /// Reads the position in the file, the next atoms coming as they would
+    /// in `current`
+    fn load(&self, current: &GameState) -> Result<GameState, String> {
+        let text = fs::read_to_string(&self.path).map_err(|e| {
+            format!("cannot read {}: {}", self.path.display(), e)
+        })?;
+        let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+        let mut state = parse_position(line, 0, current.spawn.clone())?;
+        state.rng = current.rng.clone();
+        if !state.atoms.iter().all(|a| is_known(&a.t)) ||
+           !is_known(&state.incoming.t) {
+            return Err("unknown element in the position".to_string());
+        }
+        Ok(state)
+    }
+
+    /// Shows the help and the state of the editor under the score, or
+    /// nothing once it is closed
+    fn show(&self, board: &mut Board) {
+        if !self.active {
+            board.set_status(Vec::new());
+            return;
+        }
+        let mut lines = vec![
+            "editing (E to play)".to_string(),
+            format!("click: add {}  right click: delete",
+                    atom_symbol(&self.brush)),
+            "scroll: element  S/T: score/time".to_string(),
+            format!("W/L: save/load {}", self.path.display()),
+        ];
+        if !self.number.is_empty() {
+            lines.push(format!("> {}", self.number));
+        }
+        if !self.message.is_empty() {
+            lines.push(self.message.clone());
+        }
+        board.set_status(lines);
+    }
+}
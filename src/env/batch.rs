@@ -0,0 +1,243 @@
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use super::{ACTION_SPACE, Env, EnvConfig, OBSERVATION_SIZE, StepInfo};
+use crate::agent::game_seed;
+
+/// Many independent games stepped together, split between worker threads.
+///
+/// The observations of the games are written one after the other in the
+/// buffers given by the caller, the `i`-th game at `i * OBSERVATION_SIZE`
+/// (and its action mask at `i * ACTION_SPACE`). A finished game is reset at
+/// once: its `done` flag is set, and the observation written is the first
+/// one of its next episode.
+///
+/// The games are shared out once and for all, each share being stepped by a
+/// thread of its own that lives as long as the `BatchEnv`.
+pub struct BatchEnv {
+    shares: Vec<Share>,
+    /// Games per share, all shares but the last being full
+    chunk: usize,
+    /// One per share, none when stepping on the calling thread
+    workers: Vec<Worker>,
+}
+
+/// A game of a `BatchEnv`, with what is needed to reset it
+struct Game {
+    env: Env,
+    /// Seed the seeds of the episodes of the game are derived from
+    seed: u64,
+    episodes: u64,
+    info: StepInfo,
+}
+
+impl Game {
+    /// Starts the next episode
+    fn reset(&mut self, observation: &mut [f32]) {
+        self.env.reset(game_seed(self.seed, self.episodes));
+        self.episodes += 1;
+        self.env.observe(observation);
+    }
+
+    /// Plays `action`, already checked to be legal, resetting the game if it
+    /// ends
+    fn step(&mut self, action: usize, observation: &mut [f32],
+            reward: &mut f32, done: &mut bool) {
+        let step = self.env.step(action)
+                       .expect("actions are checked before stepping");
+        *reward = step.reward as f32;
+        *done = step.done;
+        self.info = step.info;
+        if step.done {
+            self.reset(observation);
+        } else {
+            self.env.observe(observation);
+        }
+    }
+}
+
+/// The games a worker thread steps, with the actions to play and buffers
+/// for what comes out, handed to the thread and back at every step
+struct Share {
+    games: Vec<Game>,
+    actions: Vec<usize>,
+    observations: Vec<f32>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl Share {
+    fn new(games: Vec<Game>) -> Self {
+        let n = games.len();
+        Self {
+            games,
+            actions: vec![0; n],
+            observations: vec![0.0; n * OBSERVATION_SIZE],
+            rewards: vec![0.0; n],
+            dones: vec![false; n],
+        }
+    }
+
+    fn step(&mut self) {
+        let observations = self.observations.chunks_mut(OBSERVATION_SIZE);
+        for (i, (game, obs)) in self.games.iter_mut().zip(observations)
+                                                     .enumerate() {
+            game.step(self.actions[i], obs, &mut self.rewards[i],
+                      &mut self.dones[i]);
+        }
+    }
+}
+
+/// A thread stepping the share it is sent, and sending it back
+struct Worker {
+    shares: Sender<Share>,
+    /// Locked for the `BatchEnv` to be `Sync`, as receivers are not
+    stepped: Mutex<Receiver<Share>>,
+    thread: JoinHandle<()>,
+}
+
+impl Worker {
+    fn new() -> Self {
+        let (shares, to_step) = mpsc::channel::<Share>();
+        let (done, stepped) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            for mut share in to_step {
+                share.step();
+                if done.send(share).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { shares, stepped: Mutex::new(stepped), thread }
+    }
+}
+
+impl BatchEnv {
+    /// Creates `n` games played with `config`, the seeds of the episodes of
+    /// the `i`-th one being derived from `game_seed(seed, i)`.
+    pub fn new(n: usize, config: EnvConfig, seed: u64, threads: usize)
+                                                                    -> Self {
+        let threads = threads.max(1);
+        let chunk = n.div_ceil(threads).max(1);
+        let mut games = (0..n as u64).map(|i| Game {
+            env: Env::new(config.clone()),
+            seed: game_seed(seed, i),
+            episodes: 0,
+            info: StepInfo::default(),
+        });
+        let mut shares = Vec::new();
+        loop {
+            let share: Vec<Game> = games.by_ref().take(chunk).collect();
+            if share.is_empty() {
+                break;
+            }
+            shares.push(Share::new(share));
+        }
+        let workers = if threads == 1 { Vec::new() }
+                      else { shares.iter().map(|_| Worker::new()).collect() };
+        Self { shares, chunk, workers }
+    }
+
+    /// Number of games.
+    pub fn len(&self) -> usize {
+        self.shares.iter().map(|s| s.games.len()).sum()
+    }
+
+    /// Whether there are no games at all.
+    pub fn is_empty(&self) -> bool {
+        self.shares.is_empty()
+    }
+
+    fn games(&self) -> impl Iterator<Item = &Game> {
+        self.shares.iter().flat_map(|s| s.games.iter())
+    }
+
+    /// Starts a new episode of every game, writing their first observations
+    /// in `observations`, of length `len() * OBSERVATION_SIZE`.
+    pub fn reset(&mut self, observations: &mut [f32]) {
+        assert_eq!(observations.len(), self.len() * OBSERVATION_SIZE);
+        let observations = observations.chunks_mut(OBSERVATION_SIZE);
+        let games = self.shares.iter_mut().flat_map(|s| s.games.iter_mut());
+        for (game, obs) in games.zip(observations) {
+            game.reset(obs);
+        }
+    }
+
+    /// Plays `actions[i]` in the `i`-th game, for every game, and writes the
+    /// new observations, the rewards and whether the episodes have ended.
+    ///
+    /// Nothing is played if one of the actions is illegal.
+    pub fn step(&mut self, actions: &[usize], observations: &mut [f32],
+                rewards: &mut [f32], dones: &mut [bool])
+                                                    -> Result<(), String> {
+        let n = self.len();
+        if actions.len() != n || observations.len() != n * OBSERVATION_SIZE
+           || rewards.len() != n || dones.len() != n {
+            return Err(format!("buffers do not fit {} games", n));
+        }
+        for (i, (game, &action)) in self.games().zip(actions).enumerate() {
+            if game.env.action_move(action).is_none() {
+                return Err(format!("illegal action {} in game {}", action,
+                                   i));
+            }
+        }
+
+        let chunk = self.chunk;
+        for (share, actions) in self.shares.iter_mut()
+                                    .zip(actions.chunks(chunk)) {
+            share.actions.copy_from_slice(actions);
+        }
+        if self.workers.is_empty() {
+            self.shares.iter_mut().for_each(Share::step);
+        } else {
+            for (share, worker) in self.shares.drain(..).zip(&self.workers) {
+                worker.shares.send(share).expect("batch worker has died");
+            }
+            self.shares = self.workers.iter().map(|worker| {
+                worker.stepped.lock().unwrap().recv()
+                      .expect("batch worker has died")
+            }).collect();
+        }
+
+        let outputs = observations.chunks_mut(chunk * OBSERVATION_SIZE)
+            .zip(rewards.chunks_mut(chunk))
+            .zip(dones.chunks_mut(chunk));
+        for (share, ((obs, rew), done)) in self.shares.iter().zip(outputs) {
+            obs.copy_from_slice(&share.observations);
+            rew.copy_from_slice(&share.rewards);
+            done.copy_from_slice(&share.dones);
+        }
+        Ok(())
+    }
+
+    /// Writes which actions are legal in every game in `out`, of length
+    /// `len() * ACTION_SPACE`.
+    pub fn action_masks(&self, out: &mut [bool]) {
+        assert_eq!(out.len(), self.len() * ACTION_SPACE);
+        for (game, mask) in self.games().zip(out.chunks_mut(ACTION_SPACE)) {
+            game.env.write_action_mask(mask);
+        }
+    }
+
+    /// Details about the last step of every game: when a game has just been
+    /// reset, those of the end of its previous episode.
+    pub fn infos(&self) -> Vec<StepInfo> {
+        self.games().map(|g| g.info.clone()).collect()
+    }
+
+    /// The `i`-th game.
+    pub fn env(&self, i: usize) -> &Env {
+        &self.shares[i / self.chunk].games[i % self.chunk].env
+    }
+}
+
+impl Drop for BatchEnv {
+    /// Stops the workers, which wait for shares as long as they can get one
+    fn drop(&mut self) {
+        for worker in self.workers.drain(..) {
+            drop(worker.shares);
+            let _ = worker.thread.join();
+        }
+    }
+}
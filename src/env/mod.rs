@@ -0,0 +1,194 @@
+mod batch;
+
+pub use batch::*;
+
+use crate::agent::MAX_GAME_MOVES;
+use crate::game::{ACTIONS, ENCODING_SIZE, GameState, Move, SpawnModel,
+                  action_mask_from, canonical_start, encode_from,
+                  rotate_move};
+
+/// Size of the observations of an `Env`.
+pub const OBSERVATION_SIZE: usize = ENCODING_SIZE;
+
+/// Number of actions of an `Env`, legal or not.
+pub const ACTION_SPACE: usize = ACTIONS;
+
+/// What an `Env` rewards a step with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reward {
+    /// The score gained by the move
+    Score,
+    /// The score gained, plus the given weight times the atomic number of the
+    /// highest atom made by the reactions of the move
+    Shaped(f64),
+}
+
+/// Parameters of an `Env`.
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub spawn: SpawnModel,
+    pub reward: Reward,
+    /// Moves after which an episode is stopped, even if the game is not over
+    pub max_moves: u32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            spawn: SpawnModel::default(),
+            reward: Reward::Score,
+            max_moves: MAX_GAME_MOVES,
+        }
+    }
+}
+
+/// Details about a step, besides its reward.
+#[derive(Clone, Debug, Default)]
+pub struct StepInfo {
+    pub score: u32,
+    pub best: u8,
+    /// Moves played since the reset
+    pub moves: u32,
+    /// Value of the highest atom made by the reactions of the move (0 if
+    /// none)
+    pub reacted: u8,
+    /// Whether the episode has been stopped by `max_moves`, the game not
+    /// being over
+    pub truncated: bool,
+}
+
+/// Result of `Env::step`.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    pub reward: f64,
+    pub done: bool,
+    pub info: StepInfo,
+}
+
+/// A game seen as a reinforcement learning environment, with a fixed set of
+/// `ACTION_SPACE` actions and observations of `OBSERVATION_SIZE` values.
+///
+/// The ring is seen from its canonical start (see `canonical_start`), so that
+/// the same ring always gives the same observation: the `i`-th slot of an
+/// observation is the `i`-th atom from there, and the actions are numbered
+/// as in `Move::index`, but from that start: action `i` shoots the incoming
+/// atom just before the `i`-th atom, action `ACTION_SPACE / 2 + i` takes it
+/// with a Minus or a Neutrino, and the last action converts the atom taken
+/// with a Minus.
+pub struct Env {
+    pub config: EnvConfig,
+    state: GameState,
+    start: usize,
+    moves: u32,
+}
+
+impl Env {
+    /// Creates an environment, ready for a game seeded with `0`.
+    pub fn new(config: EnvConfig) -> Self {
+        let state = GameState::start_game_seeded(0, config.spawn.clone());
+        let start = canonical_start(&state);
+        Self { config, state, start, moves: 0 }
+    }
+
+    /// Starts a new game, determined by `seed` and the actions taken.
+    ///
+    /// Returns: the first observation
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.state = GameState::start_game_seeded(seed,
+                                                  self.config.spawn.clone());
+        self.start = canonical_start(&self.state);
+        self.moves = 0;
+        self.observation()
+    }
+
+    /// Plays `action`, which must be allowed by the action mask.
+    pub fn step(&mut self, action: usize) -> Result<Step, String> {
+        if self.is_done() {
+            return Err("the episode is over, it must be reset".to_string());
+        }
+        let mv = self.action_move(action)
+                     .ok_or_else(|| format!("illegal action {}", action))?;
+
+        let score = self.state.score;
+        let reacted = self.state.apply(mv);
+        self.start = canonical_start(&self.state);
+        self.moves += 1;
+
+        let gain = (self.state.score - score) as f64;
+        let reward = match self.config.reward {
+            Reward::Score => gain,
+            Reward::Shaped(weight) if reacted > 0 => {
+                gain + weight * (reacted as f64 + 1.0)
+            },
+            Reward::Shaped(_) => gain,
+        };
+        Ok(Step {
+            observation: self.observation(),
+            reward,
+            done: self.is_done(),
+            info: self.info(reacted),
+        })
+    }
+
+    /// Whether the episode is over: the game is over, or `max_moves` moves
+    /// have been played.
+    pub fn is_done(&self) -> bool {
+        self.state.is_over() || self.moves >= self.config.max_moves
+    }
+
+    /// The current observation.
+    pub fn observation(&self) -> Vec<f32> {
+        let mut out = vec![0.0; OBSERVATION_SIZE];
+        self.observe(&mut out);
+        out
+    }
+
+    /// Writes the current observation in `out`, of length
+    /// `OBSERVATION_SIZE`.
+    pub fn observe(&self, out: &mut [f32]) {
+        encode_from(&self.state, self.start, out);
+    }
+
+    /// Which actions are legal now.
+    pub fn action_mask(&self) -> Vec<bool> {
+        let mut out = vec![false; ACTION_SPACE];
+        self.write_action_mask(&mut out);
+        out
+    }
+
+    /// Writes which actions are legal now in `out`, of length
+    /// `ACTION_SPACE`.
+    pub fn write_action_mask(&self, out: &mut [bool]) {
+        action_mask_from(&self.state, self.start, out);
+    }
+
+    /// The move of the game `action` stands for, if it is legal.
+    pub fn action_move(&self, action: usize) -> Option<Move> {
+        // Whether a slot is on the ring does not depend on where it is read
+        // from
+        let mv = Move::from_index(action)?;
+        if !self.state.is_legal(mv) {
+            return None;
+        }
+        Some(rotate_move(mv, self.start, self.state.atoms.len()))
+    }
+
+    /// The game being played.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Details about the current state, `reacted` being the value of the
+    /// highest atom made by the last move.
+    pub fn info(&self, reacted: u8) -> StepInfo {
+        StepInfo {
+            score: self.state.score,
+            best: self.state.best,
+            moves: self.moves,
+            reacted,
+            truncated: !self.state.is_over()
+                       && self.moves >= self.config.max_moves,
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::f32::consts::PI;
+
+use super::{Atom, AtomType, Fusion, GameState, Layout, Move};
+
+/// Seconds taken by an atom to reach the ring, and by the others to make room
+/// for it or to close the gap it leaves
+pub const SLIDE_DURATION: f32 = 0.2;
+/// Seconds taken by a fusion
+pub const FUSION_DURATION: f32 = 0.35;
+/// Seconds taken by the next incoming atom to appear
+pub const APPEAR_DURATION: f32 = 0.15;
+/// Growth of an atom at the height of a fusion into it
+const PULSE: f32 = 0.4;
+
+/// An atom as drawn at some point of an animation: at `angle` degrees
+/// clockwise from the top, `distance` times the radius of the ring away from
+/// its centre, and `scale` times its usual size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sprite {
+    pub atom: AtomType,
+    pub angle: f32,
+    pub distance: f32,
+    pub scale: f32,
+}
+
+impl Sprite {
+    /// The `i`-th atom of a ring of `n`
+    fn on_ring(atom: &AtomType, i: usize, n: usize) -> Self {
+        Self {
+            atom: atom.clone(),
+            angle: Layout::slot_angle(i, n),
+            distance: 1.0,
+            scale: 1.0,
+        }
+    }
+
+    /// The incoming atom
+    fn in_centre(atom: &AtomType) -> Self {
+        Self { atom: atom.clone(), angle: 0.0, distance: 0.0, scale: 1.0 }
+    }
+
+    /// This sprite, moved to `angle` on the ring
+    fn to_slot(&self, angle: f32) -> Self {
+        Self { angle, distance: 1.0, ..self.clone() }
+    }
+
+    /// This sprite, moved to the centre straight from where it is
+    fn to_centre(&self) -> Self {
+        Self { distance: 0.0, ..self.clone() }
+    }
+
+    /// This sprite, shrunk to nothing
+    fn vanished(&self) -> Self {
+        Self { scale: 0.0, ..self.clone() }
+    }
+
+    /// Where the centre of the sprite is drawn
+    pub fn position(&self, layout: &Layout) -> (f32, f32) {
+        layout.ring_point(self.angle, self.distance)
+    }
+}
+
+/// Sprites going from where they are to where they go, all at once.
+#[derive(Clone, Debug)]
+struct Step {
+    duration: f32,
+    sprites: Vec<(Sprite, Sprite)>,
+    /// Sprite growing then shrinking back meanwhile
+    pulse: Option<usize>,
+    /// Score shown at the beginning and at the end
+    score: (u32, u32),
+}
+
+/// The atoms of `ring`, staying where they are
+fn still(ring: &[Atom]) -> Vec<(Sprite, Sprite)> {
+    ring.iter().enumerate().map(|(i, atom)| {
+        let sprite = Sprite::on_ring(&atom.t, i, ring.len());
+        (sprite.clone(), sprite)
+    }).collect()
+}
+
+/// Step of `fusion`: the two atoms slide into the centre one, which pulses
+/// and becomes their result, while the others close the gaps they leave.
+/// `incoming` is the sprite in the centre meanwhile, if any.
+fn fusion_step(fusion: &Fusion, incoming: &Option<Sprite>, score: u32)
+                                                                    -> Step {
+    let n = fusion.before.len();
+    let m = n.saturating_sub(2);
+    // Index of the `j`-th atom once the two have gone
+    let after = |j: usize| j - (fusion.left < j) as usize
+                             - (fusion.right < j) as usize;
+    let target = Layout::slot_angle(after(fusion.centre), m);
+
+    let mut sprites: Vec<(Sprite, Sprite)> = fusion.before.iter().enumerate()
+        .map(|(j, atom)| {
+            let from = Sprite::on_ring(&atom.t, j, n);
+            let to = if j == fusion.left || j == fusion.right {
+                from.to_slot(target).vanished()
+            } else if j == fusion.centre {
+                Sprite { atom: fusion.result.clone(), ..from.to_slot(target) }
+            } else {
+                from.to_slot(Layout::slot_angle(after(j), m))
+            };
+            (from, to)
+        }).collect();
+    sprites.extend(incoming.iter().map(|s| (s.clone(), s.clone())));
+
+    Step {
+        duration: FUSION_DURATION,
+        sprites,
+        pulse: Some(fusion.centre),
+        score: (score, fusion.score),
+    }
+}
+
+/// Animation of a move: the atoms slide around the ring to make room for the
+/// one placed or to close the gap of the one picked, the fusions it causes
+/// play out one after the other, and the next incoming atom appears. The
+/// score counts up along the fusions.
+///
+/// It is driven by the time elapsed, see `Animation::advance`.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    steps: Vec<Step>,
+    step: usize,
+    /// Seconds since the beginning of the current step
+    elapsed: f32,
+    score: u32,
+}
+
+impl Animation {
+    /// The animation of `mv` played in `before`, leading to `after` through
+    /// `fusions` (see `GameState::apply_traced`).
+    pub fn new(before: &GameState, mv: Move, fusions: &[Fusion],
+               after: &GameState) -> Self {
+        let ring = &before.atoms;
+        let n = ring.len();
+        let incoming = Sprite::in_centre(&before.incoming.t);
+        let mut steps = Vec::new();
+        // Sprite in the centre during the fusions
+        let mut centre = None;
+
+        match mv {
+            Move::Place(i) => {
+                let special = before.incoming.t == AtomType::Minus ||
+                              before.incoming.t == AtomType::Neutrino;
+                let mut sprites: Vec<(Sprite, Sprite)>;
+                if special && n == 0 {
+                    sprites = vec![(incoming.clone(), incoming.vanished())];
+                } else {
+                    sprites = ring.iter().enumerate().map(|(j, atom)| {
+                        let from = Sprite::on_ring(&atom.t, j, n);
+                        let k = if j < i { j } else { j + 1 };
+                        let to = from.to_slot(Layout::slot_angle(k, n + 1));
+                        (from, to)
+                    }).collect();
+                    let to = incoming.to_slot(Layout::slot_angle(i, n + 1));
+                    sprites.push((incoming, to));
+                }
+                steps.push(Step {
+                    duration: SLIDE_DURATION,
+                    sprites,
+                    pulse: None,
+                    score: (before.score, before.score),
+                });
+            },
+            Move::Pick(k) if k < n => {
+                let minus = before.incoming.t == AtomType::Minus;
+                let mut sprites: Vec<(Sprite, Sprite)> = Vec::new();
+                for (j, atom) in ring.iter().enumerate() {
+                    let from = Sprite::on_ring(&atom.t, j, n);
+                    if j == k {
+                        sprites.push((from.clone(), from.to_centre()));
+                        if minus {
+                            continue;
+                        }
+                    }
+                    let to = if minus && j > k {
+                        from.to_slot(Layout::slot_angle(j - 1, n - 1))
+                    } else if minus {
+                        from.to_slot(Layout::slot_angle(j, n - 1))
+                    } else {
+                        from.clone()
+                    };
+                    sprites.push((from, to));
+                }
+                sprites.push((incoming.clone(), incoming.vanished()));
+                steps.push(Step {
+                    duration: SLIDE_DURATION,
+                    sprites,
+                    pulse: None,
+                    score: (before.score, before.score),
+                });
+                centre = Some(Sprite::in_centre(&ring[k].t));
+            },
+            _ => {}
+        }
+
+        let mut score = before.score;
+        for fusion in fusions {
+            steps.push(fusion_step(fusion, &centre, score));
+            score = fusion.score;
+        }
+
+        if let Move::Place(_) = mv {
+            if after.incoming.t != AtomType::None {
+                let next = Sprite::in_centre(&after.incoming.t);
+                let mut sprites = still(&after.atoms);
+                sprites.push((next.vanished(), next));
+                steps.push(Step {
+                    duration: APPEAR_DURATION,
+                    sprites,
+                    pulse: None,
+                    score: (score, score),
+                });
+            }
+        }
+        if let Some(last) = steps.last_mut() {
+            last.score.1 = after.score;
+        }
+
+        Self { steps, step: 0, elapsed: 0.0, score: after.score }
+    }
+
+    /// Moves the animation `seconds` forward
+    pub fn advance(&mut self, seconds: f32) {
+        self.elapsed += seconds;
+        while let Some(step) = self.steps.get(self.step) {
+            if self.elapsed < step.duration {
+                break;
+            }
+            self.elapsed -= step.duration;
+            self.step += 1;
+        }
+    }
+
+    /// Returns `true` once the animation is over
+    pub fn is_done(&self) -> bool {
+        self.step >= self.steps.len()
+    }
+
+    /// Progress through the current step, from 0 to 1
+    fn progress(&self) -> f32 {
+        match self.steps.get(self.step) {
+            Some(step) => (self.elapsed / step.duration).clamp(0.0, 1.0),
+            None => 1.0
+        }
+    }
+
+    /// The atoms to draw now, none once the animation is over
+    pub fn sprites(&self) -> Vec<Sprite> {
+        let step = match self.steps.get(self.step) {
+            Some(step) => step,
+            None => return Vec::new()
+        };
+        let t = self.progress();
+        // Starts and ends smoothly
+        let e = t * t * (3.0 - 2.0 * t);
+        step.sprites.iter().enumerate().map(|(i, (from, to))| {
+            let turn = (to.angle - from.angle + 540.0).rem_euclid(360.0)
+                       - 180.0;
+            let mut scale = from.scale + (to.scale - from.scale) * e;
+            if step.pulse == Some(i) {
+                scale *= 1.0 + PULSE * (PI * t).sin();
+            }
+            let atom = if e < 0.5 { &from.atom } else { &to.atom };
+            Sprite {
+                atom: atom.clone(),
+                angle: from.angle + turn * e,
+                distance: from.distance + (to.distance - from.distance) * e,
+                scale,
+            }
+        }).collect()
+    }
+
+    /// The score to show now
+    pub fn score(&self) -> u32 {
+        match self.steps.get(self.step) {
+            Some(step) => {
+                let (from, to) = step.score;
+                let gained = (to as f32 - from as f32) * self.progress();
+                (from as f32 + gained).round() as u32
+            },
+            None => self.score
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use super::AtomType;
+
 /// Array of all the atoms' symbols.
 ///
 /// Extended with Atomas' specific atoms :)
@@ -46,6 +48,15 @@ pub const ATOMS_NAMES: [&str; 131] = [
     "Unbipentium",  "Hydrogen 1", "Helium 1",  "Lithium 1",     "Beryllium 1",
     "Boron 1",      "Carbon 1" ];
 
+/// Whether `atom` is a particle, or an element with a name: the only atoms
+/// the game can draw
+pub fn is_known(atom: &AtomType) -> bool {
+    match atom {
+        AtomType::Atom(z) => (*z as usize) < ATOMS_NAMES.len(),
+        t => *t != AtomType::None,
+    }
+}
+
 /// Array of all the atoms' color, in formated hexcode (see 
 /// `crate::game::color_from_hex` function).
 pub const ATOMS_COLORS: [&str; 131] = [
@@ -0,0 +1,600 @@
+use std::collections::VecDeque;
+
+use super::{ATOMS_NAMES, Animation, Atom, AtomType, GameState, Layout, Move,
+            Rgb, Sprite, Theme, move_notation};
+use crate::agent::Hint;
+
+use sfml::{
+    graphics::{CircleShape, Color, Font, RectangleShape, RenderTarget,
+    RenderWindow, Shape, Text, Transformable},
+};
+
+/// Holds the data to display an atom on the window
+#[derive(Clone, Debug)]
+pub struct AtomShape<'a> {
+    circle: CircleShape<'a>,
+    symbol: Text<'a>
+}
+
+/// Holds the shapes of the atoms on the board, to avoid regenerating them each
+/// time the window is re-drawn, and the state of the board.
+///
+/// Moves are animated (see `Animation`): the state is played at once, and the
+/// shapes are only drawn once the animation is over. Clicks made meanwhile
+/// are queued.
+#[derive(Clone, Debug)]
+pub struct Board<'a> {
+    state: GameState,
+    shapes: Vec<AtomShape<'a>>,
+    incoming_shape: Option<AtomShape<'a>>,
+    best_val: u8,
+    best_text: Text<'a>,
+    score_text: Text<'a>,
+    font: &'a Font,
+    layout: Layout,
+    animation: Option<Animation>,
+    /// Clicks waiting for the animation to end
+    pending: VecDeque<(i32, i32)>,
+    hint: Option<Hint>,
+    /// Number of changes of the state so far
+    version: u64,
+    /// Lines shown under the score
+    status: Vec<String>,
+    /// Position of the mouse over the window, if it is in
+    cursor: Option<(f32, f32)>,
+    /// Index of the move selected with the keyboard among the legal ones,
+    /// if any
+    selected: Option<usize>,
+    /// Lines shown over the board, if any
+    overlay: Vec<String>,
+    preview: Option<Preview>,
+    /// Moves played since the last restart
+    moves: Vec<Move>,
+    theme: Theme,
+}
+
+/// What the move under the mouse would do, shown before it is played.
+#[derive(Clone, Debug)]
+struct Preview {
+    mv: Move,
+    /// The ring after the move and its reactions
+    ring: Vec<AtomType>,
+    /// Points the move would score
+    gain: u32,
+}
+
+const POINT_COUNT: u32 = 30;
+/// Colour of the mark of the move under the mouse, and of its gain
+const PREVIEW_COLOR: Color = Color { r: 255, g: 255, b: 255, a: 170 };
+/// Opacity of the background of the overlay, over the board
+const OVERLAY_ALPHA: u8 = 210;
+/// Opacity of the atoms of the ring previewed
+const GHOST_ALPHA: u8 = 110;
+/// Distance from the centre of the ring previewed, in radii of the ring
+const GHOST_DISTANCE: f32 = 0.55;
+/// Size of the atoms of the ring previewed, relative to the real ones
+const GHOST_SCALE: f32 = 0.45;
+
+/// The SFML colour of `rgb`
+pub fn sfml_color((r, g, b): Rgb) -> Color {
+    Color::rgb(r, g, b)
+}
+
+impl<'a> AtomShape<'a> {
+    /// Constructs an atom shape from a given `AtomType`, sized by `layout`
+    /// and drawn as `theme` has it
+    fn from_atom_type(atom_type: &AtomType, font: &'a Font, layout: &Layout,
+                      theme: &Theme) -> Self {
+        let style = theme.atom(atom_type);
+        let mut circle_shape = CircleShape::new(layout.atom_radius,
+                                                POINT_COUNT);
+        circle_shape.set_fill_color(sfml_color(style.color));
+
+        let mut text_shape = Text::new(&style.symbol, font,
+                                       layout.symbol_size);
+        text_shape.set_fill_color(sfml_color(theme.symbol));
+
+        Self {
+            circle: circle_shape,
+            symbol: text_shape
+        }
+    }
+
+    /// Constructs the shape of `sprite`, at its place in `layout`
+    fn from_sprite(sprite: &Sprite, font: &'a Font, layout: &Layout,
+                   theme: &Theme) -> Self {
+        let mut shape = Self::from_atom_type(&sprite.atom, font, layout,
+                                             theme);
+        shape.circle.set_radius(layout.atom_radius * sprite.scale);
+        shape.symbol.set_scale((sprite.scale, sprite.scale));
+        shape.set_position(sprite.position(layout));
+        shape
+    }
+
+    /// Makes the shape translucent, `alpha` being its opacity
+    fn fade(&mut self, alpha: u8) {
+        let mut color = self.circle.fill_color();
+        color.a = alpha;
+        self.circle.set_fill_color(color);
+        let mut color = self.symbol.fill_color();
+        color.a = alpha;
+        self.symbol.set_fill_color(color);
+    }
+
+    /// Set the position of the shape, that is, updates the coordinates of the
+    /// atom's circle and symbol
+    fn set_position(&mut self, pos: (f32, f32)) {
+        let (x, y) = pos;
+        let radius = self.circle.radius();
+        self.circle.set_position((x - radius, y - radius));
+
+        let rect = self.symbol.global_bounds();
+        let pos = (x - rect.width / 2.0, y - rect.height / 2.0);
+        // println!("{:?} vs {:?}", pos, (x, y));
+        self.symbol.set_position(pos);
+    }
+
+    /// Draw this shape on a given `RenderWindow`
+    fn draw_on(&self, window: &mut RenderWindow) {
+        window.draw(&self.circle);
+        window.draw(&self.symbol);
+        
+        // println!("write: {} @\t {:?}\t {:?}", self.symbol.string().to_rust_string(),
+        //                               self.symbol.position(),
+        //                               self.symbol.fill_color());
+    }
+}
+
+impl<'a> Board<'a> {
+    /// Create a new `Board` with given `GameState`, no shape built
+    pub fn from_state(state: GameState, font: &'a Font) -> Self {
+        let layout = Layout::default();
+        let theme = Theme::default();
+        let mut b_text = Text::new(ATOMS_NAMES[0], font, layout.title_size);
+        b_text.set_fill_color(sfml_color(theme.title));
+        b_text.set_outline_thickness(0.3);
+
+        let mut s_text = Text::new("0", font, layout.title_size);
+        s_text.set_fill_color(sfml_color(theme.title));
+        s_text.set_outline_thickness(0.3);
+        let mut board = Self {
+            state,
+            shapes: Vec::new(),
+            incoming_shape: None,
+            best_text: b_text,
+            best_val: 0,
+            score_text: s_text,
+            font,
+            layout,
+            animation: None,
+            pending: VecDeque::new(),
+            hint: None,
+            version: 0,
+            status: Vec::new(),
+            cursor: None,
+            selected: None,
+            overlay: Vec::new(),
+            preview: None,
+            moves: Vec::new(),
+            theme,
+        };
+        board.place_texts();
+        board
+    }
+
+    /// Create a new `Board` with default starting board
+    pub fn new(font: &'a Font) -> Self {
+        Self::from_state(GameState::start_game(), font)
+    }
+
+    /// Where the board is drawn
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Lays the board out anew, e.g. once the window has been resized. The
+    /// texts are sized as the theme has them.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout.with_text_sizes(self.theme.symbol_size,
+                                             self.theme.title_size);
+        self.best_text.set_character_size(self.layout.title_size);
+        self.score_text.set_character_size(self.layout.title_size);
+        self.place_texts();
+        self.update_shapes();
+        self.update_preview();
+    }
+
+    /// The look of the board
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Draws the board as `theme` has it, with `font` loaded from its font
+    /// file
+    pub fn set_theme(&mut self, theme: Theme, font: &'a Font) {
+        self.theme = theme;
+        self.font = font;
+        for text in [&mut self.best_text, &mut self.score_text] {
+            text.set_font(font);
+            text.set_fill_color(sfml_color(self.theme.title));
+        }
+        self.set_layout(self.layout.clone());
+    }
+
+    /// Puts the name of the best atom and the score where the layout says
+    fn place_texts(&mut self) {
+        let (x, y) = self.layout.best_position;
+        let rect = self.best_text.global_bounds();
+        self.best_text.set_position((x - rect.width / 2.0, y));
+        self.score_text.set_position(self.layout.score_position);
+    }
+
+    /// Rebuilds the shapes of all the atoms, and of the incoming one
+    pub fn update_shapes(&mut self) {
+        let n = self.state.atoms.len();
+        self.shapes.clear();
+        for i in 0..n {
+            let j = (i + self.state.shift) % n;
+            let mut shape = AtomShape::from_atom_type(&self.state.atoms[j].t,
+                                                      self.font, &self.layout,
+                                                      &self.theme);
+            shape.set_position(self.layout.nth_atom_coord(j, n));
+            self.shapes.push(shape);
+        }
+
+        let mut shape = AtomShape::from_atom_type(&self.state.incoming.t,
+                                                  self.font, &self.layout,
+                                                  &self.theme);
+        shape.set_position(self.layout.centre);
+        self.incoming_shape = Some(shape);
+    }
+
+    /// Draws all the atoms on `window`, as the animation in progress has
+    /// them if any
+    pub fn draw_on(&self, window: &mut RenderWindow) {
+        self.draw_ring(window);
+        match &self.animation {
+            Some(animation) => {
+                for sprite in animation.sprites() {
+                    if sprite.atom != AtomType::None {
+                        AtomShape::from_sprite(&sprite, self.font,
+                                               &self.layout, &self.theme)
+                            .draw_on(window);
+                    }
+                }
+            },
+            None => {
+                for shape in &self.shapes {
+                    shape.draw_on(window);
+                }
+                if let Some(shape) = &self.incoming_shape {
+                    shape.draw_on(window);
+                }
+                self.draw_preview(window);
+                self.draw_hint(window);
+            }
+        }
+        window.draw(&self.best_text);
+        window.draw(&self.score_text);
+
+        let text = sfml_color(self.theme.text);
+        self.draw_lines(window, &self.status, self.layout.status_position,
+                        text);
+
+        if !self.overlay.is_empty() {
+            let mut veil = RectangleShape::with_size(
+                (self.layout.width, self.layout.height).into());
+            let mut color = sfml_color(self.theme.background);
+            color.a = OVERLAY_ALPHA;
+            veil.set_fill_color(color);
+            window.draw(&veil);
+            self.draw_lines(window, &self.overlay, self.layout.score_position,
+                            text);
+        }
+    }
+
+    /// Draws the circle the atoms are placed on
+    fn draw_ring(&self, window: &mut RenderWindow) {
+        let layout = &self.layout;
+        let radius = layout.circle_radius;
+        let mut ring = CircleShape::new(radius, POINT_COUNT * 4);
+        ring.set_fill_color(Color::TRANSPARENT);
+        ring.set_outline_color(sfml_color(self.theme.ring));
+        ring.set_outline_thickness(layout.atom_radius / 8.0);
+        ring.set_position((layout.centre.0 - radius,
+                           layout.centre.1 - radius));
+        window.draw(&ring);
+    }
+
+    /// Draws `lines` one under the other from `(x, y)`
+    fn draw_lines(&self, window: &mut RenderWindow, lines: &[String],
+                  (x, y): (f32, f32), color: Color) {
+        let size = self.layout.symbol_size;
+        for (i, line) in lines.iter().enumerate() {
+            let mut text = Text::new(line, self.font, size);
+            text.set_fill_color(color);
+            text.set_position((x, y + i as f32 * size as f32 * 1.4));
+            window.draw(&text);
+        }
+    }
+
+    /// Shows `lines` over the board, hiding it, or nothing if there are none
+    pub fn set_overlay(&mut self, lines: Vec<String>) {
+        self.overlay = lines;
+    }
+
+    /// Returns `true` while lines are shown over the board
+    pub fn has_overlay(&self) -> bool {
+        !self.overlay.is_empty()
+    }
+
+    /// The state of the game on the board
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Number of changes of the state so far, to tell when a hint on it
+    /// is no longer relevant
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Moves played since the last restart, oldest first
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    /// Starts over with `state`, as a new game or an edited position
+    pub fn restart(&mut self, state: GameState) {
+        self.resume(state, Vec::new());
+    }
+
+    /// Goes on with `state`, reached by playing `moves` since the game
+    /// started or was edited
+    pub fn resume(&mut self, state: GameState, moves: Vec<Move>) {
+        self.state = state;
+        self.moves = moves;
+        self.animation = None;
+        self.pending.clear();
+        self.best_val = self.state.best;
+        self.best_text.set_string(ATOMS_NAMES[self.best_val as usize]);
+        self.score_text.set_string(&format!("{}", self.state.score));
+        self.changed();
+        self.place_texts();
+        self.update_shapes();
+    }
+
+    /// Shows `lines` under the score
+    pub fn set_status(&mut self, lines: Vec<String>) {
+        self.status = lines;
+    }
+
+    /// Shows `hint` on the board, until it is replaced or the state changes
+    pub fn set_hint(&mut self, hint: Option<Hint>) {
+        self.hint = hint;
+    }
+
+    /// Draws the hint, if any: the best move highlighted on the ring, and
+    /// the best moves with their values in the side panel
+    fn draw_hint(&self, window: &mut RenderWindow) {
+        let hint = match &self.hint {
+            Some(hint) => hint,
+            None => return
+        };
+        let layout = &self.layout;
+        if let Some((mv, _)) = hint.moves.first() {
+            self.draw_mark(window, *mv, sfml_color(self.theme.hint));
+        }
+
+        let lines: Vec<String> = hint.moves.iter().enumerate()
+            .map(|(rank, (mv, value))| {
+                format!("{}. {}  {:.1}", rank + 1, move_notation(*mv), value)
+            }).collect();
+        self.draw_lines(window, &lines, layout.panel_position,
+                        sfml_color(self.theme.hint));
+    }
+
+    /// Circles where `mv` is played: the gap the atom enters, the atom picked
+    /// or the centre
+    fn draw_mark(&self, window: &mut RenderWindow, mv: Move, color: Color) {
+        let layout = &self.layout;
+        let n = self.state.atoms.len();
+        let (x, y) = match mv {
+            Move::Place(i) => layout.ring_point(Layout::gap_angle(i, n), 1.0),
+            Move::Pick(i) => layout.nth_atom_coord(i, n),
+            Move::Convert => layout.centre,
+        };
+        let radius = layout.atom_radius * 1.2;
+        let mut mark = CircleShape::new(radius, POINT_COUNT);
+        mark.set_fill_color(Color::TRANSPARENT);
+        mark.set_outline_color(color);
+        mark.set_outline_thickness(layout.atom_radius / 6.0);
+        mark.set_position((x - radius, y - radius));
+        window.draw(&mark);
+    }
+
+    /// Follows the mouse, at `(x, y)` or out of the window, to preview the
+    /// move under it
+    pub fn set_cursor(&mut self, cursor: Option<(i32, i32)>) {
+        self.cursor = cursor.map(|(x, y)| (x as f32, y as f32));
+        self.selected = None;
+        self.update_preview();
+    }
+
+    /// Selects the legal move `steps` after the one selected, wrapping
+    /// around: the places go clockwise, then comes the conversion if any,
+    /// and the picks go clockwise. Without a move selected yet, the first
+    /// one is selected going forward and the last one going backward.
+    pub fn select(&mut self, steps: i32) {
+        let n = self.state.legal_moves().len();
+        if n == 0 {
+            return;
+        }
+        self.selected = Some(match self.selected {
+            Some(i) => (i as i32 + steps).rem_euclid(n as i32) as usize,
+            None if steps >= 0 => 0,
+            None => n - 1
+        });
+        self.update_preview();
+    }
+
+    /// Plays the move selected, if any and unless a move is being animated
+    pub fn play_selected(&mut self) {
+        let moves = self.state.legal_moves();
+        if let Some(mv) = self.selected.and_then(|i| moves.get(i)) {
+            if !self.is_animating() {
+                self.play_move(*mv);
+            }
+        }
+    }
+
+    /// Finds what the move selected, or else the one under the mouse, would
+    /// do, if it is legal (see `Layout::target` and `GameState::dry_run`).
+    fn update_preview(&mut self) {
+        let moves = self.state.legal_moves();
+        // The moves change with the state
+        self.selected = self.selected.filter(|_| !moves.is_empty())
+                                     .map(|i| i.min(moves.len() - 1));
+        let mv = match self.selected {
+            Some(i) => Some(moves[i]),
+            None => self.cursor.and_then(|(x, y)| {
+                self.layout.target(x, y, &self.state)
+            }).filter(|mv| moves.contains(mv))
+        };
+        self.preview = mv.map(|mv| {
+            let run = self.state.dry_run(mv);
+            Preview {
+                mv,
+                ring: run.atoms.into_iter().map(|a| a.t).collect(),
+                gain: run.score_delta,
+            }
+        });
+    }
+
+    /// Draws the preview, if any: the move marked on the ring, the ring it
+    /// leads to as a smaller, translucent one inside, and the points it
+    /// scores next to the score
+    fn draw_preview(&self, window: &mut RenderWindow) {
+        let preview = match &self.preview {
+            Some(preview) => preview,
+            None => return
+        };
+        self.draw_mark(window, preview.mv, PREVIEW_COLOR);
+        if preview.mv == Move::Convert {
+            return;
+        }
+
+        let m = preview.ring.len();
+        for (i, atom) in preview.ring.iter().enumerate() {
+            let sprite = Sprite {
+                atom: atom.clone(),
+                angle: Layout::slot_angle(i, m),
+                distance: GHOST_DISTANCE,
+                scale: GHOST_SCALE,
+            };
+            let mut shape = AtomShape::from_sprite(&sprite, self.font,
+                                                   &self.layout, &self.theme);
+            shape.fade(GHOST_ALPHA);
+            shape.draw_on(window);
+        }
+
+        if preview.gain > 0 {
+            let rect = self.score_text.global_bounds();
+            let line = format!("+{}", preview.gain);
+            let mut text = Text::new(&line, self.font, self.layout.title_size);
+            text.set_fill_color(PREVIEW_COLOR);
+            text.set_position((rect.left + rect.width
+                               + self.layout.atom_radius / 2.0,
+                               self.layout.score_position.1));
+            window.draw(&text);
+        }
+    }
+
+    /// Returns `true` while a move is being animated
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Moves the animation in progress `seconds` forward. Once it is over,
+    /// the clicks queued meanwhile are handled.
+    pub fn advance(&mut self, seconds: f32) {
+        if let Some(animation) = &mut self.animation {
+            animation.advance(seconds);
+            let score = animation.score();
+            if animation.is_done() {
+                self.animation = None;
+            }
+            self.score_text.set_string(&format!("{}", score));
+        }
+        while self.animation.is_none() {
+            match self.pending.pop_front() {
+                Some((x, y)) => self.click(x, y),
+                None => break
+            }
+        }
+    }
+
+    /// Reacts to a click event in `x0`, `y0`, see `Layout::target`. It is
+    /// queued while a move is being animated.
+    pub fn click(&mut self, x0: i32, y0: i32) {
+        if self.is_animating() {
+            self.pending.push_back((x0, y0));
+            return;
+        }
+        if let Some(mv) = self.layout.target(x0 as f32, y0 as f32,
+                                             &self.state) {
+            self.play_move(mv);
+        }
+    }
+
+    /// Notes that the state has changed, which drops the hint
+    fn changed(&mut self) {
+        self.version += 1;
+        self.hint = None;
+        self.update_preview();
+    }
+
+    /// Plays `mv` if it is legal, animates it, and updates the texts and
+    /// shapes.
+    pub fn play_move(&mut self, mv: Move) {
+        if !self.state.legal_moves().contains(&mv) {
+            return;
+        }
+        let before = self.state.clone();
+        let (max, fusions) = self.state.apply_traced(mv);
+        self.moves.push(mv);
+        self.changed();
+        let animation = Animation::new(&before, mv, &fusions, &self.state);
+        if !animation.is_done() {
+            self.animation = Some(animation);
+        }
+        if max > self.best_val {
+            self.best_val = max;
+            self.best_text.set_string(ATOMS_NAMES[max as usize]);
+            self.place_texts();
+        }
+        if !self.is_animating() {
+            self.score_text.set_string(&format!("{}", self.state.score));
+        }
+        self.update_shapes();
+    }
+
+    #[allow(dead_code)]
+    /// Prints the info about the state in this `Board`
+    pub fn info(&self) {
+        self.state.info();
+    }
+
+    /// Force the incoming atom in the state to a given type.
+    ///
+    /// Obviously, for testing purposes.
+    pub fn set_state_incoming(&mut self, atom_type: AtomType) {
+        if self.is_animating() {
+            return;
+        }
+        self.state.incoming = Atom::from_type(atom_type);
+        self.state.minused = false;
+        self.changed();
+        self.update_shapes();
+    }
+}
\ No newline at end of file
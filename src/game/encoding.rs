@@ -0,0 +1,134 @@
+use super::{ACTIONS, AtomType, GameState, MAX_ATOMS, Move};
+
+/// Number of values describing each slot of the ring
+const SLOT_VALUES: usize = 5;
+/// Number of values describing the incoming atom
+const INCOMING_VALUES: usize = 6;
+/// Number of values describing the whole game
+const GLOBAL_VALUES: usize = 4;
+
+/// Number of values `encode` writes.
+pub const ENCODING_SIZE: usize = MAX_ATOMS * SLOT_VALUES + INCOMING_VALUES
+                                 + GLOBAL_VALUES;
+
+/// Atomic numbers are divided by this value, to stay around `[0, 1]`
+const Z_SCALE: f32 = 32.0;
+/// Scores are divided by this value
+const SCORE_SCALE: f32 = 10_000.0;
+
+/// Writes a fixed-size description of `state` in `out`, for neural networks.
+///
+/// The `i`-th slot of the ring is described at `i * 5`, the slots being in
+/// the order of `state.atoms` so that they line up with the moves numbered by
+/// `Move::index`. See `encode_from`.
+pub fn encode(state: &GameState, out: &mut [f32]) {
+    encode_from(state, 0, out);
+}
+
+/// Writes a fixed-size description of `state` in `out`, the ring being read
+/// from its `start`-th atom on: the `i`-th slot, described at `i * 5`, is the
+/// `(start + i) % n`-th atom. Each slot holds:
+///
+/// - whether the slot holds an atom, a Plus, a Dark Plus
+///
+/// - the atomic number of a regular atom
+///
+/// - whether it is the same atom as the incoming one
+///
+/// Then come the kind (regular, Plus, Minus, Dark Plus or Neutrino) and
+/// atomic number of the incoming atom, the number of atoms on the ring,
+/// whether the incoming atom can be converted, the highest atom on the ring
+/// and the score.
+pub fn encode_from(state: &GameState, start: usize, out: &mut [f32]) {
+    for x in out.iter_mut() {
+        *x = 0.0;
+    }
+    let n = state.atoms.len();
+    let mut top = 0;
+    for i in 0..n.min(MAX_ATOMS) {
+        let atom = &state.atoms[(start + i) % n];
+        let slot = &mut out[i * SLOT_VALUES..(i + 1) * SLOT_VALUES];
+        slot[0] = 1.0;
+        match atom.t {
+            AtomType::Plus => slot[1] = 1.0,
+            AtomType::DarkPlus => slot[2] = 1.0,
+            AtomType::Atom(z) => {
+                slot[3] = (z as f32 + 1.0) / Z_SCALE;
+                if state.incoming.t == atom.t { slot[4] = 1.0; }
+                top = top.max(z);
+            },
+            _ => {}
+        }
+    }
+
+    let incoming = &mut out[MAX_ATOMS * SLOT_VALUES..];
+    match state.incoming.t {
+        AtomType::Atom(z) => {
+            incoming[0] = 1.0;
+            incoming[5] = (z as f32 + 1.0) / Z_SCALE;
+        },
+        AtomType::Plus => incoming[1] = 1.0,
+        AtomType::Minus => incoming[2] = 1.0,
+        AtomType::DarkPlus => incoming[3] = 1.0,
+        AtomType::Neutrino => incoming[4] = 1.0,
+        AtomType::None => {}
+    }
+
+    let global = &mut out[MAX_ATOMS * SLOT_VALUES + INCOMING_VALUES..];
+    global[0] = state.atoms.len() as f32 / MAX_ATOMS as f32;
+    global[1] = if state.minused { 1.0 } else { 0.0 };
+    global[2] = (top as f32 + 1.0) / Z_SCALE;
+    global[3] = state.score as f32 / SCORE_SCALE;
+}
+
+/// Writes in `out` (of length `ACTIONS`) which moves are legal in `state`,
+/// numbered by `Move::index`.
+pub fn action_mask(state: &GameState, out: &mut [bool]) {
+    action_mask_from(state, 0, out);
+}
+
+/// Same as `action_mask`, for the moves seen from the `start`-th atom of the
+/// ring (see `rotate_move`).
+pub fn action_mask_from(state: &GameState, start: usize, out: &mut [bool]) {
+    debug_assert_eq!(out.len(), ACTIONS);
+    for x in out.iter_mut() {
+        *x = false;
+    }
+    let n = state.atoms.len();
+    for mv in state.legal_moves() {
+        out[rotate_move(mv, n - start % n.max(1), n).index()] = true;
+    }
+}
+
+/// Start of the canonical reading of the ring: the greatest of its rotations
+/// in lexicographic order, so that it begins with the highest atom. Reading
+/// the ring from there does not depend on where insertions happened.
+pub fn canonical_start(state: &GameState) -> usize {
+    let atoms = &state.atoms;
+    let n = atoms.len();
+    let mut best = 0;
+    for i in 1..n {
+        for j in 0..n {
+            let (a, b) = (&atoms[(i + j) % n], &atoms[(best + j) % n]);
+            if a != b {
+                if a > b { best = i; }
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// The move `mv` of a ring of `n` atoms, once the ring is read from its
+/// `start`-th atom: `Move::Place(i)` and `Move::Pick(i)` become those of the
+/// `(start + i) % n`-th atom.
+pub fn rotate_move(mv: Move, start: usize, n: usize) -> Move {
+    if n == 0 {
+        return mv;
+    }
+    match mv {
+        Move::Place(i) => Move::Place((start + i) % n),
+        Move::Pick(i) => Move::Pick((start + i) % n),
+        Move::Convert => Move::Convert,
+    }
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::PI;
+
+use super::{AtomType, GameState, Move};
+
+/// Side of the square window the sizes below are given for
+const REFERENCE_SIZE: f32 = 400.0;
+
+const CIRCLE_RADIUS: f32 = 100.0;
+const ATOM_RADIUS: f32 = 15.0;
+/// Usual character size of the symbols of atoms
+pub const SYMBOL_SIZE: f32 = 12.0;
+/// Usual character size of the score and of the name of the best atom
+pub const TITLE_SIZE: f32 = 20.0;
+const MARGIN: f32 = 10.0;
+const TITLE_Y: f32 = 20.0;
+const STATUS_Y: f32 = 50.0;
+const PANEL_Y: f32 = 330.0;
+
+/// Smallest size of a text, in pixels at a scale of 1, so that it stays
+/// readable in small windows
+pub const MIN_TEXT_SIZE: f32 = 8.0;
+
+/// Character size of a text of `size` in a `REFERENCE_SIZE` square window,
+/// in a window `unit` times as large on a screen of density `scale`
+fn text_size(size: f32, unit: f32, scale: f32) -> u32 {
+    (size * unit).max(MIN_TEXT_SIZE * scale).round() as u32
+}
+
+/// Where things are drawn in the window, derived from its size.
+///
+/// Everything is scaled from a layout made for a `REFERENCE_SIZE` square
+/// window, by the side of the largest square fitting in the window, and
+/// centred in it. `scale` is the ratio of the screen's pixel density to a
+/// usual one: it only bounds the size of texts from below.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    pub scale: f32,
+    /// Centre of the ring, where the incoming atom is
+    pub centre: (f32, f32),
+    pub circle_radius: f32,
+    pub atom_radius: f32,
+    /// Character size of the symbols of atoms
+    pub symbol_size: u32,
+    /// Character size of the score and of the name of the best atom
+    pub title_size: u32,
+    /// Top centre of the name of the best atom
+    pub best_position: (f32, f32),
+    /// Top left corner of the score
+    pub score_position: (f32, f32),
+    /// Top left corner of the status lines, under the score
+    pub status_position: (f32, f32),
+    /// Top left corner of the side panel, listing hints
+    pub panel_position: (f32, f32),
+}
+
+impl Default for Layout {
+    /// The layout of a `WIDTH` by `HEIGHT` window
+    fn default() -> Self {
+        Self::new(crate::WIDTH, crate::HEIGHT, 1.0)
+    }
+}
+
+impl Layout {
+    /// The layout of a `width` by `height` window, on a screen of pixel
+    /// density `scale` times the usual one.
+    pub fn new(width: f32, height: f32, scale: f32) -> Self {
+        let unit = width.min(height) / REFERENCE_SIZE;
+        // Left and top of the largest square fitting in the window
+        let left = (width - REFERENCE_SIZE * unit) / 2.0;
+        let top = (height - REFERENCE_SIZE * unit) / 2.0;
+        Self {
+            width,
+            height,
+            scale,
+            centre: (width / 2.0, height / 2.0),
+            circle_radius: CIRCLE_RADIUS * unit,
+            atom_radius: ATOM_RADIUS * unit,
+            symbol_size: text_size(SYMBOL_SIZE, unit, scale),
+            title_size: text_size(TITLE_SIZE, unit, scale),
+            best_position: (width / 2.0, top + TITLE_Y * unit),
+            score_position: (left + MARGIN * unit, top + TITLE_Y * unit),
+            status_position: (left + MARGIN * unit, top + STATUS_Y * unit),
+            panel_position: (left + MARGIN * unit, top + PANEL_Y * unit),
+        }
+    }
+
+    /// The same layout with texts of other sizes, given as `SYMBOL_SIZE`
+    /// and `TITLE_SIZE` are
+    pub fn with_text_sizes(mut self, symbol: f32, title: f32) -> Self {
+        let unit = self.width.min(self.height) / REFERENCE_SIZE;
+        self.symbol_size = text_size(symbol, unit, self.scale);
+        self.title_size = text_size(title, unit, self.scale);
+        self
+    }
+
+    /// Angle, in degrees clockwise from the top, of the `i`-th atom of a
+    /// ring of `n` atoms
+    pub fn slot_angle(i: usize, n: usize) -> f32 {
+        if n == 0 { 0.0 } else { (i as f32) * 360.0 / (n as f32) }
+    }
+
+    /// Point at `angle` degrees clockwise from the top, and `distance` times
+    /// the radius of the ring away from its centre
+    pub fn ring_point(&self, angle: f32, distance: f32) -> (f32, f32) {
+        let (xc, yc) = self.centre;
+        let theta = angle * PI / 180.0;
+        let r = self.circle_radius * distance;
+        (xc + r * theta.sin(), yc - r * theta.cos())
+    }
+
+    /// Angle of the gap where `Move::Place(i)` puts an atom in a ring of `n`
+    /// atoms, halfway between the atoms `i - 1` and `i`
+    pub fn gap_angle(i: usize, n: usize) -> f32 {
+        if n == 0 { 0.0 }
+        else      { Layout::slot_angle(i, n) - 180.0 / (n as f32) }
+    }
+
+    /// Centre of the `i`-th atom of a ring of `n` atoms, the first one being
+    /// on top and the next ones clockwise.
+    pub fn nth_atom_coord(&self, i: usize, n: usize) -> (f32, f32) {
+        self.ring_point(Layout::slot_angle(i, n), 1.0)
+    }
+
+    /// Computes the angle, in degrees clockwise from the top, of the point
+    /// at `(dx, dy)` from the centre of the ring.
+    pub fn angle(dx: f32, dy: f32) -> f32 {
+        let theta = dx.atan2(-dy) * 180.0 / PI;
+        if theta < 0.0 { theta + 360.0 } else { theta }
+    }
+
+    /// Returns `true` if `(x, y)` is on the atom in the centre
+    pub fn in_centre(&self, x: f32, y: f32) -> bool {
+        let (dx, dy) = (x - self.centre.0, y - self.centre.1);
+        dx.powi(2) + dy.powi(2) < self.atom_radius.powi(2)
+    }
+
+    /// Index of the atom at `(x, y)` on a ring of `n` atoms, if any. Atoms
+    /// cover the half gaps on both of their sides.
+    pub fn atom_at(&self, x: f32, y: f32, n: usize) -> Option<usize> {
+        let (dx, dy) = (x - self.centre.0, y - self.centre.1);
+        let d_squared = dx.powi(2) + dy.powi(2);
+        let inner = (self.circle_radius - self.atom_radius).powi(2);
+        let outer = (self.circle_radius + self.atom_radius).powi(2);
+        if n == 0 || d_squared <= inner || d_squared >= outer {
+            return None;
+        }
+        let theta = Layout::angle(dx, dy) + 360.0 / (2.0 * n as f32);
+        let i = theta * n as f32 / 360.0;
+        Some((i.floor() as usize) % n)
+    }
+
+    /// Gap pointed at by `(x, y)` inside a ring of `n` atoms, if any: the
+    /// place an atom put there would take.
+    pub fn gap_at(&self, x: f32, y: f32, n: usize) -> Option<usize> {
+        let (dx, dy) = (x - self.centre.0, y - self.centre.1);
+        let d_squared = dx.powi(2) + dy.powi(2);
+        if d_squared >= (self.circle_radius + self.atom_radius).powi(2) {
+            return None;
+        }
+        if n == 0 {
+            return Some(0);
+        }
+        // Atom `k` is at `k * 360 / n` degrees, so the gap after it is the
+        // place `k + 1`
+        let i = (Layout::angle(dx, dy) * n as f32 / 360.0).floor();
+        Some((i as usize + 1) % n)
+    }
+
+    /// The move of clicking at `(x, y)` in `state`, if any: converting the
+    /// incoming atom in the centre, picking the atom under the cursor with a
+    /// Minus or a Neutrino, or else placing the incoming atom in the gap
+    /// pointed at. The move may not be legal.
+    pub fn target(&self, x: f32, y: f32, state: &GameState) -> Option<Move> {
+        let n = state.atoms.len();
+        if self.in_centre(x, y) && state.minused {
+            Some(Move::Convert)
+        }
+        else if state.incoming.t == AtomType::Minus ||
+                state.incoming.t == AtomType::Neutrino {
+            self.atom_at(x, y, n).map(Move::Pick)
+        }
+        else {
+            self.gap_at(x, y, n).map(|i| {
+                if n == 0 { Move::Place(0) }
+                else      { Move::Place((i + n - state.shift) % n) }
+            })
+        }
+    }
+}
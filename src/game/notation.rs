@@ -0,0 +1,122 @@
+use super::{ATOMS_SYMBOLS, Atom, AtomType, GameState, Move, SpawnModel};
+
+/// Symbol of an atom in the position notation: its element symbol for a
+/// regular atom, `+` for a Plus, `-` for a Minus, `D+` for a Dark Plus, `Nu`
+/// for a Neutrino and `.` for no atom.
+///
+/// An element whose symbol is also the one of a lighter element is written
+/// with its atomic number instead.
+pub fn atom_symbol(t: &AtomType) -> String {
+    match t {
+        AtomType::Atom(z) => {
+            let z = *z as usize;
+            match ATOMS_SYMBOLS.get(z) {
+                Some(s) if ATOMS_SYMBOLS[..z].contains(s) => {
+                    (z + 1).to_string()
+                },
+                Some(s) => s.to_string(),
+                None => (z + 1).to_string()
+            }
+        },
+        AtomType::Plus => "+".to_string(),
+        AtomType::Minus => "-".to_string(),
+        AtomType::DarkPlus => "D+".to_string(),
+        AtomType::Neutrino => "Nu".to_string(),
+        AtomType::None => ".".to_string(),
+    }
+}
+
+/// Atom written `symbol` (see `atom_symbol`), which may also be an atomic
+/// number.
+pub fn parse_atom(symbol: &str) -> Option<AtomType> {
+    match symbol {
+        "+" => Some(AtomType::Plus),
+        "-" => Some(AtomType::Minus),
+        "D+" => Some(AtomType::DarkPlus),
+        "Nu" => Some(AtomType::Neutrino),
+        "." => Some(AtomType::None),
+        _ => {
+            if let Ok(z) = symbol.parse::<u8>() {
+                if z >= 1 { Some(AtomType::Atom(z - 1)) } else { None }
+            } else {
+                ATOMS_SYMBOLS.iter().position(|s| *s == symbol)
+                             .map(|z| AtomType::Atom(z as u8))
+            }
+        }
+    }
+}
+
+/// Writes `state` in the position notation: the atoms of the ring separated
+/// by commas (or `.` for an empty ring), the incoming atom, the score and the
+/// number of atoms shot so far, then `m` if the incoming atom has just been
+/// taken with a Minus. For example `H,He,+,H Li 42 17`.
+///
+/// The future atoms are not part of a position.
+pub fn position(state: &GameState) -> String {
+    let ring = if state.atoms.is_empty() {
+        ".".to_string()
+    } else {
+        state.atoms.iter().map(|a| atom_symbol(&a.t))
+                   .collect::<Vec<_>>().join(",")
+    };
+    let mut text = format!("{} {} {} {}", ring, atom_symbol(&state.incoming.t),
+                           state.score, state.time);
+    if state.minused {
+        text.push_str(" m");
+    }
+    text
+}
+
+/// Reads a position written by `position`. The incoming atoms that follow
+/// will be drawn from `spawn` with a generator seeded by `seed`.
+pub fn parse_position(text: &str, seed: u64, spawn: SpawnModel)
+                                                -> Result<GameState, String> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() != 4 && fields.len() != 5 {
+        return Err(format!("expected 4 or 5 fields in position `{}`", text));
+    }
+    let atom = |s: &str| parse_atom(s).ok_or_else(|| {
+        format!("unknown atom `{}`", s)
+    });
+
+    let mut state = GameState::new(seed, spawn);
+    if fields[0] != "." {
+        for s in fields[0].split(',') {
+            match atom(s)? {
+                AtomType::None => return Err("no atom on the ring".to_string()),
+                t => state.atoms.push(Atom::from_type(t)),
+            }
+        }
+    }
+    state.incoming = Atom::from_type(atom(fields[1])?);
+    state.score = fields[2].parse()
+                           .map_err(|_| format!("bad score `{}`", fields[2]))?;
+    state.time = fields[3].parse()
+                          .map_err(|_| format!("bad time `{}`", fields[3]))?;
+    match fields.get(4) {
+        Some(&"m") => state.minused = true,
+        Some(flag) => return Err(format!("unknown flag `{}`", flag)),
+        None => {}
+    }
+    state.best = state.atoms.iter().map(|a| a.value()).max().unwrap_or(0);
+    Ok(state)
+}
+
+/// Writes `mv` as `place:<i>`, `pick:<i>` or `convert`.
+pub fn move_notation(mv: Move) -> String {
+    match mv {
+        Move::Place(i) => format!("place:{}", i),
+        Move::Pick(i) => format!("pick:{}", i),
+        Move::Convert => "convert".to_string(),
+    }
+}
+
+/// Reads a move written by `move_notation`.
+pub fn parse_move(text: &str) -> Option<Move> {
+    match text.split_once(':') {
+        Some(("place", i)) => i.parse().ok().map(Move::Place),
+        Some(("pick", i)) => i.parse().ok().map(Move::Pick),
+        None if text == "convert" => Some(Move::Convert),
+        _ => None
+    }
+}
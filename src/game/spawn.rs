@@ -0,0 +1,294 @@
+use super::AtomType;
+use crate::invalid_data;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use rand::Rng;
+use rand_distr::{Bernoulli, Binomial, Distribution};
+use lazy_static::lazy_static;
+
+lazy_static!{
+    static ref BINOM: Binomial = Binomial::new(7, 0.5).unwrap();
+    static ref BERN_02: Bernoulli = Bernoulli::new(0.2).unwrap();
+}
+
+const PLUS_CHANCE: f64 = 0.23;
+const MINUS_CHANCE: f64 = 0.05;
+const DPLUS_CHANCE: f64 = 0.0125;
+const WHITE_CHANCE: f64 = 1_f64 / 60_f64;
+
+/// Score from which Dark Pluses can come
+pub const MIN_DPLUS_SCORE: u32 = 750;
+/// Score from which Neutrinos can come
+pub const MIN_WHITE_SCORE: u32 = 1500;
+
+/// Parameters of the spawn model described in `AtomDrawing.md`.
+///
+/// The atomic number (`1` for Hydrogen) of a regular atom drawn at time `t`
+/// is `shift(t) + Bin(n(t), p)`, where:
+///
+/// - `shift(t) = shift_slope * t + shift_intercept`
+///
+/// - `n(t) = n_slope * t + n_intercept`
+///
+/// Special atoms are drawn first, with their own chances.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnParams {
+    pub shift_slope: f64,
+    pub shift_intercept: f64,
+    pub n_slope: f64,
+    pub n_intercept: f64,
+    pub p: f64,
+    pub plus: f64,
+    pub minus: f64,
+    pub dark_plus: f64,
+    pub neutrino: f64,
+}
+
+/// Names of the parameters in a parameters file, in the order of the fields
+/// of `SpawnParams`
+pub const SPAWN_PARAM_NAMES: [&str; 9] = [
+    "shift_slope", "shift_intercept", "n_slope", "n_intercept", "p", "plus",
+    "minus", "dark_plus", "neutrino",
+];
+
+/// Where the incoming atoms come from.
+///
+/// - `Simple`: a Plus one time out of five, then a Minus one time out of five,
+///   else a regular atom drawn from a fixed Binomial
+///
+/// - `Drawing`: the time dependent model of `AtomDrawing.md`, with given
+///   parameters
+///
+/// - `Replay`: the atoms of a recorded game, the `t`-th one coming at time
+///   `t`, then as in `Simple` once they are exhausted
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SpawnModel {
+    #[default]
+    Simple,
+    Drawing(SpawnParams),
+    Replay(Vec<AtomType>),
+}
+
+impl Default for SpawnParams {
+    /// The values given in `AtomDrawing.md`
+    fn default() -> Self {
+        Self {
+            shift_slope: 0.02352571,
+            shift_intercept: 2.05071664,
+            n_slope: 0.02646,
+            n_intercept: 1.5091,
+            p: 0.5,
+            plus: PLUS_CHANCE,
+            minus: MINUS_CHANCE,
+            dark_plus: DPLUS_CHANCE,
+            neutrino: WHITE_CHANCE,
+        }
+    }
+}
+
+impl SpawnParams {
+    /// Shift of the Binomial at time `t`, rounded
+    pub fn shift(&self, t: u32) -> i32 {
+        (self.shift_slope * t as f64 + self.shift_intercept).round() as i32
+    }
+
+    /// Number of trials of the Binomial at time `t`, rounded
+    pub fn n(&self, t: u32) -> u64 {
+        let n = (self.n_slope * t as f64 + self.n_intercept).round();
+        if n < 0.0 { 0 } else { n as u64 }
+    }
+
+    /// The parameter called `name` (see `SPAWN_PARAM_NAMES`)
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "shift_slope" => Some(&mut self.shift_slope),
+            "shift_intercept" => Some(&mut self.shift_intercept),
+            "n_slope" => Some(&mut self.n_slope),
+            "n_intercept" => Some(&mut self.n_intercept),
+            "p" => Some(&mut self.p),
+            "plus" => Some(&mut self.plus),
+            "minus" => Some(&mut self.minus),
+            "dark_plus" => Some(&mut self.dark_plus),
+            "neutrino" => Some(&mut self.neutrino),
+            _ => None
+        }
+    }
+
+    /// The parameters, in the order of `SPAWN_PARAM_NAMES`
+    pub fn values(&self) -> [f64; 9] {
+        [self.shift_slope, self.shift_intercept, self.n_slope,
+         self.n_intercept, self.p, self.plus, self.minus, self.dark_plus,
+         self.neutrino]
+    }
+
+    /// The parameters of `values`, in the order of `SPAWN_PARAM_NAMES`
+    pub fn from_values(values: [f64; 9]) -> Self {
+        let [shift_slope, shift_intercept, n_slope, n_intercept, p, plus,
+             minus, dark_plus, neutrino] = values;
+        Self {
+            shift_slope, shift_intercept, n_slope, n_intercept, p, plus,
+            minus, dark_plus, neutrino,
+        }
+    }
+
+    /// Loads parameters from a file holding a `<name> <value>` pair per
+    /// line. Parameters that are not given keep their default value, and
+    /// lines starting with `#` are ignored.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a parameters file, see `SpawnParams::load`
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut params = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let value = words.next().and_then(|v| v.parse::<f64>().ok())
+                .ok_or_else(|| {
+                    invalid_data(format!("bad value in `{}`", line))
+                })?;
+            *params.get_mut(name).ok_or_else(|| {
+                invalid_data(format!("unknown parameter `{}`", name))
+            })? = value;
+        }
+        Ok(params)
+    }
+
+    /// Writes the parameters in a file that `SpawnParams::load` can read
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "# iatomas spawn parameters")?;
+        for (name, value) in SPAWN_PARAM_NAMES.iter().zip(self.values()) {
+            writeln!(file, "{} {}", name, value)?;
+        }
+        Ok(())
+    }
+
+    /// Draws a regular atom at time `t`, as an atom index (`0` for Hydrogen)
+    pub fn draw_regular<R: Rng>(&self, t: u32, rng: &mut R) -> u8 {
+        let p = self.p.clamp(0.0, 1.0);
+        let k = Binomial::new(self.n(t), p).unwrap().sample(rng);
+        let z = self.shift(t) + k as i32;
+        // `z` is an atomic number, starting at 1 for Hydrogen
+        (z - 1).clamp(0, u8::MAX as i32) as u8
+    }
+
+    /// Chance of every atom that can come at time `t` when the score is
+    /// `score`, as drawn by `SpawnModel::Drawing`: the special atoms first,
+    /// then the regular ones by increasing index.
+    pub fn chances(&self, t: u32, score: u32) -> Vec<(AtomType, f64)> {
+        let dark_plus = if score >= MIN_DPLUS_SCORE { self.dark_plus }
+                        else                        { 0.0 };
+        let neutrino = if score >= MIN_WHITE_SCORE { self.neutrino }
+                       else                        { 0.0 };
+        let mut chances: Vec<(AtomType, f64)> = [
+            (AtomType::Plus, self.plus),
+            (AtomType::Minus, self.minus),
+            (AtomType::DarkPlus, dark_plus),
+            (AtomType::Neutrino, neutrino),
+        ].iter().filter(|(_, c)| *c > 0.0).cloned().collect();
+        let regular = 1.0 - chances.iter().map(|(_, c)| c).sum::<f64>();
+        if regular <= 0.0 {
+            return chances;
+        }
+
+        let (n, p) = (self.n(t), self.p.clamp(0.0, 1.0));
+        let mut choose = 1.0;
+        for k in 0..=n {
+            let chance = regular * choose * p.powi(k as i32)
+                                 * (1.0 - p).powi((n - k) as i32);
+            choose *= (n - k) as f64 / (k + 1) as f64;
+            let z = (self.shift(t) + k as i32 - 1).clamp(0, u8::MAX as i32);
+            match chances.last_mut() {
+                Some((AtomType::Atom(last), c)) if *last as i32 == z => {
+                    *c += chance
+                },
+                _ => chances.push((AtomType::Atom(z as u8), chance)),
+            }
+        }
+        chances
+    }
+}
+
+impl SpawnModel {
+    /// Parses the name of a spawn model, as given on the command line:
+    /// `simple` or `drawing`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "simple" => Some(SpawnModel::Simple),
+            "drawing" => Some(SpawnModel::Drawing(SpawnParams::default())),
+            _ => None
+        }
+    }
+
+    /// Parses a spawn model given on the command line: `simple`, `drawing`,
+    /// `drawing:<file>` with the parameters of a file (see
+    /// `SpawnParams::load`), or `replay:<file>` with the atoms of a spawn log
+    /// (see `spawn::read_spawn_log`).
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once(':') {
+            Some(("drawing", path)) => {
+                let params = SpawnParams::load(path).map_err(|e| {
+                    format!("cannot load spawn parameters `{}`: {}", path, e)
+                })?;
+                Ok(SpawnModel::Drawing(params))
+            },
+            Some(("replay", path)) => {
+                let records = crate::spawn::read_spawn_log(path).map_err(|e| {
+                    format!("cannot load spawn log `{}`: {}", path, e)
+                })?;
+                crate::spawn::replay_model(&records)
+            },
+            _ => Self::from_name(spec)
+                      .ok_or_else(|| format!("unknown spawn model `{}`", spec))
+        }
+    }
+
+    /// Draws the type of the atom coming at time `time`, when the score is
+    /// `score`.
+    pub fn draw<R: Rng>(&mut self, time: u32, score: u32, rng: &mut R)
+                                                                -> AtomType {
+        match self {
+            SpawnModel::Replay(atoms) if (time as usize) < atoms.len() => {
+                atoms[time as usize].clone()
+            },
+            SpawnModel::Simple | SpawnModel::Replay(_) => {
+                if BERN_02.sample(rng) {
+                    AtomType::Plus
+                }
+                else if BERN_02.sample(rng) {
+                    AtomType::Minus
+                }
+                else {
+                    AtomType::Atom(BINOM.sample(rng) as u8)
+                }
+            },
+            SpawnModel::Drawing(params) => {
+                let r = rng.gen::<f64>();
+                let c2 = params.plus + params.minus;
+                let c3 = c2 + params.dark_plus;
+                let c4 = c3 + params.neutrino;
+
+                if r < params.plus {
+                    AtomType::Plus
+                } else if r < c2 {
+                    AtomType::Minus
+                } else if score >= MIN_DPLUS_SCORE && r < c3 {
+                    AtomType::DarkPlus
+                } else if score >= MIN_WHITE_SCORE && r < c4 {
+                    AtomType::Neutrino
+                } else {
+                    AtomType::Atom(params.draw_regular(time, rng))
+                }
+            }
+        }
+    }
+}
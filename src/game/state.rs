@@ -1,25 +1,17 @@
 use std::{cmp::{Ordering, max}, usize};
 
-use super::AtomShape;
+use super::SpawnModel;
 
-use rand::Rng;
-use rand_distr::{Bernoulli, Binomial, Distribution};
-use sfml::graphics::Font;
-use lazy_static::lazy_static;
-
-lazy_static!{
-    static ref BINOM: Binomial = Binomial::new(7, 0.5).unwrap();
-    static ref BERN_02: Bernoulli = Bernoulli::new(0.2).unwrap();
-}
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 /// Represents anything we can get when "drawing a new atom", that is, it takes
 /// the `Atom`s, `Plus`, `Minus`, `DarkPlus` into account.
 ///
 /// Antimatter will not be taken into account.
 #[derive(Debug, Clone)]
-pub struct Atom<'a> {
-    pub t: AtomType, 
-    pub shape: Option<AtomShape<'a>>
+pub struct Atom {
+    pub t: AtomType,
 }
 
 #[derive(Clone, Debug, Eq)]
@@ -32,18 +24,62 @@ pub enum AtomType {
     Plus, 
 }
 
-const PLUS_CHANCE: f64 = 0.23;
-const MINUS_CHANCE: f64 = 0.05;
-const DPLUS_CHANCE: f64 = 0.0125;
-const WHITE_CHANCE: f64 = 1_f64 / 60_f64;
+/// A move the player can make with the incoming atom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    /// Shots the incoming atom in the gap just before the `i`-th atom
+    Place(usize),
+    /// Takes the `i`-th atom with the incoming Minus (removes it) or Neutrino
+    /// (copies it)
+    Pick(usize),
+    /// Turns the atom just taken with a Minus into a Plus
+    Convert,
+}
 
-const C2: f64 = PLUS_CHANCE + MINUS_CHANCE;
-const C3: f64 = C2 + DPLUS_CHANCE;
-const C4: f64 = C3 + WHITE_CHANCE;
+/// A fusion of two atoms around a Plus (or around the atom a chain has made
+/// so far), as recorded by `GameState::apply_traced`.
+#[derive(Clone, Debug)]
+pub struct Fusion {
+    /// Atoms of the ring just before the fusion
+    pub before: Vec<Atom>,
+    /// Index in `before` of the atom the two around fuse into
+    pub centre: usize,
+    /// Indices in `before` of the two atoms that fuse, before and after
+    /// `centre`
+    pub left: usize,
+    pub right: usize,
+    /// Atom they become
+    pub result: AtomType,
+    /// Score once they have fused
+    pub score: u32,
+}
 
-const MIN_DPLUS_SCORE: u32 = 750;
-const MIN_WHITE_SCORE: u32 = 1500;
+/// What a move would do, as found by `GameState::dry_run` without playing
+/// it.
+#[derive(Clone, Debug)]
+pub struct DryRun {
+    /// Atoms of the ring once the reactions are over
+    pub atoms: Vec<Atom>,
+    /// Atom in the centre afterwards: the one picked, a Plus once converted,
+    /// or `AtomType::None` when it has been placed (the next one is not
+    /// drawn)
+    pub incoming: Atom,
+    /// Whether the atom in the centre can then be converted into a Plus
+    pub minused: bool,
+    /// Points scored by the move
+    pub score_delta: u32,
+    /// Value of the highest atom that has reacted, 0 if none
+    pub max: u8,
+    /// The fusions the move causes, in the order they happen
+    pub fusions: Vec<Fusion>,
+}
 
+/// Maximum number of atoms the ring can hold, the game is over once it holds
+/// more.
+pub const MAX_ATOMS: usize = 18;
+
+/// Number of different moves, once numbered by `Move::index`.
+pub const ACTIONS: usize = 2 * MAX_ATOMS + 1;
 
 /// Represents the state of the game at some point.
 ///
@@ -61,39 +97,60 @@ const MIN_WHITE_SCORE: u32 = 1500;
 ///
 /// - `score`: the score so far
 ///
-/// - `pluses`: the indexes of the plus atoms on the board
+/// - `best`: the highest atom that has been on the ring so far
+///
+/// - `minused`: whether the incoming atom has just been taken with a Minus,
+///   and can thus be converted into a Plus
+///
+/// - `rng` and `spawn`: where the incoming atoms come from
 #[derive(Debug, Clone)]
-pub struct GameState<'a> {
-    pub atoms: Vec<Atom<'a>>,
+pub struct GameState {
+    pub atoms: Vec<Atom>,
     pub shift: usize,
     pub time: u32,
-    pub incoming: Atom<'a>,
+    pub incoming: Atom,
     pub score: u32,
-    pub shapes: Vec<AtomShape<'a>>
+    pub best: u8,
+    pub minused: bool,
+    pub rng: ChaCha8Rng,
+    pub spawn: SpawnModel,
 }
 
-impl<'a> Atom<'a> {
-    /// Construct an `Atom` without shape from its `AtomType`
-    pub fn from_type(t: AtomType) -> Self {
-        Self {
-            t: t,
-            shape: None
+impl Move {
+    /// Number of the move among the `ACTIONS` possible ones: the places
+    /// first, then the picks, then the conversion.
+    pub fn index(&self) -> usize {
+        match *self {
+            Move::Place(i) => i,
+            Move::Pick(i) => MAX_ATOMS + i,
+            Move::Convert => 2 * MAX_ATOMS,
         }
     }
 
-    /// Construct an `Atom` with a shape from its `AtomType`
-    pub fn from_type_with_shape(t: AtomType, font: &'a Font, 
-                                position: (f32, f32))        -> Self {
-        let mut shape = AtomShape::from_atom_type(&t, font);
-        shape.set_position(position);
+    /// The move numbered `index` by `Move::index`, if any
+    pub fn from_index(index: usize) -> Option<Self> {
+        if index < MAX_ATOMS {
+            Some(Move::Place(index))
+        } else if index < 2 * MAX_ATOMS {
+            Some(Move::Pick(index - MAX_ATOMS))
+        } else if index == 2 * MAX_ATOMS {
+            Some(Move::Convert)
+        } else {
+            None
+        }
+    }
+}
+
+impl Atom {
+    /// Construct an `Atom` from its `AtomType`
+    pub fn from_type(t: AtomType) -> Self {
         Self {
-            t: t,
-            shape: Some(shape)
+            t,
         }
     }
 
     /// Return the atomic number of contained atom if regular, else `0`
-    fn value(&self) -> u8 {
+    pub fn value(&self) -> u8 {
         match self.t {
             AtomType::Atom(z) => z,
             _ => 0
@@ -104,27 +161,38 @@ impl<'a> Atom<'a> {
     pub fn copy(other: &Self) -> Self {
         Self {
             t: other.t.clone(),
-            shape: None,
         }
     }
 }
 
-impl<'a> GameState<'a> {
-    /// Creates a new empty `GameState`.
-    fn new() -> Self {
+impl GameState {
+    /// Creates a new empty `GameState`, whose incoming atoms will be drawn
+    /// from `spawn` with a generator seeded by `seed`.
+    pub fn new(seed: u64, spawn: SpawnModel) -> Self {
         GameState {
             atoms: Vec::new(),
             shift: 0,
             time: 0,
             incoming: Atom::from_type(AtomType::None),
             score: 0,
-            shapes: Vec::new(),
+            best: 0,
+            minused: false,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            spawn,
         }
     }
 
-    /// Creates the `GameState` for the beginning of the game. 
+    /// Creates the `GameState` for the beginning of the game, with a random
+    /// seed and the default spawn model.
     pub fn start_game() -> Self {
-        let mut new = GameState::new();
+        let seed = rand::thread_rng().gen::<u64>();
+        GameState::start_game_seeded(seed, SpawnModel::default())
+    }
+
+    /// Creates the `GameState` for the beginning of the game, the whole game
+    /// being determined by `seed` and the moves played.
+    pub fn start_game_seeded(seed: u64, spawn: SpawnModel) -> Self {
+        let mut new = GameState::new(seed, spawn);
         new.atoms.extend_from_slice(&[
             Atom::from_type(AtomType::Atom(0)),
             Atom::from_type(AtomType::Atom(0)),
@@ -136,34 +204,146 @@ impl<'a> GameState<'a> {
 
     /// Draws the incoming atom (overwrites the current one)
     pub fn draw_incoming(&mut self) {
-        let r = rand::thread_rng().gen::<f64>();
+        let t = self.spawn.draw(self.time, self.score, &mut self.rng);
+        self.incoming = Atom::from_type(t);
+    }
 
-        if r < PLUS_CHANCE {
-            // plus atom drawn
-            self.incoming = Atom::from_type(AtomType::Plus);
-        } else if r < C2 {
-            // minus atom drawn
-            self.incoming = Atom::from_type(AtomType::Minus);
-        } else if self.score >= MIN_DPLUS_SCORE && r < C3 {
-            // dark plus atom drawn
-            self.incoming = Atom::from_type(AtomType::DarkPlus);
-        } else if self. score >= MIN_WHITE_SCORE && r < C4 {
-            // white (neutrino) atom drawn
-            self.incoming = Atom::from_type(AtomType::Neutrino);
-        } else {
-            // classic atom drawn
+    /// Returns `true` once the ring holds too many atoms.
+    pub fn is_over(&self) -> bool {
+        self.atoms.len() > MAX_ATOMS
+    }
 
+    /// Lists the moves that can be played with the incoming atom.
+    ///
+    /// Returns an empty vector when the game is over.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let n = self.atoms.len();
+        if self.is_over() {
+            return Vec::new();
         }
+        match self.incoming.t {
+            AtomType::Minus | AtomType::Neutrino if n > 0 => {
+                (0..n).map(Move::Pick).collect()
+            },
+            _ => {
+                let mut moves: Vec<Move> = (0..max(n, 1)).map(Move::Place)
+                                                         .collect();
+                if self.minused {
+                    moves.push(Move::Convert);
+                }
+                moves
+            }
+        }
+    }
 
-        if BERN_02.sample(&mut rand::thread_rng()) {
-            self.incoming = Atom::from_type(AtomType::Plus);
+    /// Whether `mv` is one of `legal_moves`, without listing them
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let n = self.atoms.len();
+        if self.is_over() {
+            return false;
         }
-        else if BERN_02.sample(&mut rand::thread_rng()) {
-            self.incoming = Atom::from_type(AtomType::Minus);
+        match (&self.incoming.t, mv) {
+            (AtomType::Minus | AtomType::Neutrino, mv) if n > 0 => {
+                matches!(mv, Move::Pick(i) if i < n)
+            },
+            (_, Move::Place(i)) => i < max(n, 1),
+            (_, Move::Convert) => self.minused,
+            (_, Move::Pick(_)) => false,
         }
-        else {
-            let v = BINOM.sample(&mut rand::thread_rng());
-            self.incoming = Atom::from_type(AtomType::Atom(v as u8));
+    }
+
+    /// Plays `mv`, drawing a new incoming atom if it has been consumed.
+    ///
+    /// Returns: the value of the highest atom that has reacted (may be 0 if
+    /// none)
+    pub fn apply(&mut self, mv: Move) -> u8 {
+        match mv {
+            Move::Place(k) => self.play(k),
+            Move::Pick(k) => self.pick(k),
+            Move::Convert => {
+                self.convert();
+                0
+            }
+        }
+    }
+
+    /// Plays `mv` as `apply`, also returning the fusions it caused, in the
+    /// order they happened.
+    pub fn apply_traced(&mut self, mv: Move) -> (u8, Vec<Fusion>) {
+        let mut trace = Some(Vec::new());
+        let max = match mv {
+            Move::Place(k) => {
+                let max = self.place_traced(k, &mut trace);
+                self.draw_incoming();
+                max
+            },
+            Move::Pick(k) => self.pick_traced(k, &mut trace),
+            Move::Convert => {
+                self.convert();
+                0
+            }
+        };
+        (max, trace.unwrap_or_default())
+    }
+
+    /// Copy of this state after `mv`, with no incoming atom drawn if it has
+    /// been consumed: the generator is not touched, so that agents can look
+    /// at the result of a move without peeking at the next atom.
+    ///
+    /// Returns the state and the value of the highest atom that has reacted.
+    pub fn afterstate(&self, mv: Move) -> (GameState, u8) {
+        let mut next = self.clone();
+        let max = match mv {
+            Move::Place(k) => {
+                let max = next.place(k);
+                next.incoming = Atom::from_type(AtomType::None);
+                max
+            },
+            _ => next.apply(mv)
+        };
+        (next, max)
+    }
+
+    /// What `mv` would do if played: the ring it leads to, the points it
+    /// scores and the fusions it causes. Nothing is played: the state, its
+    /// generator and its time are left as they are, and no incoming atom is
+    /// drawn.
+    ///
+    /// As with `apply`, a pick must be of an atom of the ring.
+    pub fn dry_run(&self, mv: Move) -> DryRun {
+        // A copy with a generator of its own, never drawn from, so that the
+        // spawn model does not have to be cloned either
+        let mut scratch = GameState {
+            atoms: self.atoms.clone(),
+            shift: self.shift,
+            time: self.time,
+            incoming: self.incoming.clone(),
+            score: self.score,
+            best: self.best,
+            minused: self.minused,
+            rng: ChaCha8Rng::seed_from_u64(0),
+            spawn: SpawnModel::Simple,
+        };
+        let mut trace = Some(Vec::new());
+        let max = match mv {
+            Move::Place(k) => {
+                let max = scratch.place_traced(k, &mut trace);
+                scratch.incoming = Atom::from_type(AtomType::None);
+                max
+            },
+            Move::Pick(k) => scratch.pick_traced(k, &mut trace),
+            Move::Convert => {
+                scratch.convert();
+                0
+            }
+        };
+        DryRun {
+            atoms: scratch.atoms,
+            incoming: scratch.incoming,
+            minused: scratch.minused,
+            score_delta: scratch.score - self.score,
+            max,
+            fusions: trace.unwrap_or_default(),
         }
     }
 
@@ -171,35 +351,103 @@ impl<'a> GameState<'a> {
     ///
     /// Returns: the value of the highest atom that has reacted (may be 0 if 
     /// none)
-    pub fn play(&mut self, k: u8) -> u8{
-        let i = k as usize;
-        self.atoms.insert(i, Atom::copy(&self.incoming));
+    pub fn play(&mut self, k: usize) -> u8 {
+        let max = self.place(k);
+        self.draw_incoming();
+        max
+    }
 
-        let n = self.atoms.len();
-        let l = if i + 1 >= n { 0 } else { i + 1 };
-        if self.atoms[l] < self.atoms[l] {
-            self.reorder();
+    /// Shot the incoming atom at the n-th position, without drawing the next
+    /// one.
+    ///
+    /// A Minus or a Neutrino shot on an empty ring is simply lost.
+    fn place(&mut self, i: usize) -> u8 {
+        self.place_traced(i, &mut None)
+    }
+
+    /// `place`, recording the fusions in `trace` if given
+    fn place_traced(&mut self, i: usize, trace: &mut Option<Vec<Fusion>>)
+                                                                    -> u8 {
+        self.minused = false;
+        let special = self.incoming.t == AtomType::Minus ||
+                      self.incoming.t == AtomType::Neutrino;
+        if !(special && self.atoms.is_empty()) {
+            self.atoms.insert(i, Atom::copy(&self.incoming));
+
+            let n = self.atoms.len();
+            let l = if i + 1 >= n { 0 } else { i + 1 };
+            if self.atoms[l] < self.atoms[l] {
+                self.reorder();
+            }
         }
 
         self.time += 1;
-        let max = self.update_plus();
-        self.draw_incoming();
+        let max = self.update_plus_traced(trace);
+        self.update_best();
         max
     }
 
-    /// Makes the reactions with pluses atoms
+    /// Takes the `k`-th atom with the incoming Minus or Neutrino: it becomes
+    /// the incoming atom, and is removed from the ring in the case of a Minus.
+    ///
+    /// Returns: the value of the highest atom that has reacted (may be 0 if 
+    /// none)
+    pub fn pick(&mut self, k: usize) -> u8 {
+        self.pick_traced(k, &mut None)
+    }
+
+    /// `pick`, recording the fusions in `trace` if given
+    fn pick_traced(&mut self, k: usize, trace: &mut Option<Vec<Fusion>>)
+                                                                    -> u8 {
+        let is_neutrino = self.incoming.t == AtomType::Neutrino;
+        self.incoming = Atom::copy(&self.atoms[k]);
+        if !is_neutrino {
+            self.atoms.remove(k);
+            self.minused = true;
+        }
+        let max = self.update_plus_traced(trace);
+        self.update_best();
+        max
+    }
+
+    /// Turns the incoming atom into a Plus, if it has just been taken with a
+    /// Minus.
+    pub fn convert(&mut self) {
+        if self.minused {
+            self.incoming = Atom::from_type(AtomType::Plus);
+            self.minused = false;
+        }
+    }
+
+    /// Keeps `best` up to date with the atoms on the ring
+    fn update_best(&mut self) {
+        for atom in &self.atoms {
+            if atom.value() > self.best {
+                self.best = atom.value();
+            }
+        }
+    }
+
+    /// Makes the reactions with pluses (and dark pluses) atoms. A Dark Plus
+    /// fuses its neighbours whatever they are, as `react` has it.
     ///
     /// Returns: the value of the highest atom that has reacted (may be 0 if 
     /// none)
     pub fn update_plus(&mut self) -> u8 {
+        self.update_plus_traced(&mut None)
+    }
+
+    /// `update_plus`, recording the fusions in `trace` if given
+    fn update_plus_traced(&mut self, trace: &mut Option<Vec<Fusion>>) -> u8 {
         let mut reaction = true;
         let mut max: u8 = 0;
         while reaction {
             reaction = false;
             let mut i = 0;
             while i < self.atoms.len() {
-                if self.atoms[i].t == AtomType::Plus {
-                    let m = self.react(i);
+                if self.atoms[i].t == AtomType::Plus ||
+                   self.atoms[i].t == AtomType::DarkPlus {
+                    let m = self.react_traced(i, trace);
                     if  m > 0 {
                         reaction = true;
                         if m > max { max = m; }
@@ -212,7 +460,13 @@ impl<'a> GameState<'a> {
     }
 
     /// Attempts to react atom at index k
-    pub fn react(&mut self, mut k: usize) -> u8 {
+    pub fn react(&mut self, k: usize) -> u8 {
+        self.react_traced(k, &mut None)
+    }
+
+    /// `react`, recording the fusions in `trace` if given
+    fn react_traced(&mut self, mut k: usize, trace: &mut Option<Vec<Fusion>>)
+                                                                    -> u8 {
         let mut n = self.atoms.len();
         let mut k_prev = safe(k, -1, n);
         let mut k_next = safe(k, 1,  n);
@@ -273,6 +527,16 @@ impl<'a> GameState<'a> {
                 else            { z_in + 1 }
             };
 
+            if let Some(trace) = trace {
+                trace.push(Fusion {
+                    before: self.atoms.clone(),
+                    centre: k,
+                    left: k_prev,
+                    right: k_next,
+                    result: AtomType::Atom(z_f),
+                    score: self.score,
+                });
+            }
             self.atoms[k] = Atom::from_type(AtomType::Atom(z_f));
             self.atoms.remove(k_next);
             self.atoms.remove(safe(k, -1, n-1));
@@ -291,7 +555,9 @@ impl<'a> GameState<'a> {
             // println!("@k_prev: {:?}", self.atoms[k_prev].t);
             // println!("@k     : {:?}", self.atoms[k].t);
             // println!("@k_next: {:?}", self.atoms[k_next].t);
-            // if k_next == k_prev { println!("breaking"); break; }
+            // With two atoms left, both neighbours are the same atom, which
+            // must not fuse with itself
+            if k_next == k_prev { break; }
         }
         final_value
     }
@@ -424,28 +690,28 @@ impl std::cmp::Ord for AtomType {
     }
 }
 
-impl<'a> std::cmp::PartialEq for Atom<'a> {
+impl std::cmp::PartialEq for Atom {
     fn eq(&self, other: &Self) -> bool {
         self.t.eq(&other.t)
     }
 }
 
-impl<'a> std::cmp::PartialOrd for Atom<'a> {
+impl std::cmp::PartialOrd for Atom {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<'a> std::cmp::Ord for Atom<'a> {
+impl std::cmp::Ord for Atom {
     fn cmp(&self, other: &Self) -> Ordering {
         self.t.cmp(&other.t)
     }
 }
 
-impl<'a> std::cmp::Eq for Atom<'a> { }
+impl std::cmp::Eq for Atom { }
 
 
-impl<'a> std::cmp::PartialEq for GameState<'a> {
+impl std::cmp::PartialEq for GameState {
     /// Symmetrical states are considered equals.
     fn eq(&self, other: &Self) -> bool {
         let n = self.atoms.len();
@@ -0,0 +1,380 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::{ATOMS_COLORS, ATOMS_SYMBOLS, AtomType, SYMBOL_SIZE, TITLE_SIZE,
+            parse_atom};
+use crate::invalid_data;
+
+/// A colour, as red, green and blue components
+pub type Rgb = (u8, u8, u8);
+
+/// Reads a colour written `#rrggbb`
+pub fn parse_color(s: &str) -> Option<Rgb> {
+    let hex = s.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((component(0)?, component(2)?, component(4)?))
+}
+
+/// How an atom is drawn: a disc of `color` with `symbol` on it
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomStyle {
+    pub color: Rgb,
+    pub symbol: String,
+}
+
+impl AtomStyle {
+    fn new(color: Rgb, symbol: &str) -> Self {
+        Self { color, symbol: symbol.to_string() }
+    }
+}
+
+/// The look of the window: its colours, the symbols of the atoms, and the
+/// font and sizes of the texts.
+///
+/// Themes are read from files in a subset of TOML (see `Theme::parse`),
+/// whose values replace those of the default theme, the look of the game
+/// before themes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub background: Rgb,
+    /// Circle the atoms are placed on
+    pub ring: Rgb,
+    /// Score and name of the best atom
+    pub title: Rgb,
+    /// Status lines, hints and lines shown over the board
+    pub text: Rgb,
+    /// Symbols on the atoms
+    pub symbol: Rgb,
+    /// Mark of the move hinted
+    pub hint: Rgb,
+    pub plus: AtomStyle,
+    pub minus: AtomStyle,
+    pub dark_plus: AtomStyle,
+    pub neutrino: AtomStyle,
+    /// Elements, by atomic number minus one
+    pub elements: Vec<AtomStyle>,
+    pub font: PathBuf,
+    /// Character size of the symbols of atoms, in a `WIDTH` by `HEIGHT`
+    /// window
+    pub symbol_size: f32,
+    /// Character size of the score and of the name of the best atom, in a
+    /// `WIDTH` by `HEIGHT` window
+    pub title_size: f32,
+}
+
+impl Default for Theme {
+    /// White texts and a yellow score on black, red Pluses, blue Minuses,
+    /// the elements in the colours of `ATOMS_COLORS`, and the Aaargh font
+    fn default() -> Self {
+        let elements = ATOMS_COLORS.iter().zip(ATOMS_SYMBOLS.iter())
+            .map(|(c, s)| {
+                AtomStyle::new(parse_color(c).unwrap_or((128, 128, 128)), s)
+            }).collect();
+        Self {
+            name: "default".to_string(),
+            background: (0, 0, 0),
+            ring: (0, 0, 0),
+            title: (255, 255, 0),
+            text: (255, 255, 255),
+            symbol: (0, 0, 0),
+            hint: (255, 255, 0),
+            plus: AtomStyle::new((255, 0, 0), "+"),
+            minus: AtomStyle::new((0, 0, 255), "-"),
+            dark_plus: AtomStyle::new((0, 0, 0), "+"),
+            neutrino: AtomStyle::new((255, 255, 255), " "),
+            elements,
+            font: PathBuf::from("resources/Aaargh.ttf"),
+            symbol_size: SYMBOL_SIZE,
+            title_size: TITLE_SIZE,
+        }
+    }
+}
+
+/// A value in a theme file
+#[derive(Clone, Debug)]
+enum Value {
+    Text(String),
+    Number(f64),
+}
+
+/// Reads a TOML basic string starting after its opening quote, returning
+/// it and what follows its closing quote
+fn parse_string(s: &str) -> Result<(String, &str), String> {
+    let mut text = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((text, &s[i + 1..])),
+            '\\' => text.push(match chars.next().map(|(_, c)| c) {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some(c) => {
+                    return Err(format!("unknown escape `\\{}` in a string, \
+                                        only \\n, \\t, \\\" and \\\\ are \
+                                        read", c))
+                },
+                None => break
+            }),
+            c => text.push(c),
+        }
+    }
+    Err("string without its closing quote".to_string())
+}
+
+/// Reads a key, bare or quoted, returning it and what follows it
+fn parse_key(s: &str) -> Result<(String, &str), String> {
+    let s = s.trim_start();
+    if let Some(quoted) = s.strip_prefix('"') {
+        return parse_string(quoted);
+    }
+    let end = s.find(|c: char| {
+        !(c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }).unwrap_or(s.len());
+    if end == 0 {
+        return Err("expected a key, bare or in double quotes".to_string());
+    }
+    Ok((s[..end].to_string(), &s[end..]))
+}
+
+/// Reads a value, a string or a number, followed by an optional comment
+fn parse_value(s: &str) -> Result<Value, String> {
+    let s = s.trim();
+    let (value, rest) = match s.chars().next() {
+        Some('"') if s.starts_with("\"\"\"") => {
+            return Err(unsupported("multi-line strings"));
+        },
+        Some('"') => {
+            let (text, rest) = parse_string(&s[1..])?;
+            (Value::Text(text), rest)
+        },
+        Some('\'') => return Err(unsupported("literal strings")),
+        Some('[') => return Err(unsupported("arrays")),
+        Some('{') => return Err(unsupported("inline tables")),
+        _ => {
+            let end = s.find('#').unwrap_or(s.len());
+            let number = s[..end].trim();
+            if number == "true" || number == "false" {
+                return Err(unsupported("booleans"));
+            }
+            let value = number.replace('_', "").parse().map_err(|_| {
+                format!("expected a string in double quotes or a number, \
+                         not `{}`", number)
+            })?;
+            (Value::Number(value), &s[end..])
+        }
+    };
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(value)
+    } else {
+        Err(format!("unexpected `{}` after the value", rest))
+    }
+}
+
+/// Error for a TOML construct themes do without
+fn unsupported(what: &str) -> String {
+    format!("{} are not read in theme files, which only hold strings and \
+             numbers", what)
+}
+
+/// Reads the `key = value` pairs of the subset of TOML used by themes, with
+/// the table each is in (`""` before the first one) and its line number.
+///
+/// Only tables with a single name, bare or quoted keys, basic strings and
+/// decimal numbers are read: no dotted keys, arrays, inline tables,
+/// booleans, dates, literal or multi-line strings.
+fn parse_toml(content: &str)
+                    -> io::Result<Vec<(usize, String, String, Value)>> {
+    let mut pairs = Vec::new();
+    let mut table = String::new();
+    for (n, line) in content.lines().enumerate() {
+        let at_line = |msg: String| {
+            invalid_data(format!("line {}: {}", n + 1, msg))
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            if header.starts_with('[') {
+                return Err(at_line(unsupported("arrays of tables")));
+            }
+            let (name, rest) = parse_key(header).map_err(at_line)?;
+            let rest = rest.trim_start();
+            if rest.starts_with('.') {
+                return Err(at_line(unsupported("dotted keys")));
+            }
+            let rest = rest.strip_prefix(']').ok_or_else(|| {
+                at_line(format!("expected `]` after the table `{}`", name))
+            })?.trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(at_line(format!("unexpected `{}` after the table \
+                                            `{}`", rest, name)));
+            }
+            table = name;
+            continue;
+        }
+        let (key, rest) = parse_key(line).map_err(at_line)?;
+        let rest = rest.trim_start();
+        if rest.starts_with('.') {
+            return Err(at_line(unsupported("dotted keys")));
+        }
+        let rest = rest.strip_prefix('=').ok_or_else(|| {
+            at_line(format!("expected `=` after `{}`", key))
+        })?;
+        let value = parse_value(rest).map_err(|e| {
+            at_line(format!("{}: {}", key, e))
+        })?;
+        pairs.push((n + 1, table.clone(), key, value));
+    }
+    Ok(pairs)
+}
+
+impl Theme {
+    /// Reads a theme from a TOML file, see `Theme::parse`. A relative font
+    /// path is taken from the current directory, as the resources are.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a theme file, written in the subset of TOML
+    /// read by `parse_toml`, e.g.
+    ///
+    /// ```toml
+    /// name = "dusk"
+    /// font = "resources/Aaargh.ttf"
+    /// symbol_size = 12
+    /// title_size = 20
+    ///
+    /// [colors]
+    /// background = "#101020"
+    /// ring = "#303050"
+    /// plus = "#ff4040"
+    ///
+    /// [elements]
+    /// H = "#63b9d5"
+    /// He = "#d1c991"
+    ///
+    /// [symbols]
+    /// neutrino = "Nu"
+    /// ```
+    ///
+    /// The keys of `[colors]` are `background`, `ring`, `title` (the score
+    /// and the best atom), `text`, `symbol` (on the atoms), `hint`, `plus`,
+    /// `minus`, `dark_plus` and `neutrino`. Elements are keyed by their
+    /// symbol or atomic number, in `[elements]` for their colour and in
+    /// `[symbols]` for their symbol, along with the special atoms. What is
+    /// not given is as in the default theme.
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut theme = Self::default();
+        for (line, table, key, value) in parse_toml(content)? {
+            let bad = || {
+                let expected = match (table.as_str(), key.as_str()) {
+                    ("colors" | "elements", _) => {
+                        "a colour written \"#rrggbb\""
+                    },
+                    ("", "symbol_size" | "title_size") => "a positive number",
+                    _ => "a string",
+                };
+                invalid_data(format!("line {}: {} should be {}", line,
+                                     place(&table, &key), expected))
+            };
+            let unknown = || {
+                invalid_data(format!("line {}: unknown key {}", line,
+                                     place(&table, &key)))
+            };
+            let text = match &value {
+                Value::Text(text) => Some(text.as_str()),
+                Value::Number(_) => None,
+            };
+            let color = || text.and_then(parse_color).ok_or_else(bad);
+            match (table.as_str(), key.as_str(), &value) {
+                ("", "name", Value::Text(name)) => theme.name = name.clone(),
+                ("", "font", Value::Text(path)) => theme.font = path.into(),
+                ("", "symbol_size", &Value::Number(size)) if size > 0.0 => {
+                    theme.symbol_size = size as f32;
+                },
+                ("", "title_size", &Value::Number(size)) if size > 0.0 => {
+                    theme.title_size = size as f32;
+                },
+                ("colors", "background", _) => theme.background = color()?,
+                ("colors", "ring", _) => theme.ring = color()?,
+                ("colors", "title", _) => theme.title = color()?,
+                ("colors", "text", _) => theme.text = color()?,
+                ("colors", "symbol", _) => theme.symbol = color()?,
+                ("colors", "hint", _) => theme.hint = color()?,
+                ("colors", name, _) => {
+                    theme.special(name).ok_or_else(unknown)?
+                         .color = color()?;
+                },
+                ("elements", name, _) => {
+                    theme.element(name).ok_or_else(unknown)?
+                         .color = color()?;
+                },
+                ("symbols", name, _) => {
+                    let symbol = text.ok_or_else(bad)?.to_string();
+                    match theme.special(name) {
+                        Some(style) => style.symbol = symbol,
+                        None => {
+                            theme.element(name)
+                                 .ok_or_else(unknown)?
+                                 .symbol = symbol;
+                        }
+                    }
+                },
+                ("", "name", _) | ("", "font", _) | ("", "symbol_size", _) |
+                ("", "title_size", _) => return Err(bad()),
+                _ => return Err(unknown())
+            }
+        }
+        Ok(theme)
+    }
+
+    /// The style of the special atom named `name` in a theme file
+    fn special(&mut self, name: &str) -> Option<&mut AtomStyle> {
+        match name {
+            "plus" => Some(&mut self.plus),
+            "minus" => Some(&mut self.minus),
+            "dark_plus" => Some(&mut self.dark_plus),
+            "neutrino" => Some(&mut self.neutrino),
+            _ => None
+        }
+    }
+
+    /// The style of the element written `name`, as a symbol or an atomic
+    /// number
+    fn element(&mut self, name: &str) -> Option<&mut AtomStyle> {
+        match parse_atom(name) {
+            Some(AtomType::Atom(z)) => self.elements.get_mut(z as usize),
+            _ => None
+        }
+    }
+
+    /// How `atom` is drawn
+    pub fn atom(&self, atom: &AtomType) -> &AtomStyle {
+        match atom {
+            AtomType::Plus => &self.plus,
+            AtomType::Minus => &self.minus,
+            AtomType::DarkPlus => &self.dark_plus,
+            AtomType::Neutrino => &self.neutrino,
+            AtomType::Atom(z) => &self.elements[*z as usize],
+            AtomType::None => panic!("uninitialized atom"),
+        }
+    }
+}
+
+/// How the key `key` of `table` is told in errors
+fn place(table: &str, key: &str) -> String {
+    if table.is_empty() {
+        format!("`{}`", key)
+    } else {
+        format!("`{}` in [{}]", key, table)
+    }
+}
@@ -0,0 +1,252 @@
+//! High scores and summaries of the games finished, kept in a local data
+//! directory so that they survive the window.
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::{ATOMS_NAMES, AtomType, GameState, atom_symbol, parse_atom};
+use crate::invalid_data;
+
+/// Number of games kept in the high-score table
+pub const TOP_SCORES: usize = 10;
+
+const SCORES_FILE: &str = "scores.csv";
+const HISTORY_FILE: &str = "history.csv";
+const HEADER: &str = "date,mode,seed,score,best,time";
+
+/// A finished game.
+///
+/// Contains:
+///
+/// - `date`: when it ended, in seconds since the Unix epoch
+///
+/// - `mode`: how it was played, e.g. `window`, `window edited`, `tui` or
+///   `autoplay <agent>`
+///
+/// - `seed`: the seed its atoms were drawn with, if it was neither edited
+///   nor cheated
+///
+/// - `score`, `best` and `time`: as in its last `GameState`
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameSummary {
+    pub date: u64,
+    pub mode: String,
+    pub seed: Option<u64>,
+    pub score: u32,
+    pub best: u8,
+    pub time: u32,
+}
+
+impl GameSummary {
+    /// Summary of the game ending now in `state`
+    pub fn new(state: &GameState, mode: &str, seed: Option<u64>) -> Self {
+        let date = SystemTime::now().duration_since(UNIX_EPOCH)
+                                    .map(|d| d.as_secs()).unwrap_or(0);
+        Self {
+            date,
+            mode: mode.to_string(),
+            seed,
+            score: state.score,
+            best: state.best,
+            time: state.time,
+        }
+    }
+
+    /// The game as a CSV row, see `HEADER`
+    fn to_csv(&self) -> String {
+        let seed = self.seed.map(|s| s.to_string()).unwrap_or_default();
+        format!("{},{},{},{},{},{}", self.date, csv_field(&self.mode), seed,
+                self.score, atom_symbol(&AtomType::Atom(self.best)),
+                self.time)
+    }
+
+    /// Reads a row written by `to_csv`
+    fn parse(line: &str) -> io::Result<Self> {
+        let fields = split_csv(line);
+        let bad = || invalid_data(format!("bad game `{}`", line));
+        if fields.len() != 6 {
+            return Err(bad());
+        }
+        let seed = match fields[2].as_str() {
+            "" => None,
+            s => Some(s.parse().map_err(|_| bad())?),
+        };
+        let best = match parse_atom(&fields[4]) {
+            Some(AtomType::Atom(z)) => z,
+            _ => return Err(bad())
+        };
+        Ok(Self {
+            date: fields[0].parse().map_err(|_| bad())?,
+            mode: fields[1].clone(),
+            seed,
+            score: fields[3].parse().map_err(|_| bad())?,
+            best,
+            time: fields[5].parse().map_err(|_| bad())?,
+        })
+    }
+}
+
+/// Quotes a CSV field if needed
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Splits a CSV row into its fields, unquoting them
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// Directory the scores are kept in: `$IATOMAS_DATA` if set, else
+/// `iatomas` in `$XDG_DATA_HOME` or `~/.local/share`, else `.iatomas` in the
+/// current directory.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("IATOMAS_DATA") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return PathBuf::from(dir).join("iatomas");
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".local/share/iatomas"),
+        None => PathBuf::from(".iatomas")
+    }
+}
+
+/// Writes `content` to `path` through a temporary file renamed over it, so
+/// that the file holds either its old content or the new one, whatever
+/// happens meanwhile.
+pub(crate) fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// Reads the games in the CSV file at `path`, none if there is no such file
+fn read_games(path: &Path) -> io::Result<Vec<GameSummary>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e)
+    };
+    content.lines().skip(1)
+                   .filter(|line| !line.trim().is_empty())
+                   .map(GameSummary::parse)
+                   .collect()
+}
+
+/// The games in CSV, with a header
+fn games_csv(games: &[GameSummary]) -> String {
+    let mut csv = format!("{}\n", HEADER);
+    for game in games {
+        csv.push_str(&game.to_csv());
+        csv.push('\n');
+    }
+    csv
+}
+
+/// The high scores and the summaries of all the games finished, as kept in
+/// a directory: `scores.csv` holds the `TOP_SCORES` best games, best first,
+/// and `history.csv` every game in the order they ended.
+#[derive(Clone, Debug)]
+pub struct History {
+    dir: PathBuf,
+    pub top: Vec<GameSummary>,
+    pub games: Vec<GameSummary>,
+}
+
+impl History {
+    /// Reads the scores kept in `dir`, which need not exist yet
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let top = read_games(&dir.join(SCORES_FILE))?;
+        let games = read_games(&dir.join(HISTORY_FILE))?;
+        Ok(Self { dir, top, games })
+    }
+
+    /// Adds `game` to the history, and to the high scores if it is good
+    /// enough, then saves them. Games without a seed, whose position was
+    /// edited or cheated, are not ranked.
+    ///
+    /// Returns the rank of the game among the high scores, from 0, if it is
+    /// one of them.
+    pub fn record(&mut self, game: GameSummary) -> io::Result<Option<usize>> {
+        self.games.push(game.clone());
+        // Ties go to the earlier games
+        let rank = self.top.iter().take_while(|g| g.score >= game.score)
+                                  .count();
+        let rank = if rank < TOP_SCORES && game.seed.is_some() {
+            self.top.insert(rank, game);
+            self.top.truncate(TOP_SCORES);
+            Some(rank)
+        } else {
+            None
+        };
+        self.save()?;
+        Ok(rank)
+    }
+
+    /// Writes the scores in the directory, creating it if needed
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.dir.join(SCORES_FILE), &games_csv(&self.top))?;
+        write_atomically(&self.dir.join(HISTORY_FILE),
+                         &games_csv(&self.games))
+    }
+}
+
+/// Writes `date`, in seconds since the Unix epoch, as `YYYY-MM-DD HH:MM`
+/// (UTC)
+pub fn format_date(date: u64) -> String {
+    let days = (date / 86_400) as i64;
+    let minutes = (date % 86_400) / 60;
+    // Civil date of a number of days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60,
+            minutes % 60)
+}
+
+/// Lines listing `games`, ranked: score, best atom, date, mode and seed
+pub fn table(games: &[GameSummary]) -> Vec<String> {
+    games.iter().enumerate().map(|(i, game)| {
+        let best = ATOMS_NAMES.get(game.best as usize).copied()
+                              .unwrap_or("?");
+        let seed = game.seed.map(|s| format!("  seed {}", s))
+                            .unwrap_or_default();
+        format!("{:>2}. {:>6}  {:<10} {}  {}{}", i + 1, game.score, best,
+                format_date(game.date), game.mode, seed)
+    }).collect()
+}
@@ -0,0 +1,23 @@
+use std::io;
+
+pub mod agent;
+pub mod autoplay;
+pub mod autosave;
+pub mod bench;
+pub mod env;
+pub mod game;
+pub mod history;
+pub mod nn;
+pub mod protocol;
+pub mod spawn;
+#[cfg(feature = "python")]
+mod python;
+
+/// An `InvalidData` error saying `msg`, for the files and messages that
+/// cannot be read
+pub fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub const WIDTH: f32 = 400.0;
+pub const HEIGHT: f32 = 400.0;
@@ -1,75 +1,490 @@
-mod game;
-mod agent;
+#[cfg(feature = "gui")]
+mod bindings;
+#[cfg(feature = "gui")]
+mod editor;
+#[cfg(feature = "gui")]
+mod themes;
+mod cli;
+mod tui;
 
+#[cfg(feature = "gui")]
+use std::path::Path;
 
-use game::{AtomType, Board};
+#[cfg(feature = "gui")]
+use iatomas::{HEIGHT, WIDTH};
+#[cfg(feature = "gui")]
+use iatomas::agent::{self, Hinter};
+#[cfg(feature = "gui")]
+use iatomas::autoplay::{Autoplay, Control, Turn};
+#[cfg(feature = "gui")]
+use iatomas::autosave::{self, SavedGame};
+#[cfg(feature = "gui")]
+use iatomas::game::{AtomType, Board, GameState, Layout, Move, sfml_color};
+#[cfg(feature = "gui")]
+use iatomas::history::{self, GameSummary, History};
+#[cfg(feature = "gui")]
+use bindings::{Action, Bindings};
+#[cfg(feature = "gui")]
+use editor::Editor;
+#[cfg(feature = "gui")]
+use themes::Themes;
 
+#[cfg(feature = "gui")]
 use sfml::{
-    graphics::{Color, Font, RenderTarget, RenderWindow}, 
-    window::{Event, Key, Style, mouse::Button}
+    graphics::{FloatRect, RenderTarget, RenderWindow, View},
+    system::Clock,
+    window::{Event, Key, Style, VideoMode, mouse::Button}
 };
 
-const WIDTH: f32 = 400.0;
-const HEIGHT: f32 = 400.0;
-
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = args.first().filter(|a| !a.starts_with("--"));
+    if let Some(command) = command {
+        let result = cli::Options::parse(&args[1..]).and_then(|options| {
+            match command.as_str() {
+                "tune" => cli::tune(&options),
+                "train-ntuple" => cli::train_ntuple(&options),
+                "train-mlp" => cli::train_mlp(&options),
+                "engine" => cli::engine(&options),
+                "play-engine" => cli::play_engine(&options),
+                "bench" => cli::bench(&options),
+                "fit" => cli::fit(&options),
+                "spawn-check" => cli::spawn_check(&options),
+                "spawn-posterior" => cli::spawn_posterior(&options),
+                "tui" => tui::tui(&options),
+                "scores" => cli::scores(&options),
+                _ => Err(format!("unknown command `{}`", command))
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("iatomas {}: {}", command, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let result = cli::Options::parse(&args).and_then(|o| run_window(&o));
+    if let Err(e) = result {
+        eprintln!("iatomas: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Ratio of the pixel density of the screen to a usual one. SFML does not
+/// give it, so it is guessed from the resolution of the desktop, taking
+/// 1080 lines as usual.
+#[cfg(feature = "gui")]
+fn screen_scale() -> f32 {
+    (VideoMode::desktop_mode().height as f32 / 1080.0).max(1.0)
+}
+
+/// Seconds of play between two saves of the game in progress
+#[cfg(feature = "gui")]
+const AUTOSAVE_PERIOD: f32 = 30.0;
+
+/// What `key` asks of the autoplay: Space pauses and resumes, S or Right
+/// plays a single move while paused, Up or `+` doubles the speed and Down or
+/// `-` halves it.
+#[cfg(feature = "gui")]
+fn autoplay_control(key: Key) -> Option<Control> {
+    match key {
+        Key::Space => Some(Control::Pause),
+        Key::S | Key::Right => Some(Control::Step),
+        Key::Up | Key::Add => Some(Control::Faster),
+        Key::Down | Key::Subtract => Some(Control::Slower),
+        _ => None
+    }
+}
+
+/// Debug cheats, forcing the incoming atom. Returns `true` if `key` is one
+/// of them.
+#[cfg(feature = "gui")]
+fn cheat(board: &mut Board, key: Key) -> bool {
+    match key {
+        Key::P => board.set_state_incoming(AtomType::Plus),
+        Key::M => board.set_state_incoming(AtomType::Minus),
+        Key::D => board.set_state_incoming(AtomType::DarkPlus),
+        Key::W => board.set_state_incoming(AtomType::Neutrino),
+        _ => return false
+    }
+    true
+}
+
+/// Saves the game on `board` in `dir`, played with the spawn model `spawn`
+/// and a generator created with `seed`, so that it can be resumed. Removes
+/// the save instead once the game is over.
+#[cfg(feature = "gui")]
+fn autosave(dir: &Path, board: &Board, spawn: &str, seed: u64,
+            edited: bool) {
+    let result = if board.state().is_over() {
+        autosave::remove(dir)
+    } else {
+        SavedGame {
+            state: board.state().clone(),
+            spawn: spawn.to_string(),
+            seed,
+            edited,
+            moves: board.moves().to_vec(),
+        }.save(dir)
+    };
+    if let Err(e) = result {
+        eprintln!("iatomas: cannot save the game in {}: {}", dir.display(),
+                  e);
+    }
+}
+
+/// Lines offering to resume `saved`
+#[cfg(feature = "gui")]
+fn resume_overlay(saved: &SavedGame) -> Vec<String> {
+    vec![
+        "Resume the last game?".to_string(),
+        format!("score {}, time {}", saved.state.score, saved.state.time),
+        String::new(),
+        "Y or Enter: resume".to_string(),
+        "N or Escape: new game".to_string(),
+    ]
+}
+
+/// Lines showing the high scores of `history` under `heading`
+#[cfg(feature = "gui")]
+fn scores_overlay(history: &Option<History>, mut heading: Vec<String>)
+                                                            -> Vec<String> {
+    heading.push("High scores".to_string());
+    match history {
+        Some(history) if history.top.is_empty() => {
+            heading.push("none yet".to_string());
+        },
+        Some(history) => heading.extend(history::table(&history.top)),
+        None => heading.push("not recorded".to_string()),
+    }
+    heading
+}
+
+/// Opens the game window, until it is closed.
+///
+/// Options:
+///
+/// - `--hint <agent>`: the agent whose recommendation H shows (`heuristic`
+///   by default, see `agent::from_spec`)
+///
+/// - `--autoplay <agent>`: lets the agent play games one after the other
+///   instead of the player, see `Autoplay` and `autoplay_control`
+///
+/// - `--seed <n>`: seed of the games of `--autoplay` (0 by default)
+///
+/// - `--spawn <model>`: spawn model of the games
+///
+/// - `--position <file>`: where the editor, toggled by E, saves and loads
+///   positions (`position.txt` by default), see `Editor`
+///
+/// - `--keys <file>`: the keys of the actions, see `Bindings::load`
+///
+/// - `--theme <theme>`: the look of the window, `default`, the name of a
+///   theme in `resources/themes` or a theme file (see `Theme::parse`). T
+///   goes through the others, and the file is read again when it changes.
+///
+/// - `--debug`: lets P, M, D and W force the incoming atom to a Plus, a
+///   Minus, a Dark Plus or a Neutrino
+///
+/// The games finished are recorded with the high scores (see
+/// `history::History`), which show once a game is over. The game in progress
+/// is saved every `AUTOSAVE_PERIOD` seconds and when the window is closed
+/// (see `autosave::SavedGame`), and offered to be resumed the next time.
+#[cfg(feature = "gui")]
+fn run_window(options: &cli::Options) -> Result<(), String> {
+    let hint_agent = agent::from_spec(options.get("hint")
+                                             .unwrap_or("heuristic"), 0)?;
+    let mut hinter = Hinter::new(hint_agent);
+    // Version of the board the hint asked for is about
+    let mut hint_version = None;
+    let spawn = options.spawn()?;
+    let spawn_spec = options.get("spawn").unwrap_or("simple");
+    let mut autoplay = match options.get("autoplay") {
+        Some(spec) => {
+            let seed = options.parse_or("seed", 0u64)?;
+            let agent = agent::from_spec(spec, seed)?;
+            Some(Autoplay::new(agent, spawn.clone(), seed))
+        },
+        None => None
+    };
+    let mode = match options.get("autoplay") {
+        Some(spec) => format!("autoplay {}", spec),
+        None => "window".to_string()
+    };
+    let dir = history::data_dir();
+    let mut history = match History::open(&dir) {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("iatomas: scores not recorded, cannot read {}: {}",
+                      dir.display(), e);
+            None
+        }
+    };
+    // Game saved when the window was last closed, until it is resumed or
+    // declined
+    let mut offer = None;
+    if autoplay.is_none() {
+        match SavedGame::load(&dir) {
+            Ok(Some(saved)) if saved.spawn != spawn_spec => {
+                eprintln!("iatomas: not resuming the game saved in {}, \
+                           played with `--spawn {}`", dir.display(),
+                          saved.spawn);
+            },
+            Ok(Some(saved)) if !saved.state.is_over() => offer = Some(saved),
+            Ok(_) => {},
+            Err(e) => eprintln!("iatomas: cannot resume the game saved in \
+                                 {}: {}", dir.display(), e),
+        }
+    }
+    // Seed of the generator of the game played
+    let mut rng_seed = rand::random::<u64>();
+    // Seed the game is recorded with, unless its position has been edited
+    let mut seed = Some(rng_seed);
+    // Version of the board last saved, and seconds since
+    let mut saved_version = None;
+    let mut since_save = 0.0;
+    // Version of the board whose game over has been recorded
+    let mut recorded = None;
+    let bindings = match options.get("keys") {
+        Some(path) => Bindings::load(path).map_err(|e| {
+            format!("cannot read the bindings in {}: {}", path, e)
+        })?,
+        None => Bindings::default()
+    };
+    let debug = options.flag("debug");
+    let themes = Themes::new(options.get("theme"))?;
+    let (theme, font) = themes.load()?;
+    let mut editor = Editor::new(options.get("position")
+                                        .unwrap_or("position.txt").into());
 
     let mut window = RenderWindow::new(
         (WIDTH as u32, HEIGHT as u32),
         "IAtomas",
-        Style::CLOSE,
+        Style::DEFAULT,
         &Default::default(),
     );
 
     window.set_mouse_cursor_visible(true);
     window.set_framerate_limit(60);
 
-    let font = Font::from_file("resources/Aaargh.ttf").unwrap();
+    let scale = screen_scale();
 
-    let mut board = Board::new(&font);
+    let start = GameState::start_game_seeded(rng_seed, spawn.clone());
+    let mut board = Board::from_state(start, font);
+    board.set_theme(theme, font);
+    board.set_layout(Layout::new(WIDTH, HEIGHT, scale));
+    if let Some(saved) = &offer {
+        board.set_overlay(resume_overlay(saved));
+    }
+    if let Some(autoplay) = &autoplay {
+        board.restart(autoplay.start());
+        board.set_status(autoplay.status());
+    }
     board.draw_on(&mut window);
     window.display();
 
+    // Animations follow the time elapsed, whatever the frame rate
+    let mut clock = Clock::start();
     'mainloop: loop {
         while let Some(event) = window.poll_event() {
             match event {
-                Event::Closed => { break 'mainloop; },
+                Event::Closed => {
+                    if autoplay.is_none() && offer.is_none() {
+                        autosave(&dir, &board, spawn_spec, rng_seed,
+                                 seed.is_none());
+                    }
+                    break 'mainloop;
+                },
+                Event::Resized { width, height } => {
+                    // Keep one unit per pixel, so that clicks land where the
+                    // layout says
+                    let (w, h) = (width as f32, height as f32);
+                    window.set_view(&View::from_rect(
+                        &FloatRect::new(0.0, 0.0, w, h)));
+                    board.set_layout(Layout::new(w, h, scale));
+                },
                 // Event::MouseButtonPressed { button, x, y } => {
                 //     // ...
                 // },
+                Event::MouseMoved { x, y } if autoplay.is_none() &&
+                                              !editor.is_active() => {
+                    board.set_cursor(Some((x, y)));
+                },
+                Event::MouseLeft => board.set_cursor(None),
+                Event::MouseButtonReleased { .. } if offer.is_some() => {},
+                Event::MouseButtonReleased { button, x, y }
+                                                    if editor.is_active() => {
+                    editor.click(&mut board, button, x, y);
+                },
+                Event::MouseWheelScrolled { delta, x, y, .. }
+                                                    if editor.is_active() => {
+                    editor.scroll(&mut board, delta, x, y);
+                },
                 Event::MouseButtonReleased { button, x, y } => {
-                    if button == Button::Left {
+                    if button == Button::Left && autoplay.is_none() {
                         board.click(x, y);
                     }
                 },
                 Event::KeyPressed { code: key, .. } => {
-                    match key {
-                        Key::P => {
-                            println!("hep hep setting plus");
-                            board.set_state_incoming(AtomType::Plus);
+                    if let Some(saved) = offer.take() {
+                        match key {
+                            Key::Y | Key::Return => {
+                                rng_seed = saved.seed;
+                                seed = Some(saved.seed)
+                                           .filter(|_| !saved.edited);
+                                board.resume(saved.state, saved.moves);
+                                board.set_overlay(Vec::new());
+                            },
+                            Key::N | Key::Escape => {
+                                board.set_overlay(Vec::new());
+                            },
+                            _ => offer = Some(saved)
+                        }
+                        continue;
+                    }
+                    let action = bindings.action(key);
+                    if action == Some(Action::Scores) {
+                        let lines = if board.has_overlay() {
+                            Vec::new()
+                        } else {
+                            scores_overlay(&history, Vec::new())
+                        };
+                        board.set_overlay(lines);
+                        continue;
+                    }
+                    if action == Some(Action::Theme) && !editor.is_active() {
+                        match themes.next() {
+                            Ok((theme, font)) => board.set_theme(theme, font),
+                            Err(e) => eprintln!("iatomas: {}", e),
+                        }
+                        continue;
+                    }
+                    if let Some(autoplay) = &mut autoplay {
+                        if let Some(control) = autoplay_control(key) {
+                            autoplay.control(control);
+                        }
+                        continue;
+                    }
+                    if action == Some(Action::Editor) {
+                        editor.toggle(&mut board);
+                        if editor.is_active() {
+                            seed = None;
+                        }
+                        continue;
+                    }
+                    if editor.is_active() {
+                        editor.key(&mut board, key);
+                        continue;
+                    }
+                    match action {
+                        Some(Action::Previous) => board.select(-1),
+                        Some(Action::Next) => board.select(1),
+                        Some(Action::Play) => board.play_selected(),
+                        Some(Action::Convert) if !board.is_animating() => {
+                            board.play_move(Move::Convert);
                         },
-                        Key::M => {
-                            println!("hep hep setting minus");
-                            board.set_state_incoming(AtomType::Minus);
+                        Some(Action::Hint) if !board.state().is_over() => {
+                            hinter.request(board.state());
+                            hint_version = Some(board.version());
                         },
-                        Key::D => {
-                            println!("hep hep setting dark plus");
-                            board.set_state_incoming(AtomType::DarkPlus);
+                        Some(Action::NewGame) => {
+                            let new_seed = rand::random();
+                            board.restart(GameState::start_game_seeded(
+                                new_seed, spawn.clone()));
+                            board.set_overlay(Vec::new());
+                            rng_seed = new_seed;
+                            seed = Some(new_seed);
                         },
-                        Key::W => {
-                            println!("hep hep setting neutrino");
-                            board.set_state_incoming(AtomType::Neutrino);
+                        // A game cheated in is not recorded with its seed
+                        None if debug && cheat(&mut board, key) => {
+                            seed = None;
                         },
-
                         _ => {}
-                    } // match key
+                    }
                 },
                 _ => {}
             } // match event
         }
-        window.clear(Color::rgb(0, 0, 0));
+        let seconds = clock.restart().as_seconds();
+        match themes.watch(seconds) {
+            Some(Ok((theme, font))) => board.set_theme(theme, font),
+            Some(Err(e)) => eprintln!("iatomas: {}", e),
+            None => {}
+        }
+        match &mut autoplay {
+            Some(autoplay) => {
+                board.advance(seconds * autoplay.speed());
+                board.set_status(autoplay.status());
+                if !board.is_animating() {
+                    let state = board.state();
+                    match autoplay.update(state, board.version(), seconds) {
+                        Some(Turn::Play(mv)) => board.play_move(mv),
+                        Some(Turn::Restart(state)) => board.restart(*state),
+                        None => {}
+                    }
+                }
+            },
+            None => board.advance(seconds)
+        }
+        if autoplay.is_none() && offer.is_none() {
+            since_save += seconds;
+            if since_save >= AUTOSAVE_PERIOD &&
+               saved_version != Some(board.version()) {
+                autosave(&dir, &board, spawn_spec, rng_seed, seed.is_none());
+                saved_version = Some(board.version());
+                since_save = 0.0;
+            }
+        }
+        let over = board.state().is_over() && !board.is_animating() &&
+                   !editor.is_active();
+        if over && recorded != Some(board.version()) {
+            recorded = Some(board.version());
+            let game_seed = match &autoplay {
+                Some(autoplay) => Some(autoplay.seed()),
+                None => seed
+            };
+            // Edited and cheated games are told apart, and not ranked
+            let mode = if game_seed.is_some() { mode.clone() }
+                       else { format!("{} edited", mode) };
+            let game = GameSummary::new(board.state(), &mode, game_seed);
+            let rank = history.as_mut().map(|h| h.record(game));
+            let mut heading = vec![format!("Game over: {}",
+                                           board.state().score)];
+            match rank {
+                Some(Ok(Some(rank))) => {
+                    heading.push(format!("New high score: #{}", rank + 1));
+                },
+                Some(Err(e)) => eprintln!("iatomas: cannot save the scores \
+                                           in {}: {}", dir.display(), e),
+                _ => {}
+            }
+            heading.push(String::new());
+            if autoplay.is_none() {
+                board.set_overlay(scores_overlay(&history, heading));
+                // A finished game cannot be resumed
+                autosave(&dir, &board, spawn_spec, rng_seed, seed.is_none());
+                saved_version = Some(board.version());
+            }
+        }
+        if let Some(version) = hint_version {
+            if version != board.version() {
+                hinter.cancel();
+                hint_version = None;
+            } else if let Some(hint) = hinter.poll() {
+                board.set_hint(Some(hint));
+                hint_version = None;
+            }
+        }
+        window.clear(sfml_color(board.theme().background));
         board.draw_on(&mut window);
         // board.info();
         window.display();
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_window(_options: &cli::Options) -> Result<(), String> {
+    Err("built without the `gui` feature, only the sub-commands are \
+         available".to_string())
+}
@@ -0,0 +1,52 @@
+/// Adam optimiser, keeping the moment estimates of a list of parameter
+/// vectors.
+#[derive(Clone, Debug)]
+pub struct Adam {
+    pub rate: f32,
+    pub beta1: f32,
+    pub beta2: f32,
+    pub epsilon: f32,
+    step: i32,
+    moments: Vec<(Vec<f32>, Vec<f32>)>,
+}
+
+impl Adam {
+    /// Creates an optimiser with the usual betas and the given learning rate
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+            step: 0,
+            moments: Vec::new(),
+        }
+    }
+
+    /// Starts a new step, to be followed by one `update` call per parameter
+    /// vector, always in the same order.
+    pub fn begin_step(&mut self) {
+        self.step += 1;
+    }
+
+    /// Moves the `k`-th parameter vector `params` against `grads`
+    pub fn update(&mut self, k: usize, params: &mut [f32], grads: &[f32]) {
+        while self.moments.len() <= k {
+            self.moments.push((Vec::new(), Vec::new()));
+        }
+        let (m, v) = &mut self.moments[k];
+        if m.len() != params.len() {
+            *m = vec![0.0; params.len()];
+            *v = vec![0.0; params.len()];
+        }
+        let c1 = 1.0 - self.beta1.powi(self.step);
+        let c2 = 1.0 - self.beta2.powi(self.step);
+        for i in 0..params.len() {
+            m[i] = self.beta1 * m[i] + (1.0 - self.beta1) * grads[i];
+            v[i] = self.beta2 * v[i] + (1.0 - self.beta2) * grads[i] * grads[i];
+            let m_hat = m[i] / c1;
+            let v_hat = v[i] / c2;
+            params[i] -= self.rate * m_hat / (v_hat.sqrt() + self.epsilon);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+/// Fully connected layer, computing `x W^T + b` for a batch of rows `x`.
+///
+/// `weights` holds `outputs` rows of `inputs` values.
+#[derive(Clone, Debug)]
+pub struct Dense {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+}
+
+/// Gradients of the parameters of a `Dense` layer
+#[derive(Clone, Debug)]
+pub struct DenseGrad {
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+}
+
+impl Dense {
+    /// Creates a layer with He initialised weights and zero biases
+    pub fn new<R: Rng>(inputs: usize, outputs: usize, rng: &mut R) -> Self {
+        let normal = Normal::new(0.0, (2.0 / inputs as f32).sqrt()).unwrap();
+        Self {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs).map(|_| normal.sample(rng))
+                                          .collect(),
+            biases: vec![0.0; outputs],
+        }
+    }
+
+    /// Gradients of the same shape as this layer, set to zero
+    pub fn zero_grad(&self) -> DenseGrad {
+        DenseGrad {
+            weights: vec![0.0; self.weights.len()],
+            biases: vec![0.0; self.biases.len()],
+        }
+    }
+
+    /// Output of the layer for the `batch` rows of `x`
+    pub fn forward(&self, x: &[f32], batch: usize) -> Vec<f32> {
+        let mut y = Vec::with_capacity(batch * self.outputs);
+        for row in x.chunks(self.inputs).take(batch) {
+            for (w, b) in self.weights.chunks(self.inputs)
+                                      .zip(self.biases.iter()) {
+                y.push(dot(row, w) + b);
+            }
+        }
+        y
+    }
+
+    /// Accumulates in `grad` the gradients of the parameters, given the
+    /// input `x` of the forward pass and the gradient `dy` of its output.
+    ///
+    /// Returns: the gradient of the input
+    pub fn backward(&self, x: &[f32], dy: &[f32], batch: usize,
+                    grad: &mut DenseGrad) -> Vec<f32> {
+        let mut dx = vec![0.0; batch * self.inputs];
+        for r in 0..batch {
+            let row = &x[r * self.inputs..(r + 1) * self.inputs];
+            let dx_row = &mut dx[r * self.inputs..(r + 1) * self.inputs];
+            for o in 0..self.outputs {
+                let d = dy[r * self.outputs + o];
+                if d == 0.0 {
+                    continue;
+                }
+                grad.biases[o] += d;
+                let w = &self.weights[o * self.inputs..(o + 1) * self.inputs];
+                let gw = &mut grad.weights[o * self.inputs..
+                                           (o + 1) * self.inputs];
+                for i in 0..self.inputs {
+                    gw[i] += d * row[i];
+                    dx_row[i] += d * w[i];
+                }
+            }
+        }
+        dx
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
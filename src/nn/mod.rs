@@ -0,0 +1,33 @@
+mod adam;
+mod dense;
+mod network;
+
+pub use adam::*;
+pub use dense::*;
+pub use network::*;
+
+/// Applies the ReLU function to every value of `x`
+pub fn relu(x: &mut [f32]) {
+    for v in x.iter_mut() {
+        if *v < 0.0 { *v = 0.0; }
+    }
+}
+
+/// Turns `logits` into probabilities, in place. The entries not allowed by
+/// `mask` get a zero probability.
+pub fn softmax(logits: &mut [f32], mask: Option<&[bool]>) {
+    let allowed = |i: usize| mask.is_none_or(|m| m[i]);
+    let max = (0..logits.len()).filter(|&i| allowed(i))
+                               .map(|i| logits[i])
+                               .fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+    for (i, l) in logits.iter_mut().enumerate() {
+        *l = if allowed(i) { (*l - max).exp() } else { 0.0 };
+        sum += *l;
+    }
+    if sum > 0.0 {
+        for p in logits.iter_mut() {
+            *p /= sum;
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use super::{Adam, Dense, DenseGrad, relu, softmax};
+use crate::invalid_data;
+
+use rand::Rng;
+
+const MAGIC: &[u8; 4] = b"IATM";
+const VERSION: u32 = 1;
+/// Most layers, and most inputs or outputs of a layer, a network read from a
+/// file may have
+const MAX_LAYERS: usize = 64;
+const MAX_LAYER_SIZE: usize = 1 << 16;
+/// Most parameters a layer read from a file may have
+const MAX_LAYER_PARAMS: usize = 1 << 26;
+
+/// Multi-layer perceptron with a shared trunk of ReLU layers and two heads:
+/// a scalar value and the logits of a policy over a fixed set of actions.
+#[derive(Clone, Debug)]
+pub struct PolicyValueNet {
+    pub trunk: Vec<Dense>,
+    pub value: Dense,
+    pub policy: Dense,
+}
+
+/// Gradients of the parameters of a `PolicyValueNet`, layer by layer
+#[derive(Clone, Debug)]
+pub struct PolicyValueGrad {
+    pub trunk: Vec<DenseGrad>,
+    pub value: DenseGrad,
+    pub policy: DenseGrad,
+}
+
+/// Losses of a training batch, averaged over its rows
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Losses {
+    pub value: f32,
+    pub policy: f32,
+}
+
+impl PolicyValueNet {
+    /// Creates a randomly initialised network taking `inputs` values, whose
+    /// trunk has layers of the `hidden` sizes, and with `actions` logits.
+    pub fn new<R: Rng>(inputs: usize, hidden: &[usize], actions: usize,
+                       rng: &mut R) -> Self {
+        let mut trunk = Vec::new();
+        let mut size = inputs;
+        for &h in hidden {
+            trunk.push(Dense::new(size, h, rng));
+            size = h;
+        }
+        Self {
+            trunk,
+            value: Dense::new(size, 1, rng),
+            policy: Dense::new(size, actions, rng),
+        }
+    }
+
+    /// Number of values the network takes as input
+    pub fn inputs(&self) -> usize {
+        self.trunk.first().unwrap_or(&self.value).inputs
+    }
+
+    /// Number of actions of the policy head
+    pub fn actions(&self) -> usize {
+        self.policy.outputs
+    }
+
+    /// Activations of the trunk for the `batch` rows of `x`, starting with
+    /// `x` itself
+    fn trunk_forward(&self, x: &[f32], batch: usize) -> Vec<Vec<f32>> {
+        let mut activations = vec![x.to_vec()];
+        for layer in &self.trunk {
+            let mut y = layer.forward(activations.last().unwrap(), batch);
+            relu(&mut y);
+            activations.push(y);
+        }
+        activations
+    }
+
+    /// Values and policy logits for the `batch` rows of `x`
+    pub fn forward(&self, x: &[f32], batch: usize) -> (Vec<f32>, Vec<f32>) {
+        let activations = self.trunk_forward(x, batch);
+        let h = activations.last().unwrap();
+        (self.value.forward(h, batch), self.policy.forward(h, batch))
+    }
+
+    /// Value of a single input, and its policy restricted to the actions
+    /// allowed by `mask`
+    pub fn predict(&self, x: &[f32], mask: &[bool]) -> (f32, Vec<f32>) {
+        let (value, mut policy) = self.forward(x, 1);
+        softmax(&mut policy, Some(mask));
+        (value[0], policy)
+    }
+
+    /// Losses of a batch, and the gradients of their sum with respect to
+    /// the parameters: the squared error of the value and the cross-entropy
+    /// of the masked policy.
+    ///
+    /// `policies` holds the target distributions, `values` the target values.
+    pub fn gradients(&self, x: &[f32], masks: &[bool], policies: &[f32],
+                     values: &[f32], batch: usize)
+                                            -> (Losses, PolicyValueGrad) {
+        let actions = self.actions();
+        let activations = self.trunk_forward(x, batch);
+        let h = activations.last().unwrap();
+        let v = self.value.forward(h, batch);
+        let mut p = self.policy.forward(h, batch);
+
+        let mut losses = Losses::default();
+        let scale = 1.0 / batch as f32;
+        let mut dv = vec![0.0; batch];
+        for r in 0..batch {
+            let error = v[r] - values[r];
+            losses.value += error * error * scale;
+            dv[r] = 2.0 * error * scale;
+        }
+        for r in 0..batch {
+            let row = r * actions..(r + 1) * actions;
+            softmax(&mut p[row.clone()], Some(&masks[row.clone()]));
+            for a in row {
+                if policies[a] > 0.0 {
+                    losses.policy -= policies[a] * p[a].max(1e-12).ln() * scale;
+                }
+                // the gradient of the cross-entropy with respect to the logits
+                p[a] = (p[a] - policies[a]) * scale;
+            }
+        }
+
+        let mut value_grad = self.value.zero_grad();
+        let mut policy_grad = self.policy.zero_grad();
+        let mut dh = self.value.backward(h, &dv, batch, &mut value_grad);
+        let dh_policy = self.policy.backward(h, &p, batch, &mut policy_grad);
+        for (a, b) in dh.iter_mut().zip(dh_policy.iter()) {
+            *a += b;
+        }
+
+        let mut trunk_grads: Vec<DenseGrad> = self.trunk.iter()
+                                                  .map(|l| l.zero_grad())
+                                                  .collect();
+        for l in (0..self.trunk.len()).rev() {
+            for (d, y) in dh.iter_mut().zip(activations[l + 1].iter()) {
+                if *y <= 0.0 { *d = 0.0; }
+            }
+            dh = self.trunk[l].backward(&activations[l], &dh, batch,
+                                        &mut trunk_grads[l]);
+        }
+        let grad = PolicyValueGrad {
+            trunk: trunk_grads,
+            value: value_grad,
+            policy: policy_grad,
+        };
+        (losses, grad)
+    }
+
+    /// Makes one Adam step on a batch, minimising the losses of
+    /// `PolicyValueNet::gradients`
+    pub fn train_batch(&mut self, adam: &mut Adam, x: &[f32], masks: &[bool],
+                       policies: &[f32], values: &[f32], batch: usize)
+                                                                -> Losses {
+        let (losses, grad) = self.gradients(x, masks, policies, values, batch);
+        adam.begin_step();
+        let mut k = 0;
+        let layers = self.trunk.iter_mut().zip(grad.trunk.iter())
+            .chain(std::iter::once((&mut self.value, &grad.value)))
+            .chain(std::iter::once((&mut self.policy, &grad.policy)));
+        for (layer, grad) in layers {
+            adam.update(k, &mut layer.weights, &grad.weights);
+            adam.update(k + 1, &mut layer.biases, &grad.biases);
+            k += 2;
+        }
+        losses
+    }
+
+    /// Writes the network in a binary file: a magic number and a version,
+    /// then the shape and parameters of every layer, in little endian
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.trunk.len() as u32).to_le_bytes())?;
+        for layer in self.trunk.iter().chain([&self.value, &self.policy]) {
+            out.write_all(&(layer.inputs as u32).to_le_bytes())?;
+            out.write_all(&(layer.outputs as u32).to_le_bytes())?;
+            for x in layer.weights.iter().chain(layer.biases.iter()) {
+                out.write_all(&x.to_le_bytes())?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Reads a network written by `PolicyValueNet::save`, checking its
+    /// version and that its layers fit together
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut input = io::BufReader::new(fs::File::open(path)?);
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a network file"));
+        }
+        let version = read_u32(&mut input)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("network file version {}, \
+                                             expected {}", version,
+                                            VERSION)));
+        }
+        let count = read_u32(&mut input)? as usize;
+        if count > MAX_LAYERS {
+            return Err(invalid_data(format!("{} layers, at most {} \
+                                             expected", count,
+                                            MAX_LAYERS)));
+        }
+        let mut layers = Vec::new();
+        for _ in 0..count + 2 {
+            let inputs = read_u32(&mut input)? as usize;
+            let outputs = read_u32(&mut input)? as usize;
+            let size = |n| n > 0 && n <= MAX_LAYER_SIZE;
+            if !size(inputs) || !size(outputs) ||
+               inputs * outputs > MAX_LAYER_PARAMS {
+                return Err(invalid_data(format!("layer of {} inputs and \
+                                                 {} outputs", inputs,
+                                                outputs)));
+            }
+            let mut params = vec![0.0; inputs * outputs + outputs];
+            let mut bytes = [0; 4];
+            for x in params.iter_mut() {
+                input.read_exact(&mut bytes)?;
+                *x = f32::from_le_bytes(bytes);
+            }
+            let biases = params.split_off(inputs * outputs);
+            layers.push(Dense { inputs, outputs, weights: params, biases });
+        }
+        let policy = layers.pop().unwrap();
+        let value = layers.pop().unwrap();
+        let fits = layers.windows(2).all(|w| w[0].outputs == w[1].inputs) &&
+                   value.outputs == 1 &&
+                   value.inputs == policy.inputs &&
+                   layers.last().is_none_or(|l| l.outputs == value.inputs);
+        if !fits {
+            return Err(invalid_data("layers of the network do not fit \
+                                     together"));
+        }
+        Ok(Self { trunk: layers, value, policy })
+    }
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
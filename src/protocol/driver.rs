@@ -0,0 +1,223 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command as Process, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use super::{Command, Reply};
+use crate::agent::{GameRecord, MAX_GAME_MOVES, game_seed};
+use crate::game::{GameState, Move, SpawnModel, move_notation, position};
+use crate::invalid_data;
+
+/// Time an engine is given to answer the handshake and `isready`
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time an engine is given on top of the budget of a move, for the
+/// communication
+pub const MOVE_GRACE: Duration = Duration::from_millis(1000);
+
+/// An engine running in a subprocess, spoken to through its standard input
+/// and output.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// Name given in the handshake
+    pub name: String,
+    /// Options announced in the handshake, with their default values
+    pub options: Vec<(String, String)>,
+}
+
+impl EngineProcess {
+    /// Launches `command` (a program followed by its arguments, separated by
+    /// spaces) and makes the handshake.
+    pub fn launch(command: &str) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "empty engine command")
+        })?;
+        let mut child = Process::new(program).args(words)
+                                             .stdin(Stdio::piped())
+                                             .stdout(Stdio::piped())
+                                             .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // Lines are read by a thread of their own, so that waiting for them
+        // can time out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+            name: String::new(),
+            options: Vec::new(),
+        };
+        engine.handshake()?;
+        Ok(engine)
+    }
+
+    /// Sends `command` to the engine.
+    pub fn send(&mut self, command: &Command) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// Waits for the next reply of the engine, skipping empty and `info`
+    /// lines.
+    pub fn receive(&mut self, timeout: Duration) -> io::Result<Reply> {
+        loop {
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(io::ErrorKind::TimedOut,
+                                              "the engine did not answer"));
+                },
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "the engine has stopped"));
+                }
+            };
+            match Reply::parse(&line).map_err(invalid_data)? {
+                Some(Reply::Info(_)) | None => continue,
+                Some(reply) => return Ok(reply),
+            }
+        }
+    }
+
+    fn handshake(&mut self) -> io::Result<()> {
+        self.send(&Command::Iap)?;
+        loop {
+            match self.receive(HANDSHAKE_TIMEOUT)? {
+                Reply::Id { key, value } if key == "name" => self.name = value,
+                Reply::Id { .. } => {},
+                Reply::Option { name, default } => {
+                    self.options.push((name, default));
+                },
+                Reply::IapOk => return Ok(()),
+                reply => {
+                    return Err(invalid_data(format!(
+                        "unexpected `{}` in the handshake", reply)));
+                }
+            }
+        }
+    }
+
+    /// Sets an option of the engine.
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&Command::SetOption { name: name.to_string(),
+                                        value: value.to_string() })
+    }
+
+    /// Waits until the engine is ready, failing if it has reported an error
+    /// meanwhile.
+    pub fn ready(&mut self) -> io::Result<()> {
+        self.send(&Command::IsReady)?;
+        let mut error = None;
+        loop {
+            match self.receive(HANDSHAKE_TIMEOUT)? {
+                Reply::ReadyOk => break,
+                Reply::Error(e) => error = Some(e),
+                reply => {
+                    return Err(invalid_data(format!(
+                        "unexpected `{}` before `readyok`", reply)));
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(invalid_data(format!("engine error: {}", e))),
+            None => Ok(())
+        }
+    }
+
+    /// Asks the engine for its move in `state`, checking that it is legal.
+    pub fn best_move(&mut self, state: &GameState, budget: Duration)
+                                                        -> io::Result<Move> {
+        self.send(&Command::Position(position(state)))?;
+        self.send(&Command::Go { budget: budget.as_millis() as u64 })?;
+        match self.receive(budget + MOVE_GRACE)? {
+            Reply::BestMove(mv) if state.legal_moves().contains(&mv) => Ok(mv),
+            Reply::BestMove(mv) => Err(invalid_data(format!(
+                "illegal move `{}` in `{}`", move_notation(mv),
+                position(state)))),
+            Reply::Error(e) => {
+                Err(invalid_data(format!("engine error: {}", e)))
+            },
+            reply => Err(invalid_data(format!(
+                "unexpected `{}` instead of a move", reply)))
+        }
+    }
+
+    /// Plays a whole game, determined by `seed` and `spawn`, with the moves
+    /// of the engine.
+    pub fn play_game(&mut self, seed: u64, spawn: &SpawnModel,
+                     budget: Duration) -> io::Result<GameRecord> {
+        self.send(&Command::NewGame)?;
+        let mut state = GameState::start_game_seeded(seed, spawn.clone());
+        let mut moves = 0;
+        while !state.is_over() && moves < MAX_GAME_MOVES {
+            let mv = self.best_move(&state, budget)?;
+            state.apply(mv);
+            moves += 1;
+        }
+        Ok(GameRecord {
+            score: state.score,
+            best: state.best,
+            moves,
+        })
+    }
+
+    /// Asks the engine to quit, and waits for it.
+    pub fn quit(mut self) -> io::Result<()> {
+        // The engine may already be gone
+        let _ = self.send(&Command::Quit);
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for EngineProcess {
+    /// Kills the engine if it is still running, e.g. after an error, so that
+    /// it is not left behind
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Parameters of games played by an engine.
+#[derive(Clone, Debug)]
+pub struct MatchConfig {
+    pub games: u64,
+    pub seed: u64,
+    pub spawn: SpawnModel,
+    pub budget: Duration,
+    /// Options set before the first game
+    pub options: Vec<(String, String)>,
+}
+
+/// Launches `command` and lets the engine play `config.games` games, the
+/// `i`-th one being seeded by `game_seed(config.seed, i)`. The engine is
+/// stopped whatever happens.
+pub fn play_engine(command: &str, config: &MatchConfig)
+                                            -> io::Result<Vec<GameRecord>> {
+    let mut engine = EngineProcess::launch(command)?;
+    for (name, value) in &config.options {
+        engine.set_option(name, value)?;
+    }
+    engine.ready()?;
+    let mut records = Vec::new();
+    for i in 0..config.games {
+        records.push(engine.play_game(game_seed(config.seed, i),
+                                      &config.spawn, config.budget)?);
+    }
+    engine.quit()?;
+    Ok(records)
+}
@@ -0,0 +1,151 @@
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use super::{Command, Reply};
+use crate::agent::{self, Agent, SearchLimit};
+use crate::game::{GameState, SpawnModel, parse_position};
+
+/// Name the reference engine gives in its handshake
+pub const ENGINE_NAME: &str = "iatomas";
+/// Share of the budget of a move the search may take, the rest being kept
+/// to answer in time
+const SEARCH_SHARE: f64 = 0.9;
+
+/// The reference engine, playing with one of the agents of `agent`.
+///
+/// Its options are `agent` (a spec for `agent::from_spec`, `heuristic` by
+/// default), `spawn` (the spawn model it expects, `simple` by default) and
+/// `seed` (for its own random choices, `0` by default).
+pub struct Engine {
+    agent_spec: String,
+    spawn: SpawnModel,
+    seed: u64,
+    agent: Option<Box<dyn Agent>>,
+    state: Option<GameState>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self {
+            agent_spec: "heuristic".to_string(),
+            spawn: SpawnModel::default(),
+            seed: 0,
+            agent: None,
+            state: None,
+        }
+    }
+}
+
+impl Engine {
+    /// Follows `command`.
+    ///
+    /// Returns: the replies to send, and whether the engine should stop
+    pub fn handle(&mut self, command: Command) -> (Vec<Reply>, bool) {
+        let replies = match command {
+            Command::Iap => vec![
+                Reply::Id { key: "name".to_string(),
+                            value: ENGINE_NAME.to_string() },
+                Reply::Option { name: "agent".to_string(),
+                                default: "heuristic".to_string() },
+                Reply::Option { name: "spawn".to_string(),
+                                default: "simple".to_string() },
+                Reply::Option { name: "seed".to_string(),
+                                default: "0".to_string() },
+                Reply::IapOk,
+            ],
+            Command::SetOption { name, value } => {
+                match self.set_option(&name, &value) {
+                    Ok(()) => Vec::new(),
+                    Err(e) => vec![Reply::Error(e)]
+                }
+            },
+            Command::IsReady => match self.load_agent() {
+                Ok(()) => vec![Reply::ReadyOk],
+                Err(e) => vec![Reply::Error(e), Reply::ReadyOk]
+            },
+            Command::NewGame => {
+                self.state = None;
+                Vec::new()
+            },
+            Command::Position(text) => {
+                match parse_position(&text, self.seed, self.spawn.clone()) {
+                    Ok(state) => {
+                        self.state = Some(state);
+                        Vec::new()
+                    },
+                    Err(e) => {
+                        self.state = None;
+                        vec![Reply::Error(e)]
+                    }
+                }
+            },
+            Command::Go { budget } => match self.best_move(budget) {
+                Ok(reply) => vec![reply],
+                Err(e) => vec![Reply::Error(e)]
+            },
+            Command::Quit => return (Vec::new(), true),
+        };
+        (replies, false)
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "agent" => self.agent_spec = value.to_string(),
+            "spawn" => {
+                self.spawn = SpawnModel::parse(value)?;
+            },
+            "seed" => {
+                self.seed = value.parse()
+                    .map_err(|_| format!("bad seed `{}`", value))?;
+            },
+            _ => return Err(format!("unknown option `{}`", name))
+        }
+        self.agent = None;
+        Ok(())
+    }
+
+    /// Builds the agent of the options, if not done yet
+    fn load_agent(&mut self) -> Result<(), String> {
+        if self.agent.is_none() {
+            self.agent = Some(agent::from_spec(&self.agent_spec, self.seed)?);
+        }
+        Ok(())
+    }
+
+    /// The move to play in the current position, searched for at most
+    /// `budget` milliseconds, from the command on
+    fn best_move(&mut self, budget: u64) -> Result<Reply, String> {
+        let budget = Duration::from_millis(budget).mul_f64(SEARCH_SHARE);
+        let limit = SearchLimit::within(budget);
+        self.load_agent()?;
+        let state = self.state.as_ref()
+                        .ok_or_else(|| "no position".to_string())?;
+        if state.is_over() || state.legal_moves().is_empty() {
+            return Err("the game is over".to_string());
+        }
+        let agent = self.agent.as_mut().expect("the agent has been loaded");
+        Ok(Reply::BestMove(agent.rank_moves_within(state, &limit)[0].0))
+    }
+}
+
+/// Runs the reference engine on `input` and `output`, until `quit` or the
+/// end of `input`.
+pub fn run_engine<R: BufRead, W: Write>(input: R, mut output: W)
+                                                        -> io::Result<()> {
+    let mut engine = Engine::default();
+    for line in input.lines() {
+        let (replies, quit) = match Command::parse(&line?) {
+            Ok(Some(command)) => engine.handle(command),
+            Ok(None) => continue,
+            Err(e) => (vec![Reply::Error(e)], false)
+        };
+        for reply in replies {
+            writeln!(output, "{}", reply)?;
+        }
+        output.flush()?;
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! A line-based text protocol between a driver holding the game and an
+//! engine choosing the moves, in the spirit of UCI for chess engines.
+//!
+//! The driver starts with `iap`, to which the engine answers with `id name
+//! <name>`, optional `id author <author>` and `option name <name> default
+//! <value>` lines, then `iapok`. The driver may then send:
+//!
+//! - `setoption name <name> value <value>`
+//!
+//! - `isready`, answered by `readyok` once the engine is ready
+//!
+//! - `newgame`, before the first position of a game
+//!
+//! - `position <position>`, in the notation of `game::position`
+//!
+//! - `go budget=<ms>`, answered by `bestmove <move>` (see
+//!   `game::move_notation`) within the given number of milliseconds
+//!
+//! - `quit`
+//!
+//! The engine answers `error <message>` to a command it cannot follow, and
+//! may send `info <text>` lines at any time. Empty lines are ignored.
+
+mod driver;
+mod engine;
+
+pub use driver::*;
+pub use engine::*;
+
+use std::fmt;
+
+use crate::game::{Move, move_notation, parse_move};
+
+/// A line sent by the driver to the engine.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Iap,
+    SetOption { name: String, value: String },
+    IsReady,
+    NewGame,
+    Position(String),
+    Go { budget: u64 },
+    Quit,
+}
+
+/// A line sent by the engine to the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    Id { key: String, value: String },
+    Option { name: String, default: String },
+    IapOk,
+    ReadyOk,
+    BestMove(Move),
+    Info(String),
+    Error(String),
+}
+
+impl Command {
+    /// Reads a command line, `None` meaning an empty line.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        let (word, rest) = match line.split_once(' ') {
+            Some((word, rest)) => (word, rest.trim()),
+            None => (line, "")
+        };
+        let command = match word {
+            "" => return Ok(None),
+            "iap" => Command::Iap,
+            "isready" => Command::IsReady,
+            "newgame" => Command::NewGame,
+            "quit" => Command::Quit,
+            "position" => Command::Position(rest.to_string()),
+            "setoption" => {
+                let (name, value) = rest.strip_prefix("name ")
+                    .and_then(|r| r.split_once(" value "))
+                    .ok_or_else(|| format!("bad setoption `{}`", rest))?;
+                Command::SetOption { name: name.trim().to_string(),
+                                     value: value.trim().to_string() }
+            },
+            "go" => {
+                let budget = rest.strip_prefix("budget=")
+                                 .and_then(|b| b.parse().ok())
+                                 .ok_or_else(|| format!("bad go `{}`", rest))?;
+                Command::Go { budget }
+            },
+            _ => return Err(format!("unknown command `{}`", word))
+        };
+        Ok(Some(command))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Iap => write!(f, "iap"),
+            Command::SetOption { name, value } => {
+                write!(f, "setoption name {} value {}", name, value)
+            },
+            Command::IsReady => write!(f, "isready"),
+            Command::NewGame => write!(f, "newgame"),
+            Command::Position(position) => write!(f, "position {}", position),
+            Command::Go { budget } => write!(f, "go budget={}", budget),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl Reply {
+    /// Reads a reply line, `None` meaning an empty line.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        let (word, rest) = match line.split_once(' ') {
+            Some((word, rest)) => (word, rest.trim()),
+            None => (line, "")
+        };
+        let reply = match word {
+            "" => return Ok(None),
+            "iapok" => Reply::IapOk,
+            "readyok" => Reply::ReadyOk,
+            "info" => Reply::Info(rest.to_string()),
+            "error" => Reply::Error(rest.to_string()),
+            "id" => {
+                let (key, value) = rest.split_once(' ')
+                    .ok_or_else(|| format!("bad id `{}`", rest))?;
+                Reply::Id { key: key.to_string(),
+                            value: value.trim().to_string() }
+            },
+            "option" => {
+                let (name, default) = rest.strip_prefix("name ")
+                    .and_then(|r| r.split_once(" default "))
+                    .ok_or_else(|| format!("bad option `{}`", rest))?;
+                Reply::Option { name: name.trim().to_string(),
+                                default: default.trim().to_string() }
+            },
+            "bestmove" => {
+                let mv = parse_move(rest)
+                    .ok_or_else(|| format!("bad move `{}`", rest))?;
+                Reply::BestMove(mv)
+            },
+            _ => return Err(format!("unknown reply `{}`", word))
+        };
+        Ok(Some(reply))
+    }
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reply::Id { key, value } => write!(f, "id {} {}", key, value),
+            Reply::Option { name, default } => {
+                write!(f, "option name {} default {}", name, default)
+            },
+            Reply::IapOk => write!(f, "iapok"),
+            Reply::ReadyOk => write!(f, "readyok"),
+            Reply::BestMove(mv) => write!(f, "bestmove {}", move_notation(*mv)),
+            Reply::Info(text) => write!(f, "info {}", text),
+            Reply::Error(text) => write!(f, "error {}", text),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! The `iatomas` Python extension module, built with the `python` feature.
+
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1, PyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::env::{ACTION_SPACE, BatchEnv, EnvConfig, OBSERVATION_SIZE, Reward};
+use crate::game::{self, ENCODING_SIZE, Move};
+
+/// Turns the errors of the crate into Python `ValueError`s
+fn value_error<E: ToString>(e: E) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// The spawn model given by `spec` (see `SpawnModel::parse`)
+fn spawn_model(spec: &str) -> PyResult<game::SpawnModel> {
+    game::SpawnModel::parse(spec).map_err(value_error)
+}
+
+/// A game, its moves being numbered as in `Move::index`.
+#[pyclass(name = "GameState")]
+#[derive(Clone)]
+struct PyGameState {
+    state: game::GameState,
+}
+
+#[pymethods]
+impl PyGameState {
+    /// Starts a game determined by `seed`, drawing its atoms from the spawn
+    /// model `spawn`.
+    #[new]
+    #[pyo3(signature = (seed=0, spawn="simple"))]
+    fn new(seed: u64, spawn: &str) -> PyResult<Self> {
+        let state = game::GameState::start_game_seeded(seed,
+                                                       spawn_model(spawn)?);
+        Ok(Self { state })
+    }
+
+    /// Reads a position in the notation of `position()`.
+    #[staticmethod]
+    #[pyo3(signature = (text, seed=0, spawn="simple"))]
+    fn from_position(text: &str, seed: u64, spawn: &str) -> PyResult<Self> {
+        let state = game::parse_position(text, seed, spawn_model(spawn)?)
+                         .map_err(value_error)?;
+        Ok(Self { state })
+    }
+
+    /// The position, as in `H,He,+,H Li 42 17`.
+    fn position(&self) -> String {
+        game::position(&self.state)
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        self.state.legal_moves().iter().map(Move::index).collect()
+    }
+
+    /// Plays the move numbered `mv`, returning the value of the highest atom
+    /// made by its reactions.
+    fn apply(&mut self, mv: usize) -> PyResult<u8> {
+        match Move::from_index(mv) {
+            Some(m) if self.state.legal_moves().contains(&m) => {
+                Ok(self.state.apply(m))
+            },
+            _ => Err(value_error(format!("illegal move {}", mv)))
+        }
+    }
+
+    fn clone(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __copy__(&self) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __deepcopy__(&self, _memo: &Bound<'_, PyAny>) -> Self {
+        Clone::clone(self)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("GameState('{}')", self.position())
+    }
+
+    /// The description of the state used by the networks.
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        let mut x = vec![0.0; ENCODING_SIZE];
+        game::encode(&self.state, &mut x);
+        x.into_pyarray(py)
+    }
+
+    #[getter]
+    fn atoms(&self) -> Vec<String> {
+        self.state.atoms.iter().map(|a| game::atom_symbol(&a.t)).collect()
+    }
+
+    #[getter]
+    fn incoming(&self) -> String {
+        game::atom_symbol(&self.state.incoming.t)
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.state.score
+    }
+
+    #[getter]
+    fn time(&self) -> u32 {
+        self.state.time
+    }
+
+    #[getter]
+    fn best(&self) -> u8 {
+        self.state.best
+    }
+
+    fn is_over(&self) -> bool {
+        self.state.is_over()
+    }
+}
+
+/// A spawn model with its own generator, drawing atoms as a game would.
+#[pyclass(name = "SpawnModel")]
+struct PySpawnModel {
+    model: game::SpawnModel,
+    rng: ChaCha8Rng,
+}
+
+#[pymethods]
+impl PySpawnModel {
+    #[new]
+    #[pyo3(signature = (name="simple", seed=0))]
+    fn new(name: &str, seed: u64) -> PyResult<Self> {
+        Ok(Self {
+            model: spawn_model(name)?,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        })
+    }
+
+    /// Restarts the generator from `seed`.
+    fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// Draws the atom coming after `time` atoms have been shot, the score
+    /// being `score`.
+    fn draw(&mut self, time: u32, score: u32) -> String {
+        game::atom_symbol(&self.model.draw(time, score, &mut self.rng))
+    }
+}
+
+/// What `BatchEnv.step` returns
+type StepArrays<'py> = (Bound<'py, PyArray2<f32>>, Bound<'py, PyArray1<f32>>,
+                        Bound<'py, PyArray1<bool>>, Bound<'py, PyDict>);
+
+/// Many games stepped at once (see `iatomas::env::BatchEnv`), exchanging
+/// NumPy arrays.
+#[pyclass(name = "BatchEnv")]
+struct PyBatchEnv {
+    env: BatchEnv,
+    observations: Vec<f32>,
+    rewards: Vec<f32>,
+    dones: Vec<bool>,
+}
+
+impl PyBatchEnv {
+    /// The current observations, one row per game
+    fn observations<'py>(&self, py: Python<'py>)
+                                            -> Bound<'py, PyArray2<f32>> {
+        let n = self.env.len();
+        Array2::from_shape_vec((n, OBSERVATION_SIZE), self.observations.clone())
+               .expect("the buffer fits the games")
+               .into_pyarray(py)
+    }
+}
+
+#[pymethods]
+impl PyBatchEnv {
+    /// Creates `n` games. `reward` is `score` for the score gained, or
+    /// `shaped` to add `shaping` times the atomic number of the highest atom
+    /// made.
+    #[new]
+    #[pyo3(signature = (n, seed=0, threads=1, spawn="simple", reward="score",
+                        shaping=1.0))]
+    fn new(n: usize, seed: u64, threads: usize, spawn: &str, reward: &str,
+           shaping: f64) -> PyResult<Self> {
+        let reward = match reward {
+            "score" => Reward::Score,
+            "shaped" => Reward::Shaped(shaping),
+            _ => return Err(value_error(format!("unknown reward `{}`",
+                                                reward)))
+        };
+        let config = EnvConfig {
+            spawn: spawn_model(spawn)?,
+            reward,
+            ..EnvConfig::default()
+        };
+        Ok(Self {
+            env: BatchEnv::new(n, config, seed, threads),
+            observations: vec![0.0; n * OBSERVATION_SIZE],
+            rewards: vec![0.0; n],
+            dones: vec![false; n],
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.env.len()
+    }
+
+    /// Starts new episodes of all the games, returning their observations.
+    fn reset<'py>(&mut self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        self.env.reset(&mut self.observations);
+        self.observations(py)
+    }
+
+    /// Plays one action per game.
+    ///
+    /// Returns: the observations, rewards and `done` flags, and a dict of
+    /// per-game `score`, `best`, `moves`, `truncated` and `action_mask`
+    fn step<'py>(&mut self, py: Python<'py>, actions: Vec<usize>)
+                                                -> PyResult<StepArrays<'py>> {
+        let Self { env, observations, rewards, dones } = self;
+        py.detach(|| env.step(&actions, observations, rewards, dones))
+          .map_err(value_error)?;
+
+        let infos = self.env.infos();
+        let info = PyDict::new(py);
+        info.set_item("score", infos.iter().map(|i| i.score)
+                                    .collect::<Vec<_>>().into_pyarray(py))?;
+        info.set_item("best", infos.iter().map(|i| i.best)
+                                   .collect::<Vec<_>>().into_pyarray(py))?;
+        info.set_item("moves", infos.iter().map(|i| i.moves)
+                                    .collect::<Vec<_>>().into_pyarray(py))?;
+        info.set_item("truncated", infos.iter().map(|i| i.truncated)
+                                        .collect::<Vec<_>>()
+                                        .into_pyarray(py))?;
+        info.set_item("action_mask", self.action_masks(py))?;
+        Ok((self.observations(py), self.rewards.clone().into_pyarray(py),
+            self.dones.clone().into_pyarray(py), info))
+    }
+
+    /// Which actions are legal in every game, one row per game.
+    fn action_masks<'py>(&self, py: Python<'py>)
+                                            -> Bound<'py, PyArray2<bool>> {
+        let n = self.env.len();
+        let mut masks = vec![false; n * ACTION_SPACE];
+        self.env.action_masks(&mut masks);
+        Array2::from_shape_vec((n, ACTION_SPACE), masks)
+               .expect("the buffer fits the games")
+               .into_pyarray(py)
+    }
+
+    /// The game played by the `i`-th environment.
+    fn state(&self, i: usize) -> PyResult<PyGameState> {
+        if i >= self.env.len() {
+            return Err(value_error(format!("no game {}", i)));
+        }
+        Ok(PyGameState { state: self.env.env(i).state().clone() })
+    }
+}
+
+#[pymodule]
+fn iatomas(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGameState>()?;
+    m.add_class::<PySpawnModel>()?;
+    m.add_class::<PyBatchEnv>()?;
+    m.add("ACTIONS", ACTION_SPACE)?;
+    m.add("OBSERVATION_SIZE", OBSERVATION_SIZE)?;
+    Ok(())
+}
@@ -0,0 +1,354 @@
+use super::SpawnRecord;
+use crate::game::{AtomType, GameState, MIN_DPLUS_SCORE, MIN_WHITE_SCORE,
+                  SpawnModel};
+
+/// Categories of atoms before the regular ones, see `category`
+const SPECIALS: [AtomType; 4] = [
+    AtomType::Plus, AtomType::Minus, AtomType::DarkPlus, AtomType::Neutrino,
+];
+
+/// Smallest expected count of a chi-square bin, smaller ones being merged
+const MIN_EXPECTED: f64 = 5.0;
+
+/// Configuration of `check_spawn`.
+#[derive(Clone, Debug)]
+pub struct CheckConfig {
+    /// Width of the time windows of the chi-square tests
+    pub window: u32,
+    /// Atoms drawn from the model for each recorded one, to estimate the
+    /// distribution expected at its time and score
+    pub samples: usize,
+    /// Seed of the draws
+    pub seed: u64,
+    /// Significance level under which a test flags the model as off
+    pub alpha: f64,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            window: 100,
+            samples: 200,
+            seed: 0,
+            alpha: 0.01,
+        }
+    }
+}
+
+/// Chi-square test of the atoms recorded in a time window.
+#[derive(Clone, Debug)]
+pub struct WindowCheck {
+    /// First time of the window
+    pub start: u32,
+    /// Time following the last one of the window
+    pub end: u32,
+    pub atoms: usize,
+    pub chi_square: f64,
+    pub degrees: usize,
+    pub p_value: f64,
+    /// Kolmogorov-Smirnov distance on the index of the regular atoms
+    pub ks_distance: f64,
+    pub off: bool,
+}
+
+/// Observed and expected number of a special atom.
+#[derive(Clone, Debug)]
+pub struct SpecialCheck {
+    pub atom: AtomType,
+    /// Recorded atoms which could have been this one, given their score
+    pub eligible: usize,
+    pub observed: usize,
+    pub expected: f64,
+    /// Standard score of the observed number
+    pub z: f64,
+    pub p_value: f64,
+    pub off: bool,
+}
+
+/// How well a spawn model fits recorded atoms, see `check_spawn`.
+#[derive(Clone, Debug)]
+pub struct SpawnCheck {
+    pub windows: Vec<WindowCheck>,
+    /// Kolmogorov-Smirnov distance on the index of all the regular atoms
+    pub ks_distance: f64,
+    /// Distance over which the model is off, at the significance level
+    pub ks_critical: f64,
+    pub specials: Vec<SpecialCheck>,
+}
+
+impl SpawnCheck {
+    /// Returns `true` if any of the tests flags the model as off
+    pub fn is_off(&self) -> bool {
+        self.windows.iter().any(|w| w.off)
+            || self.ks_distance > self.ks_critical
+            || self.specials.iter().any(|s| s.off)
+    }
+}
+
+/// Index of `atom` in the distributions: the special atoms in the order of
+/// `SPECIALS`, then the regular atoms by increasing index.
+fn category(atom: &AtomType) -> Option<usize> {
+    match atom {
+        AtomType::Atom(z) => Some(SPECIALS.len() + *z as usize),
+        _ => SPECIALS.iter().position(|s| s == atom)
+    }
+}
+
+/// Whether `atom` could come with `record`, given its score if known
+fn is_eligible(record: &SpawnRecord, atom: &AtomType) -> bool {
+    let min_score = match atom {
+        AtomType::DarkPlus => MIN_DPLUS_SCORE,
+        AtomType::Neutrino => MIN_WHITE_SCORE,
+        _ => 0
+    };
+    record.score.is_none_or(|score| score >= min_score)
+}
+
+fn add(total: &mut Vec<f64>, values: &[f64]) {
+    if total.len() < values.len() {
+        total.resize(values.len(), 0.0);
+    }
+    for (t, v) in total.iter_mut().zip(values) {
+        *t += v;
+    }
+}
+
+/// Distribution of the atom coming with each of `records`, estimated by
+/// drawing them through `GameState::draw_incoming` at their time and score.
+/// An unknown score is taken as high enough for every atom.
+fn expected_distributions(model: &SpawnModel, records: &[SpawnRecord],
+                          config: &CheckConfig) -> Vec<Vec<f64>> {
+    let mut state = GameState::new(config.seed, model.clone());
+    let weight = 1.0 / config.samples.max(1) as f64;
+    records.iter().map(|record| {
+        let mut distribution = Vec::new();
+        state.time = record.time;
+        state.score = record.score.unwrap_or(u32::MAX);
+        for _ in 0..config.samples.max(1) {
+            state.draw_incoming();
+            if let Some(c) = category(&state.incoming.t) {
+                if distribution.len() <= c {
+                    distribution.resize(c + 1, 0.0);
+                }
+                distribution[c] += weight;
+            }
+        }
+        distribution
+    }).collect()
+}
+
+/// Chi-square statistic and degrees of freedom of `observed` counts against
+/// `expected` ones. Bins expected less than `MIN_EXPECTED` times are merged
+/// together, and into the smallest other bin if still too small.
+fn chi_square(observed: &[f64], expected: &[f64]) -> (f64, usize) {
+    let mut bins: Vec<(f64, f64)> = Vec::new();
+    let mut rest = (0.0, 0.0);
+    for (i, &e) in expected.iter().enumerate() {
+        let o = observed.get(i).cloned().unwrap_or(0.0);
+        if e >= MIN_EXPECTED { bins.push((o, e)); }
+        else                 { rest.0 += o; rest.1 += e; }
+    }
+    rest.0 += observed.iter().skip(expected.len()).sum::<f64>();
+    if rest.1 >= MIN_EXPECTED || bins.is_empty() {
+        bins.push(rest);
+    } else if rest.0 > 0.0 || rest.1 > 0.0 {
+        let smallest = bins.iter_mut()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap()).unwrap();
+        smallest.0 += rest.0;
+        smallest.1 += rest.1;
+    }
+
+    let statistic = bins.iter().map(|&(o, e)| {
+        if e > 0.0          { (o - e) * (o - e) / e }
+        else if o > 0.0     { f64::INFINITY }
+        else                { 0.0 }
+    }).sum();
+    (statistic, bins.len().saturating_sub(1))
+}
+
+/// Kolmogorov-Smirnov distance between the observed and expected
+/// distributions of the regular atoms, each normalised on them.
+fn ks_distance(observed: &[f64], expected: &[f64]) -> f64 {
+    let regular = |d: &[f64]| -> Vec<f64> {
+        d.iter().skip(SPECIALS.len()).cloned().collect()
+    };
+    let (observed, expected) = (regular(observed), regular(expected));
+    let total_o: f64 = observed.iter().sum();
+    let total_e: f64 = expected.iter().sum();
+    if total_o == 0.0 || total_e == 0.0 {
+        return 0.0;
+    }
+    let (mut cdf_o, mut cdf_e, mut distance) = (0.0, 0.0, 0.0_f64);
+    for i in 0..observed.len().max(expected.len()) {
+        cdf_o += observed.get(i).cloned().unwrap_or(0.0) / total_o;
+        cdf_e += expected.get(i).cloned().unwrap_or(0.0) / total_e;
+        distance = distance.max((cdf_o - cdf_e).abs());
+    }
+    distance
+}
+
+/// `ln(Γ(x))` for `x > 0`, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1,
+        -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+        -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS.iter().skip(1).enumerate()
+        .fold(COEFFICIENTS[0], |s, (i, c)| s + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t
+        + sum.ln()
+}
+
+/// The regularised upper incomplete gamma function `Q(a, x)`, by its series
+/// for small `x` and its continued fraction otherwise
+fn upper_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    if x.is_infinite() {
+        return 0.0;
+    }
+    let front = (a * x.ln() - x - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term < sum * 1e-15 {
+                break;
+            }
+        }
+        1.0 - front * sum
+    } else {
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..1000 {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny { d = tiny; }
+            c = b + an / c;
+            if c.abs() < tiny { c = tiny; }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        front * h
+    }
+}
+
+/// Probability that a chi-square with `degrees` degrees of freedom exceeds
+/// `statistic`
+fn chi_square_p_value(statistic: f64, degrees: usize) -> f64 {
+    if degrees == 0 { 1.0 }
+    else            { upper_gamma(degrees as f64 / 2.0, statistic / 2.0) }
+}
+
+/// Checks how well `model` fits `records`:
+///
+/// - a chi-square test of the atoms of each time window of `config.window`,
+///   over the special atoms and the regular ones by index
+///
+/// - the Kolmogorov-Smirnov distance between the observed and expected
+///   distributions of the regular atoms, per window and over all the records
+///
+/// - the observed and expected numbers of each special atom
+///
+/// The expected distributions come from drawing `config.samples` atoms
+/// through `GameState::draw_incoming` for each record, so that the check
+/// covers the drawing code as well as the model.
+pub fn check_spawn(model: &SpawnModel, records: &[SpawnRecord],
+                   config: &CheckConfig) -> SpawnCheck {
+    let distributions = expected_distributions(model, records, config);
+    let observed: Vec<Vec<f64>> = records.iter().map(|r| {
+        let mut d = Vec::new();
+        if let Some(c) = category(&r.atom) {
+            d.resize(c + 1, 0.0);
+            d[c] = 1.0;
+        }
+        d
+    }).collect();
+
+    let window = config.window.max(1);
+    let last = records.iter().map(|r| r.time).max().unwrap_or(0);
+    let mut windows = Vec::new();
+    for start in (0..=last / window).map(|k| k * window) {
+        let (mut o, mut e, mut atoms) = (Vec::new(), Vec::new(), 0);
+        for (i, r) in records.iter().enumerate() {
+            if r.time >= start && r.time - start < window {
+                add(&mut o, &observed[i]);
+                add(&mut e, &distributions[i]);
+                atoms += 1;
+            }
+        }
+        if atoms == 0 {
+            continue;
+        }
+        let (chi_square, degrees) = chi_square(&o, &e);
+        let p_value = chi_square_p_value(chi_square, degrees);
+        windows.push(WindowCheck {
+            start,
+            end: start.saturating_add(window),
+            atoms,
+            chi_square,
+            degrees,
+            p_value,
+            ks_distance: ks_distance(&o, &e),
+            off: p_value < config.alpha,
+        });
+    }
+
+    let (mut o, mut e) = (Vec::new(), Vec::new());
+    for (observed, expected) in observed.iter().zip(&distributions) {
+        add(&mut o, observed);
+        add(&mut e, expected);
+    }
+    let regular = o.iter().skip(SPECIALS.len()).sum::<f64>();
+    let ks_critical = if regular > 0.0 {
+        (-(config.alpha / 2.0).ln() / 2.0).sqrt() / regular.sqrt()
+    } else {
+        f64::INFINITY
+    };
+
+    let specials = SPECIALS.iter().enumerate().map(|(c, atom)| {
+        let at = |d: &Vec<f64>| d.get(c).cloned().unwrap_or(0.0);
+        let observed = records.iter().filter(|r| r.atom == *atom).count();
+        let expected: f64 = distributions.iter().map(at).sum();
+        let variance: f64 = distributions.iter()
+                                         .map(|d| at(d) * (1.0 - at(d)))
+                                         .sum();
+        let deviation = observed as f64 - expected;
+        let z = if variance > 0.0      { deviation / variance.sqrt() }
+                else if deviation == 0.0 { 0.0 }
+                else                   { deviation.signum() * f64::INFINITY };
+        // Two-sided normal p-value, as `erfc(|z| / sqrt(2))`
+        let p_value = upper_gamma(0.5, z * z / 2.0);
+        SpecialCheck {
+            atom: atom.clone(),
+            eligible: records.iter().filter(|r| is_eligible(r, atom)).count(),
+            observed,
+            expected,
+            z,
+            p_value,
+            off: p_value < config.alpha,
+        }
+    }).collect();
+
+    SpawnCheck {
+        windows,
+        ks_distance: ks_distance(&o, &e),
+        ks_critical,
+        specials,
+    }
+}
@@ -0,0 +1,601 @@
+use std::cmp::Ordering;
+
+use lazy_static::lazy_static;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::SpawnRecord;
+use crate::game::{ATOMS_SYMBOLS, AtomType, MIN_DPLUS_SCORE, MIN_WHITE_SCORE,
+                  SpawnParams};
+
+/// Weight of a uniform distribution over all the atoms mixed into the
+/// shifted Binomial of the regular atoms, so that a few atoms out of its
+/// support (like the low atoms that come late in real games) do not make the
+/// likelihood of the whole log 0
+pub const OUTLIER_WEIGHT: f64 = 1e-3;
+
+/// Evaluations of the likelihood allowed to a run of the optimiser
+const MAX_EVALUATIONS: usize = 2000;
+/// Times the optimiser is restarted from its last result, as long as it
+/// improves it
+const RESTARTS: usize = 3;
+
+/// Directions along which `scan` looks for better parameters
+const SCAN_DIRECTIONS: usize = 8;
+/// Step of the points tried by `scan`, for shifts and numbers of trials
+/// (and a fifth of it for `p`)
+const SCAN_STEP: f64 = 0.05;
+/// Points tried by `scan` on each side of the current one
+const SCAN_WIDTH: i32 = 40;
+/// Rounds of `scan` along all its directions
+const MAX_SCANS: usize = 100;
+/// Random moves away from the best parameters found, to look for better
+/// ones around
+const KICKS: usize = 20;
+/// Temperature of the continuous version of the likelihood giving the
+/// standard errors of the parameters of regular atoms
+const ERROR_TEMPERATURE: f64 = 0.2;
+/// Steps of the finite differences giving those standard errors, in the
+/// order of `with_regular`
+const HESSIAN_STEPS: [f64; 5] = [1e-4, 1e-2, 1e-4, 1e-2, 1e-3];
+
+/// Parameters of the spawn model estimated from recorded atoms.
+#[derive(Clone, Debug)]
+pub struct SpawnFit {
+    pub params: SpawnParams,
+    /// Standard error of every parameter, NaN when it cannot be estimated
+    pub std_errors: SpawnParams,
+    /// Log-likelihood of the atoms under `params`
+    pub log_likelihood: f64,
+    pub atoms: usize,
+}
+
+/// Whether a Dark Plus and a Neutrino could come with `record`. They are
+/// assumed to when the score is not known.
+fn eligibility(record: &SpawnRecord) -> (bool, bool) {
+    match record.score {
+        Some(score) => (score >= MIN_DPLUS_SCORE, score >= MIN_WHITE_SCORE),
+        None => (true, true)
+    }
+}
+
+/// Log-likelihood of the kinds of atoms (special or regular) of `records`
+fn special_log_likelihood(params: &SpawnParams, records: &[SpawnRecord])
+                                                                    -> f64 {
+    let chances = [params.plus, params.minus, params.dark_plus,
+                   params.neutrino];
+    if chances.iter().any(|c| *c < 0.0) {
+        return f64::NEG_INFINITY;
+    }
+    let mut l = 0.0;
+    for record in records {
+        let (dark_plus, neutrino) = eligibility(record);
+        let dark_plus = if dark_plus { params.dark_plus } else { 0.0 };
+        let neutrino = if neutrino { params.neutrino } else { 0.0 };
+        let chance = match record.atom {
+            AtomType::Plus => params.plus,
+            AtomType::Minus => params.minus,
+            AtomType::DarkPlus => dark_plus,
+            AtomType::Neutrino => neutrino,
+            _ => 1.0 - params.plus - params.minus - dark_plus - neutrino,
+        };
+        if chance <= 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        l += chance.ln();
+    }
+    l
+}
+
+lazy_static! {
+    /// `LN_FACTORIALS[n]` is the logarithm of `n!`
+    static ref LN_FACTORIALS: Vec<f64> = {
+        let mut table = vec![0.0];
+        for n in 1..1024 {
+            table.push(table[n - 1] + (n as f64).ln());
+        }
+        table
+    };
+}
+
+/// Logarithm of `n!`, from Stirling's series beyond the table: numbers of
+/// trials far beyond it come up when scanning `p` towards 0
+fn ln_factorial(n: u64) -> f64 {
+    match LN_FACTORIALS.get(n as usize) {
+        Some(v) => *v,
+        None => {
+            let n = n as f64;
+            n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln()
+                + 1.0 / (12.0 * n) - 1.0 / (360.0 * n * n * n)
+        }
+    }
+}
+
+/// Logarithm of the chance of `k` successes out of `n` trials of chance `p`
+fn ln_binomial(n: u64, k: u64, p: f64) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    let successes = if k == 0 { 0.0 } else { k as f64 * p.ln() };
+    let failures = if k == n { 0.0 } else { (n - k) as f64 * (-p).ln_1p() };
+    ln_choose(n, k) + successes + failures
+}
+
+/// Logarithm of the number of ways to choose `k` items out of `n`, summing
+/// the logarithms of the factors of `n! / (n - k)!` when `n` is beyond the
+/// table, as the difference of the logarithms of factorials would lose all
+/// precision for large `n`
+fn ln_choose(n: u64, k: u64) -> f64 {
+    let k = k.min(n - k);
+    let table = LN_FACTORIALS.len() as u64;
+    if n < table || k >= table {
+        return ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k);
+    }
+    (0..k).map(|i| ((n - i) as f64).ln()).sum::<f64>() - ln_factorial(k)
+}
+
+/// Log-likelihood of regular atoms, given as `(time, atom index)`
+fn regular_log_likelihood(params: &SpawnParams, atoms: &[(u32, u8)]) -> f64 {
+    if !(0.0..=1.0).contains(&params.p) {
+        return f64::NEG_INFINITY;
+    }
+    let floor = OUTLIER_WEIGHT / ATOMS_SYMBOLS.len() as f64;
+    atoms.iter().map(|(t, atom)| {
+        // Atomic number of the atom, minus the shift
+        let k = *atom as i64 + 1 - params.shift(*t) as i64;
+        let chance = if k < 0 { 0.0 }
+                     else     { ln_binomial(params.n(*t), k as u64,
+                                            params.p).exp() };
+        ((1.0 - OUTLIER_WEIGHT) * chance + floor).ln()
+    }).sum()
+}
+
+/// Log-likelihood of regular atoms in a continuous version of the model:
+/// instead of being rounded, the shift and the number of trials of the
+/// Binomial are drawn between the integers around them, with the chance of
+/// the upper one going smoothly from 0 to 1 around the middle, in a width of
+/// about `temperature`. It tends to the likelihood of the model as
+/// `temperature` tends to 0, and unlike it it can be optimised and derived.
+fn smooth_log_likelihood(params: &SpawnParams, atoms: &[(u32, u8)],
+                         temperature: f64) -> f64 {
+    if !(0.0..=1.0).contains(&params.p) {
+        return f64::NEG_INFINITY;
+    }
+    let floor = OUTLIER_WEIGHT / ATOMS_SYMBOLS.len() as f64;
+    let upper = |x: f64| 1.0 / (1.0 + (-(x - x.floor() - 0.5)
+                                       / temperature).exp());
+    atoms.iter().map(|(t, atom)| {
+        let t = *t as f64;
+        let shift = params.shift_slope * t + params.shift_intercept;
+        let n = (params.n_slope * t + params.n_intercept).max(0.0);
+        let (s0, n0) = (shift.floor(), n.floor());
+        let (ws, wn) = (upper(shift), upper(n));
+        let mut chance = 0.0;
+        for (ds, w1) in [(0.0, 1.0 - ws), (1.0, ws)] {
+            for (dn, w2) in [(0.0, 1.0 - wn), (1.0, wn)] {
+                let k = *atom as f64 + 1.0 - s0 - ds;
+                if w1 * w2 > 0.0 && k >= 0.0 {
+                    chance += w1 * w2 * ln_binomial((n0 + dn) as u64, k as u64,
+                                                    params.p).exp();
+                }
+            }
+        }
+        ((1.0 - OUTLIER_WEIGHT) * chance + floor).ln()
+    }).sum()
+}
+
+fn regular_atoms(records: &[SpawnRecord]) -> Vec<(u32, u8)> {
+    records.iter().filter_map(|r| match r.atom {
+        AtomType::Atom(z) => Some((r.time, z)),
+        _ => None
+    }).collect()
+}
+
+/// Log-likelihood of `records` under the spawn model of `params`.
+pub fn log_likelihood(params: &SpawnParams, records: &[SpawnRecord]) -> f64 {
+    special_log_likelihood(params, records)
+        + regular_log_likelihood(params, &regular_atoms(records))
+}
+
+/// Minimises `f` with the simplex method of Nelder and Mead, starting from
+/// `start` and a simplex of sides `steps`.
+fn nelder_mead<F>(f: &F, start: &[f64], steps: &[f64]) -> (Vec<f64>, f64)
+                                            where F: Fn(&[f64]) -> f64 {
+    let eval = |x: &[f64]| {
+        let v = f(x);
+        if v.is_nan() { f64::INFINITY } else { v }
+    };
+    let n = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = (0..=n).map(|i| {
+        let mut x = start.to_vec();
+        if i > 0 {
+            x[i - 1] += steps[i - 1];
+        }
+        let v = eval(&x);
+        (x, v)
+    }).collect();
+    let mut evaluations = n + 1;
+    // Point `c + s * (x - c)`
+    let towards = |c: &[f64], x: &[f64], s: f64| -> Vec<f64> {
+        c.iter().zip(x).map(|(c, x)| c + s * (x - c)).collect()
+    };
+
+    while evaluations < MAX_EVALUATIONS {
+        simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        let (best, worst) = (simplex[0].1, simplex[n].1);
+        if worst - best <= 1e-10 * (1.0 + best.abs()) {
+            break;
+        }
+        let mut centroid = vec![0.0; n];
+        for (x, _) in &simplex[..n] {
+            for (c, xi) in centroid.iter_mut().zip(x) {
+                *c += xi / n as f64;
+            }
+        }
+
+        let reflected = towards(&centroid, &simplex[n].0, -1.0);
+        let fr = eval(&reflected);
+        evaluations += 1;
+        if fr < best {
+            let expanded = towards(&centroid, &simplex[n].0, -2.0);
+            let fe = eval(&expanded);
+            evaluations += 1;
+            simplex[n] = if fe < fr { (expanded, fe) } else { (reflected, fr) };
+        } else if fr < simplex[n - 1].1 {
+            simplex[n] = (reflected, fr);
+        } else {
+            let contracted = if fr < worst {
+                towards(&centroid, &reflected, 0.5)
+            } else {
+                towards(&centroid, &simplex[n].0, 0.5)
+            };
+            let fc = eval(&contracted);
+            evaluations += 1;
+            if fc < fr.min(worst) {
+                simplex[n] = (contracted, fc);
+            } else {
+                let lowest = simplex[0].0.clone();
+                for (x, v) in simplex.iter_mut().skip(1) {
+                    *x = towards(&lowest, x, 0.5);
+                    *v = eval(x);
+                }
+                evaluations += n;
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+    simplex.swap_remove(0)
+}
+
+/// Runs `nelder_mead` from each of `starts`, restarting it from its result
+/// while this improves, and keeps the best point.
+fn minimize<F>(f: &F, starts: &[Vec<f64>], steps: &[f64]) -> (Vec<f64>, f64)
+                                            where F: Fn(&[f64]) -> f64 {
+    let mut best: Option<(Vec<f64>, f64)> = None;
+    for start in starts {
+        let (mut x, mut v) = nelder_mead(f, start, steps);
+        for _ in 0..RESTARTS {
+            let (x2, v2) = nelder_mead(f, &x, steps);
+            if v2 >= v - 1e-9 {
+                break;
+            }
+            x = x2;
+            v = v2;
+        }
+        if best.as_ref().is_none_or(|(_, b)| v < *b) {
+            best = Some((x, v));
+        }
+    }
+    best.expect("at least one start")
+}
+
+/// `params` with the parameters of regular atoms taken from `x`: shift
+/// slope and intercept, n slope and intercept, p
+fn with_regular(params: &SpawnParams, x: &[f64]) -> SpawnParams {
+    SpawnParams {
+        shift_slope: x[0],
+        shift_intercept: x[1],
+        n_slope: x[2],
+        n_intercept: x[3],
+        p: x[4],
+        ..params.clone()
+    }
+}
+
+/// Parameters of regular atoms as the shift and the number of trials at the
+/// first and last times of the atoms, and `p`: the likelihood is far less
+/// skewed along these than along slopes and intercepts.
+#[derive(Clone, Copy)]
+struct Ends {
+    first: f64,
+    last: f64,
+}
+
+impl Ends {
+    fn new(atoms: &[(u32, u8)]) -> Self {
+        let first = atoms.iter().map(|(t, _)| *t).min().unwrap_or(0) as f64;
+        let last = atoms.iter().map(|(t, _)| *t).max().unwrap_or(0) as f64;
+        Self { first, last: last.max(first + 1.0) }
+    }
+
+    /// `[shift(first), shift(last), n(first), n(last), p]` from parameters
+    /// in the order of `with_regular`
+    fn to_ends(self, x: &[f64]) -> [f64; 5] {
+        [x[0] * self.first + x[1], x[0] * self.last + x[1],
+         x[2] * self.first + x[3], x[2] * self.last + x[3], x[4]]
+    }
+
+    /// Inverse of `to_ends`
+    fn to_regular(self, y: &[f64; 5]) -> Vec<f64> {
+        let span = self.last - self.first;
+        let a = (y[1] - y[0]) / span;
+        let c = (y[3] - y[2]) / span;
+        vec![a, y[0] - a * self.first, c, y[2] - c * self.first, y[4]]
+    }
+}
+
+/// The point `j` steps away from `y` in the direction `direction` of
+/// `SCAN_DIRECTIONS`
+fn scan_point(y: &[f64; 5], direction: usize, j: i32) -> [f64; 5] {
+    let mut y = *y;
+    let step = SCAN_STEP * j as f64;
+    match direction {
+        0..=3 => y[direction] += step,
+        4 => { y[0] += step; y[1] += step; },
+        5 => { y[2] += step; y[3] += step; },
+        6 => y[4] += step / 5.0,
+        _ => {
+            // Changes `p` keeping the mean and the variance of the atoms
+            let q = y[4] + step / 5.0;
+            let ratio = y[4] * (1.0 - y[4]) / (q * (1.0 - q));
+            for (s, n) in [(0, 2), (1, 3)] {
+                let m = y[n] * ratio;
+                y[s] += y[n] * y[4] - m * q;
+                y[n] = m;
+            }
+            y[4] = q;
+        }
+    }
+    y
+}
+
+/// Negated log-likelihood of regular atoms, with parameters as in `Ends`
+fn ends_objective(atoms: &[(u32, u8)], ends: Ends, y: &[f64; 5]) -> f64 {
+    let params = with_regular(&SpawnParams::default(), &ends.to_regular(y));
+    let v = -regular_log_likelihood(&params, atoms);
+    if v.is_nan() { f64::INFINITY } else { v }
+}
+
+/// Maximises the likelihood of regular atoms from `start` (as in `Ends`), by
+/// moving in turn to the best point along each of the directions of
+/// `scan_point`, until none improves it.
+///
+/// The likelihood being piecewise constant, scanning whole segments is what
+/// gets it out of its plateaus and small local maxima.
+///
+/// Returns: the parameters and their negated log-likelihood
+fn scan(atoms: &[(u32, u8)], ends: Ends, start: [f64; 5]) -> ([f64; 5], f64) {
+    let f = |y: &[f64; 5]| ends_objective(atoms, ends, y);
+    let mut y = start;
+    let mut value = f(&y);
+    for _ in 0..MAX_SCANS {
+        let mut improved = false;
+        for direction in 0..SCAN_DIRECTIONS {
+            for j in (-SCAN_WIDTH..=SCAN_WIDTH).filter(|j| *j != 0) {
+                let z = scan_point(&y, direction, j);
+                let v = f(&z);
+                if v < value - 1e-9 {
+                    y = z;
+                    value = v;
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    (y, value)
+}
+
+/// Estimates the parameters of regular atoms (in the order of
+/// `with_regular`): `scan` from the moment starts of a grid of `p`s, then
+/// from random moves around the best result while they improve it.
+fn fit_regular(atoms: &[(u32, u8)]) -> Vec<f64> {
+    let ends = Ends::new(atoms);
+    let (mut y, mut value) = (1..10)
+        .map(|i| {
+            let start = moment_start(atoms, i as f64 / 10.0);
+            scan(atoms, ends, ends.to_ends(&start))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .expect("the grid is not empty");
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    for _ in 0..KICKS {
+        let mut z = y;
+        for (i, zi) in z.iter_mut().enumerate() {
+            let scale = if i == 4 { 0.05 } else { 0.5 };
+            *zi += scale * (rng.gen::<f64>() * 2.0 - 1.0);
+        }
+        let (z, v) = scan(atoms, ends, z);
+        if v < value - 1e-9 {
+            y = z;
+            value = v;
+        }
+    }
+    ends.to_regular(&y)
+}
+
+/// Inverse of a symmetric positive definite matrix, by the Gauss-Jordan
+/// method, `None` if it is not one.
+fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut a: Vec<Vec<f64>> = matrix.iter().enumerate().map(|(i, row)| {
+        let mut row = row.clone();
+        row.extend((0..n).map(|j| if i == j { 1.0 } else { 0.0 }));
+        row
+    }).collect();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|i, j| {
+            a[*i][col].abs().partial_cmp(&a[*j][col].abs())
+                      .unwrap_or(Ordering::Equal)
+        })?;
+        if a[pivot][col].abs().partial_cmp(&1e-300) != Some(Ordering::Greater) {
+            return None;
+        }
+        a.swap(col, pivot);
+        let p = a[col][col];
+        a[col].iter_mut().for_each(|v| *v /= p);
+        for i in 0..n {
+            if i != col {
+                let factor = a[i][col];
+                let pivot_row = a[col].clone();
+                a[i].iter_mut().zip(pivot_row)
+                    .for_each(|(v, pv)| *v -= factor * pv);
+            }
+        }
+    }
+    let inverse: Vec<Vec<f64>> = a.into_iter().map(|row| row[n..].to_vec())
+                                  .collect();
+    if (0..n).all(|i| inverse[i][i] > 0.0) { Some(inverse) } else { None }
+}
+
+/// Standard errors of the parameters of regular atoms estimated near `x`,
+/// from the observed information (the Hessian of the negated
+/// log-likelihood) of a continuous version of the likelihood at its
+/// maximum, by finite differences. NaN when it cannot be inverted.
+fn regular_std_errors(atoms: &[(u32, u8)], x: &[f64]) -> Vec<f64> {
+    let base = SpawnParams::default();
+    let smooth = |x: &[f64]| {
+        -smooth_log_likelihood(&with_regular(&base, x), atoms,
+                               ERROR_TEMPERATURE)
+    };
+    let steps: Vec<f64> = HESSIAN_STEPS.iter().map(|h| h * 10.0).collect();
+    let x = &minimize(&smooth, &[x.to_vec()], &steps).0;
+    let f = |dx: &[(usize, f64)]| {
+        let mut y = x.to_vec();
+        for (i, d) in dx {
+            y[*i] += d;
+        }
+        -smooth_log_likelihood(&with_regular(&base, &y), atoms,
+                               ERROR_TEMPERATURE)
+    };
+    let h = HESSIAN_STEPS;
+    let n = x.len();
+    let center = f(&[]);
+    let mut hessian = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        hessian[i][i] = (f(&[(i, h[i])]) - 2.0 * center + f(&[(i, -h[i])]))
+                        / (h[i] * h[i]);
+        for j in 0..i {
+            let v = (f(&[(i, h[i]), (j, h[j])]) - f(&[(i, h[i]), (j, -h[j])])
+                     - f(&[(i, -h[i]), (j, h[j])])
+                     + f(&[(i, -h[i]), (j, -h[j])])) / (4.0 * h[i] * h[j]);
+            hessian[i][j] = v;
+            hessian[j][i] = v;
+        }
+    }
+    match invert(&hessian) {
+        Some(inverse) => (0..n).map(|i| inverse[i][i].sqrt()).collect(),
+        None => vec![f64::NAN; n]
+    }
+}
+
+/// Least squares line through the points `(t, y)`: slope and intercept
+fn least_squares(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (t, y) in points {
+        cov += (t - mean_t) * (y - mean_y);
+        var += (t - mean_t) * (t - mean_t);
+    }
+    let slope = if var > 0.0 { cov / var } else { 0.0 };
+    (slope, mean_y - slope * mean_t)
+}
+
+/// Parameters with a given `p` whose mean and variance follow the least
+/// squares lines through the atomic numbers and their squared residuals
+fn moment_start(atoms: &[(u32, u8)], p: f64) -> Vec<f64> {
+    let points: Vec<(f64, f64)> = atoms.iter()
+        .map(|(t, z)| (*t as f64, *z as f64 + 1.0))
+        .collect();
+    let (slope, intercept) = least_squares(&points);
+    let squares: Vec<(f64, f64)> = points.iter().map(|(t, z)| {
+        (*t, (z - slope * t - intercept).powi(2))
+    }).collect();
+    let (var_slope, var_intercept) = least_squares(&squares);
+    let c = (var_slope / (p * (1.0 - p))).max(0.0);
+    let d = (var_intercept / (p * (1.0 - p))).max(0.0);
+    vec![slope - c * p, intercept - d * p, c, d, p]
+}
+
+/// Estimates the chances of the special atoms, starting from their
+/// frequencies among the atoms where they could come
+fn fit_special(records: &[SpawnRecord]) -> (SpawnParams, [usize; 4]) {
+    let mut counts = [0; 4];
+    let mut eligible = [records.len(), records.len(), 0, 0];
+    for record in records {
+        let (dark_plus, neutrino) = eligibility(record);
+        eligible[2] += dark_plus as usize;
+        eligible[3] += neutrino as usize;
+        match record.atom {
+            AtomType::Plus => counts[0] += 1,
+            AtomType::Minus => counts[1] += 1,
+            AtomType::DarkPlus => counts[2] += 1,
+            AtomType::Neutrino => counts[3] += 1,
+            _ => {}
+        }
+    }
+    let start: Vec<f64> = counts.iter().zip(&eligible)
+        .map(|(c, e)| if *e > 0 { *c as f64 / *e as f64 } else { 0.0 })
+        .collect();
+    let with_special = |x: &[f64]| SpawnParams {
+        plus: x[0],
+        minus: x[1],
+        dark_plus: x[2],
+        neutrino: x[3],
+        ..SpawnParams::default()
+    };
+    let f = |x: &[f64]| -special_log_likelihood(&with_special(x), records);
+    let (x, _) = minimize(&f, &[start], &[0.01; 4]);
+    (with_special(&x), eligible)
+}
+
+/// Estimates the parameters of the spawn model by maximum likelihood on the
+/// atoms of `records`.
+///
+/// The standard errors of the chances of special atoms are the ones of
+/// binomial proportions, and the ones of regular atoms come from the
+/// observed information.
+pub fn fit_spawn(records: &[SpawnRecord]) -> Result<SpawnFit, String> {
+    let atoms = regular_atoms(records);
+    if atoms.is_empty() {
+        return Err("no regular atom to fit the model on".to_string());
+    }
+    let (special, eligible) = fit_special(records);
+    let x = fit_regular(&atoms);
+    let params = with_regular(&special, &x);
+
+    let errors = regular_std_errors(&atoms, &x);
+    // Not estimated for an atom that could not come at all
+    let proportion = |p: f64, n: usize| {
+        if n == 0 { f64::NAN }
+        else      { (p * (1.0 - p)).max(0.0).sqrt() / (n as f64).sqrt() }
+    };
+    let std_errors = SpawnParams {
+        plus: proportion(params.plus, eligible[0]),
+        minus: proportion(params.minus, eligible[1]),
+        dark_plus: proportion(params.dark_plus, eligible[2]),
+        neutrino: proportion(params.neutrino, eligible[3]),
+        ..with_regular(&SpawnParams::default(), &errors)
+    };
+
+    Ok(SpawnFit {
+        log_likelihood: log_likelihood(&params, records),
+        params,
+        std_errors,
+        atoms: records.len(),
+    })
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::SpawnRecord;
+use crate::game::{AtomType, SpawnModel, atom_symbol, parse_atom};
+use crate::invalid_data;
+
+/// Entries written per line by `write_spawn_log`
+const ENTRIES_PER_LINE: usize = 16;
+
+/// The atom written `code` in the encoding of `estimate.py`: `0` for a Plus,
+/// `-1` for a Minus, `-2` for a Dark Plus, `-3` for a Neutrino, and the
+/// atomic number (`1` for Hydrogen) of a regular atom.
+pub fn atom_from_code(code: i64) -> Option<AtomType> {
+    match code {
+        0 => Some(AtomType::Plus),
+        -1 => Some(AtomType::Minus),
+        -2 => Some(AtomType::DarkPlus),
+        -3 => Some(AtomType::Neutrino),
+        z if (1..=u8::MAX as i64 + 1).contains(&z) => {
+            Some(AtomType::Atom((z - 1) as u8))
+        },
+        _ => None
+    }
+}
+
+/// The atom of a spawn log entry: an integer in the encoding of
+/// `estimate.py` (see `atom_from_code`), or a symbol of `game::atom_symbol`
+fn parse_log_atom(text: &str) -> Option<AtomType> {
+    match text.parse::<i64>() {
+        Ok(code) => atom_from_code(code),
+        Err(_) => parse_atom(text).filter(|a| *a != AtomType::None)
+    }
+}
+
+/// Reads a spawn log: the atoms that came in a game, in order.
+///
+/// The entries are separated by spaces, commas or new lines, and everything
+/// after a `#` on a line is ignored, so that the `data` list of
+/// `estimate.py` is a valid log. An entry is written
+/// `<atom>[@<time>][:<score>]`, where:
+///
+/// - `<atom>` is either an integer in the encoding of `estimate.py` (see
+///   `atom_from_code`), or a symbol as in positions (see `game::atom_symbol`):
+///   `Li`, `+`, `-`, `D+`, `Nu`...
+///
+/// - `<time>` is the time at which the atom came, as `GameState::time`. It is
+///   the one of the previous entry plus one when not given, and `0` for the
+///   first entry.
+///
+/// - `<score>` is the score when the atom came, unknown when not given.
+///
+/// For example `H@0:0 H + He:4 -3@40:1620`.
+pub fn read_spawn_log<P: AsRef<Path>>(path: P)
+                                        -> io::Result<Vec<SpawnRecord>> {
+    parse_spawn_log(&fs::read_to_string(path)?)
+}
+
+/// Parses the content of a spawn log, see `read_spawn_log`
+pub fn parse_spawn_log(content: &str) -> io::Result<Vec<SpawnRecord>> {
+    let mut records: Vec<SpawnRecord> = Vec::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        for entry in line.split(|c: char| c == ',' || c.is_whitespace()) {
+            if entry.is_empty() {
+                continue;
+            }
+            let (rest, score) = match entry.split_once(':') {
+                Some((rest, score)) => {
+                    let score = score.parse().map_err(|_| {
+                        invalid_data(format!("bad score in `{}`", entry))
+                    })?;
+                    (rest, Some(score))
+                },
+                None => (entry, None)
+            };
+            let (atom, time) = match rest.split_once('@') {
+                Some((atom, time)) => {
+                    let time = time.parse().map_err(|_| {
+                        invalid_data(format!("bad time in `{}`", entry))
+                    })?;
+                    (atom, Some(time))
+                },
+                None => (rest, None)
+            };
+            let atom = parse_log_atom(atom)
+                .ok_or_else(|| {
+                    invalid_data(format!("bad atom in `{}`", entry))
+                })?;
+            let time = time.unwrap_or_else(|| {
+                records.last().map_or(0, |r| r.time + 1)
+            });
+            records.push(SpawnRecord { time, score, atom });
+        }
+    }
+    Ok(records)
+}
+
+/// Writes `records` as a spawn log (see `read_spawn_log`), with atoms as
+/// symbols. Times are only written where they do not follow the previous
+/// one.
+pub fn write_spawn_log<W: Write>(records: &[SpawnRecord], mut out: W)
+                                                        -> io::Result<()> {
+    writeln!(out, "# iatomas spawn log")?;
+    let mut previous: Option<u32> = None;
+    for (i, record) in records.iter().enumerate() {
+        let mut entry = atom_symbol(&record.atom);
+        if record.time != previous.map_or(0, |t| t + 1) {
+            entry += &format!("@{}", record.time);
+        }
+        if let Some(score) = record.score {
+            entry += &format!(":{}", score);
+        }
+        previous = Some(record.time);
+        let end = if (i + 1) % ENTRIES_PER_LINE == 0 || i + 1 == records.len()
+                  { "\n" } else { " " };
+        write!(out, "{}{}", entry, end)?;
+    }
+    out.flush()
+}
+
+/// Saves `records` in a spawn log file, see `write_spawn_log`
+pub fn save_spawn_log<P: AsRef<Path>>(records: &[SpawnRecord], path: P)
+                                                        -> io::Result<()> {
+    write_spawn_log(records, io::BufWriter::new(fs::File::create(path)?))
+}
+
+/// The spawn model replaying `records`: the atom coming at each time is the
+/// one recorded at that time. They must cover every time from 0 up to the
+/// last one, once.
+pub fn replay_model(records: &[SpawnRecord]) -> Result<SpawnModel, String> {
+    let mut atoms: Vec<Option<AtomType>> = Vec::new();
+    for record in records {
+        let t = record.time as usize;
+        if t >= atoms.len() {
+            atoms.resize(t + 1, None);
+        }
+        if atoms[t].replace(record.atom.clone()).is_some() {
+            return Err(format!("two atoms at time {}", t));
+        }
+    }
+    let atoms = atoms.into_iter().enumerate().map(|(t, atom)| {
+        atom.ok_or_else(|| format!("no atom at time {}", t))
+    }).collect::<Result<Vec<_>, _>>()?;
+    Ok(SpawnModel::Replay(atoms))
+}
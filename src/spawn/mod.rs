@@ -0,0 +1,25 @@
+//! Analysis of the atoms that come in recorded games, to estimate and check
+//! the spawn models of `game::SpawnModel`, and to infer their parameters
+//! while a game goes on.
+
+mod check;
+mod fit;
+mod log;
+mod posterior;
+
+pub use check::*;
+pub use fit::*;
+pub use log::*;
+pub use posterior::*;
+
+use crate::game::AtomType;
+
+/// An atom that came in a recorded game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnRecord {
+    /// Time at which the atom came, as `GameState::time`
+    pub time: u32,
+    /// Score when the atom came, if known
+    pub score: Option<u32>,
+    pub atom: AtomType,
+}
@@ -0,0 +1,281 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, StandardNormal};
+
+use super::{SpawnRecord, log_likelihood};
+use crate::game::{AtomType, GameState, SpawnParams};
+
+/// Standard deviations of the prior around its centre, in the order of
+/// `SPAWN_PARAM_NAMES`
+const PRIOR_STD: [f64; 9] = [
+    0.005, 0.5, 0.005, 0.5, 0.1, 0.05, 0.02, 0.005, 0.005,
+];
+/// Step of the Metropolis moves of resampled particles, relative to the
+/// spread of the posterior
+const MOVE_STEP: f64 = 0.8;
+/// Parameters moved together by a Metropolis step: those of the regular
+/// atoms, then the chances of the special ones
+const MOVE_BLOCKS: [std::ops::Range<usize>; 2] = [0..5, 5..9];
+
+/// Configuration of a `SpawnPosterior`.
+#[derive(Clone, Debug)]
+pub struct PosteriorConfig {
+    pub particles: usize,
+    /// Centre of the prior over the parameters
+    pub prior: SpawnParams,
+    /// Factor of the standard deviations of the prior
+    pub spread: f64,
+    pub seed: u64,
+}
+
+impl Default for PosteriorConfig {
+    fn default() -> Self {
+        Self {
+            particles: 2000,
+            prior: SpawnParams::default(),
+            spread: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Posterior distribution of the parameters of the `drawing` spawn model
+/// given the atoms that came so far, approximated by weighted particles.
+///
+/// The particles are drawn from a Gaussian prior around `PosteriorConfig::
+/// prior`, and reweighted by the likelihood of every observed atom. When too
+/// few of them keep most of the weight, they are resampled, then moved by a
+/// Metropolis step on the whole posterior so that they do not collapse onto
+/// a few values.
+#[derive(Clone, Debug)]
+pub struct SpawnPosterior {
+    particles: Vec<SpawnParams>,
+    log_weights: Vec<f64>,
+    /// Log-likelihood of the observed atoms under every particle
+    log_likelihoods: Vec<f64>,
+    records: Vec<SpawnRecord>,
+    centre: [f64; 9],
+    std: [f64; 9],
+    rng: ChaCha8Rng,
+    /// Time of the last atom observed by `observe_state`
+    last_time: Option<u32>,
+    observed: usize,
+}
+
+/// Whether `values` are valid parameters: chances at least 0 and summing to
+/// at most 1, and `p` in `[0, 1]`
+fn is_valid(values: &[f64; 9]) -> bool {
+    (0.0..=1.0).contains(&values[4])
+        && values[5..].iter().all(|c| *c >= 0.0)
+        && values[5..].iter().sum::<f64>() <= 1.0
+}
+
+impl SpawnPosterior {
+    /// Draws the particles of the prior.
+    pub fn new(config: &PosteriorConfig) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let centre = config.prior.values();
+        let std = PRIOR_STD.map(|s| s * config.spread);
+        let particles = (0..config.particles.max(1)).map(|_| loop {
+            let mut values = centre;
+            for (v, s) in values.iter_mut().zip(std) {
+                let noise: f64 = StandardNormal.sample(&mut rng);
+                *v += noise * s;
+            }
+            if is_valid(&values) {
+                break SpawnParams::from_values(values);
+            }
+        }).collect::<Vec<_>>();
+        Self {
+            log_weights: vec![0.0; particles.len()],
+            log_likelihoods: vec![0.0; particles.len()],
+            particles,
+            records: Vec::new(),
+            centre,
+            std,
+            rng,
+            last_time: None,
+            observed: 0,
+        }
+    }
+
+    /// Log-density of the prior at `values`, up to a constant
+    fn log_prior(&self, values: &[f64; 9]) -> f64 {
+        if !is_valid(values) {
+            return f64::NEG_INFINITY;
+        }
+        values.iter().zip(self.centre).zip(self.std).map(|((v, c), s)| {
+            -(v - c) * (v - c) / (2.0 * s * s)
+        }).sum()
+    }
+
+    /// Number of atoms observed
+    pub fn observed(&self) -> usize {
+        self.observed
+    }
+
+    /// The particles, with `weights`
+    pub fn particles(&self) -> &[SpawnParams] {
+        &self.particles
+    }
+
+    /// Weights of the particles, summing to 1
+    pub fn weights(&self) -> Vec<f64> {
+        let max = self.log_weights.iter().cloned()
+                                 .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = self.log_weights.iter()
+                                                .map(|w| (w - max).exp())
+                                                .collect();
+        let total: f64 = weights.iter().sum();
+        weights.into_iter().map(|w| w / total).collect()
+    }
+
+    /// Number of particles that would hold the same information with equal
+    /// weights
+    pub fn effective_size(&self) -> f64 {
+        1.0 / self.weights().iter().map(|w| w * w).sum::<f64>()
+    }
+
+    /// Updates the posterior with the atom of `record`. An atom that no
+    /// particle can explain, such as a Dark Plus before it is allowed, is
+    /// left out: the posterior stays as it was.
+    pub fn observe(&mut self, record: &SpawnRecord) {
+        let terms: Vec<f64> = self.particles.iter().map(|params| {
+            log_likelihood(params, std::slice::from_ref(record))
+        }).collect();
+        let max = self.log_weights.iter().zip(&terms).map(|(w, l)| w + l)
+                                  .fold(f64::NEG_INFINITY, f64::max);
+        if !max.is_finite() {
+            return;
+        }
+        for (i, l) in terms.into_iter().enumerate() {
+            self.log_weights[i] += l - max;
+            self.log_likelihoods[i] += l;
+        }
+        self.records.push(record.clone());
+        self.observed += 1;
+        if self.effective_size() < self.particles.len() as f64 / 2.0 {
+            self.resample();
+        }
+    }
+
+    /// Updates the posterior with the incoming atom of `state`, unless it
+    /// was already observed: it comes at each time, and picking an atom
+    /// does not draw a new one.
+    pub fn observe_state(&mut self, state: &GameState) {
+        if self.last_time == Some(state.time)
+            || state.incoming.t == AtomType::None {
+            return;
+        }
+        self.last_time = Some(state.time);
+        self.observe(&SpawnRecord {
+            time: state.time,
+            score: Some(state.score),
+            atom: state.incoming.t.clone(),
+        });
+    }
+
+    /// Draws the particles anew from their weights, systematically, then
+    /// moves each of them by a Metropolis step whose target is the posterior
+    /// given all the observed atoms.
+    fn resample(&mut self) {
+        let weights = self.weights();
+        let n = self.particles.len();
+        let step = self.std_dev().values().map(|s| s * MOVE_STEP);
+
+        let start: f64 = self.rng.gen::<f64>() / n as f64;
+        let (mut cumulated, mut j) = (weights[0], 0);
+        let mut particles = Vec::with_capacity(n);
+        let mut log_likelihoods = Vec::with_capacity(n);
+        for i in 0..n {
+            let u = start + i as f64 / n as f64;
+            while u > cumulated && j + 1 < n {
+                j += 1;
+                cumulated += weights[j];
+            }
+            particles.push(self.particles[j].clone());
+            log_likelihoods.push(self.log_likelihoods[j]);
+        }
+
+        for (params, l) in particles.iter_mut().zip(&mut log_likelihoods) {
+            for block in MOVE_BLOCKS {
+                let values = params.values();
+                let mut proposal = values;
+                for k in block {
+                    let noise: f64 = StandardNormal.sample(&mut self.rng);
+                    proposal[k] += noise * step[k];
+                }
+                let prior = self.log_prior(&proposal);
+                if prior == f64::NEG_INFINITY {
+                    continue;
+                }
+                let proposed = SpawnParams::from_values(proposal);
+                let proposal_l = log_likelihood(&proposed, &self.records);
+                let ratio = proposal_l + prior - *l - self.log_prior(&values);
+                if ratio >= 0.0 || self.rng.gen::<f64>() < ratio.exp() {
+                    *params = proposed;
+                    *l = proposal_l;
+                }
+            }
+        }
+        self.particles = particles;
+        self.log_likelihoods = log_likelihoods;
+        self.log_weights = vec![0.0; n];
+    }
+
+    /// Posterior mean of the parameters
+    pub fn mean(&self) -> SpawnParams {
+        let mut mean = [0.0; 9];
+        for (params, w) in self.particles.iter().zip(self.weights()) {
+            for (m, v) in mean.iter_mut().zip(params.values()) {
+                *m += w * v;
+            }
+        }
+        SpawnParams::from_values(mean)
+    }
+
+    /// Posterior standard deviation of the parameters
+    pub fn std_dev(&self) -> SpawnParams {
+        let mean = self.mean().values();
+        let mut variance = [0.0; 9];
+        for (params, w) in self.particles.iter().zip(self.weights()) {
+            for ((s, v), m) in variance.iter_mut().zip(params.values())
+                                                  .zip(mean) {
+                *s += w * (v - m) * (v - m);
+            }
+        }
+        SpawnParams::from_values(variance.map(f64::sqrt))
+    }
+
+    /// Posterior predictive distribution of the atom coming at time `time`
+    /// when the score is `score`: the special atoms first, then the regular
+    /// ones by increasing index (see `SpawnParams::chances`).
+    pub fn predictive(&self, time: u32, score: u32) -> Vec<(AtomType, f64)> {
+        let mut predictive: Vec<(AtomType, f64)> = Vec::new();
+        for (params, w) in self.particles.iter().zip(self.weights()) {
+            for (atom, chance) in params.chances(time, score) {
+                match predictive.iter_mut().find(|(a, _)| *a == atom) {
+                    Some((_, c)) => *c += w * chance,
+                    None => predictive.push((atom, w * chance)),
+                }
+            }
+        }
+        let order = |atom: &AtomType| match atom {
+            AtomType::Plus => 0,
+            AtomType::Minus => 1,
+            AtomType::DarkPlus => 2,
+            AtomType::Neutrino => 3,
+            AtomType::Atom(z) => 4 + *z as usize,
+            _ => usize::MAX,
+        };
+        predictive.sort_by_key(|(atom, _)| order(atom));
+        predictive
+    }
+
+    /// Posterior predictive distribution of the atom that will come after
+    /// the incoming one of `state`, at the next time, taking the score as
+    /// unchanged.
+    pub fn predict_next(&self, state: &GameState) -> Vec<(AtomType, f64)> {
+        self.predictive(state.time + 1, state.score)
+    }
+}
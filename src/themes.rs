@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use iatomas::game::Theme;
+
+use sfml::graphics::Font;
+use sfml::system::SfBox;
+
+/// Directory of the themes bundled with the game
+const THEMES_DIR: &str = "resources/themes";
+/// Seconds between two looks at the file of the theme in use
+const WATCH_PERIOD: f32 = 1.0;
+
+/// The themes the window can be drawn with: the default one, those bundled
+/// in `THEMES_DIR` and the one given on the command line, if any.
+///
+/// The file of the theme in use is watched, to read it again once it has
+/// changed. The fonts of all the themes are loaded at first and kept here,
+/// so that the board can borrow them while the themes change: a theme
+/// edited meanwhile cannot switch to a font none of them had.
+pub struct Themes {
+    /// Files of the themes, `None` for the default one
+    files: Vec<Option<PathBuf>>,
+    current: Cell<usize>,
+    /// Modification time of the file of the theme in use when it was read
+    modified: Cell<Option<SystemTime>>,
+    /// Seconds since the file was last looked at
+    since_watch: Cell<f32>,
+    /// Fonts of the themes by path, `None` for those that cannot be loaded
+    fonts: HashMap<PathBuf, Option<SfBox<Font>>>,
+}
+
+impl Themes {
+    /// The themes, starting with `spec`: `default`, the name of a bundled
+    /// theme or the path of a theme file. The default one is used first if
+    /// none is given.
+    pub fn new(spec: Option<&str>) -> Result<Self, String> {
+        let mut files = vec![None];
+        if let Ok(entries) = fs::read_dir(THEMES_DIR) {
+            let mut bundled: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+                .collect();
+            bundled.sort();
+            files.extend(bundled.into_iter().map(Some));
+        }
+        let current = match spec {
+            None | Some("default") => 0,
+            Some(spec) => {
+                let path = if Path::new(spec).is_file() {
+                    PathBuf::from(spec)
+                } else {
+                    Path::new(THEMES_DIR).join(format!("{}.toml", spec))
+                };
+                if !path.is_file() {
+                    return Err(format!("no theme `{}`", spec));
+                }
+                match files.iter().position(|f| f.as_ref() == Some(&path)) {
+                    Some(i) => i,
+                    None => {
+                        files.push(Some(path));
+                        files.len() - 1
+                    }
+                }
+            }
+        };
+        let mut fonts = HashMap::new();
+        for file in &files {
+            let font = match file {
+                Some(path) => match Theme::load(path) {
+                    Ok(theme) => theme.font,
+                    Err(_) => continue
+                },
+                None => Theme::default().font
+            };
+            let loaded = font.to_str().and_then(Font::from_file);
+            fonts.entry(font).or_insert(loaded);
+        }
+        Ok(Self {
+            files,
+            current: Cell::new(current),
+            modified: Cell::new(None),
+            since_watch: Cell::new(0.0),
+            fonts,
+        })
+    }
+
+    /// Reads the theme in use, with its font
+    pub fn load(&self) -> Result<(Theme, &Font), String> {
+        let theme = match &self.files[self.current.get()] {
+            Some(path) => {
+                // Noted first, so that a broken file is only reported once
+                self.modified.set(modified(path));
+                Theme::load(path).map_err(|e| {
+                    format!("cannot read the theme in {}: {}", path.display(),
+                            e)
+                })?
+            },
+            None => Theme::default()
+        };
+        let font = self.font(&theme.font)?;
+        Ok((theme, font))
+    }
+
+    /// Switches to the next theme, and reads it
+    pub fn next(&self) -> Result<(Theme, &Font), String> {
+        self.current.set((self.current.get() + 1) % self.files.len());
+        self.load()
+    }
+
+    /// Reads the theme in use again if its file has changed, looking at it
+    /// every `WATCH_PERIOD` seconds, `seconds` being the time elapsed since
+    /// the last call
+    pub fn watch(&self, seconds: f32)
+                                -> Option<Result<(Theme, &Font), String>> {
+        self.since_watch.set(self.since_watch.get() + seconds);
+        if self.since_watch.get() < WATCH_PERIOD {
+            return None;
+        }
+        self.since_watch.set(0.0);
+        let path = self.files[self.current.get()].as_ref()?;
+        if modified(path) == self.modified.get() {
+            return None;
+        }
+        Some(self.load())
+    }
+
+    /// The font in the file at `path`, as loaded at first
+    fn font(&self, path: &Path) -> Result<&Font, String> {
+        match self.fonts.get(path) {
+            Some(Some(font)) => Ok(font),
+            Some(None) => {
+                Err(format!("cannot load the font {}", path.display()))
+            },
+            None => Err(format!("the font {} was not used by any theme when \
+                                 the game started", path.display()))
+        }
+    }
+}
+
+/// When the file at `path` was last modified, if it can be told
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
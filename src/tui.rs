@@ -0,0 +1,579 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use iatomas::agent::{self, Hint, Hinter};
+use iatomas::autoplay::{Autoplay, Control, Turn};
+use iatomas::game::{ATOMS_NAMES, AtomType, GameState, Layout, Move, Rgb,
+                    SpawnModel, Theme, atom_symbol, move_notation};
+use iatomas::history::{self, GameSummary, History};
+
+use crate::cli::Options;
+
+/// Size of the picture drawn, in columns and rows
+const COLUMNS: usize = 64;
+const ROWS: usize = 30;
+/// Centre of the ring, as a column and a row
+const CENTRE: (f32, f32) = (32.0, 12.0);
+/// Radii of the ring in columns and in rows, cells being about twice as high
+/// as wide
+const RADII: (f32, f32) = (20.0, 9.0);
+/// First row under the ring
+const PANEL_ROW: usize = 23;
+
+/// Time between two frames, at most
+const FRAME: Duration = Duration::from_millis(50);
+/// Tenths of a second a read from the terminal waits for a key, so that a
+/// lone escape is not taken for the start of a sequence
+const READ_TIMEOUT: &str = "1";
+
+const WHITE: Rgb = (255, 255, 255);
+/// Colour of the cursor
+const CURSOR_COLOR: Rgb = (0, 255, 255);
+
+/// How colours are written to the terminal: as RGB triplets if it says it
+/// supports them (through `COLORTERM`), else among the 256 usual ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Palette {
+    TrueColor,
+    Indexed,
+}
+
+impl Palette {
+    fn detect() -> Self {
+        match env::var("COLORTERM") {
+            Ok(v) if v == "truecolor" || v == "24bit" => Palette::TrueColor,
+            _ => Palette::Indexed
+        }
+    }
+
+    /// Escape sequence setting the foreground (`layer` 38) or the
+    /// background (`layer` 48) to `(r, g, b)`
+    fn color(self, layer: u8, (r, g, b): Rgb) -> String {
+        match self {
+            Palette::TrueColor => {
+                format!("\x1b[{};2;{};{};{}m", layer, r, g, b)
+            },
+            Palette::Indexed => {
+                // Nearest colour of the 6x6x6 cube of the 256 colours
+                let level = |c: u8| (c as u16 * 5 + 127) / 255;
+                let index = 16 + 36 * level(r) + 6 * level(g) + level(b);
+                format!("\x1b[{};5;{}m", layer, index)
+            }
+        }
+    }
+}
+
+/// How a cell is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Style {
+    fg: Option<Rgb>,
+    bg: Option<Rgb>,
+    bold: bool,
+}
+
+impl Style {
+    fn fg(color: Rgb) -> Self {
+        Self { fg: Some(color), bold: true, ..Self::default() }
+    }
+
+    /// Escape sequences switching to this style from any other one
+    fn escape(&self, palette: Palette) -> String {
+        let mut text = "\x1b[0m".to_string();
+        if self.bold {
+            text.push_str("\x1b[1m");
+        }
+        if let Some(fg) = self.fg {
+            text.push_str(&palette.color(38, fg));
+        }
+        if let Some(bg) = self.bg {
+            text.push_str(&palette.color(48, bg));
+        }
+        text
+    }
+}
+
+/// Colour of `atom` in `theme`, and that of its symbol: that of the theme,
+/// unless it would not show on the atom
+fn atom_colors(theme: &Theme, atom: &AtomType) -> (Rgb, Rgb) {
+    let color = match atom {
+        AtomType::None => theme.background,
+        atom => theme.atom(atom).color,
+    };
+    let light = |(r, g, b): Rgb| {
+        299 * r as u32 + 587 * g as u32 + 114 * b as u32 >= 128_000
+    };
+    let symbol = if light(color) != light(theme.symbol) { theme.symbol }
+                 else if light(color) { theme.background }
+                 else { theme.text };
+    (color, symbol)
+}
+
+/// The picture drawn, cell by cell.
+struct Canvas {
+    cells: Vec<Vec<(char, Style)>>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self { cells: vec![vec![(' ', Style::default()); COLUMNS]; ROWS] }
+    }
+
+    /// Writes `text` from `(row, column)`, cut at the edges
+    fn put(&mut self, row: usize, column: usize, text: &str, style: Style) {
+        if let Some(cells) = self.cells.get_mut(row) {
+            for (cell, c) in cells.iter_mut().skip(column).zip(text.chars()) {
+                *cell = (c, style);
+            }
+        }
+    }
+
+    /// Writes `text` centred on the point `(column, row)`
+    fn put_centred(&mut self, (column, row): (f32, f32), text: &str,
+                   style: Style) {
+        let left = column - text.chars().count() as f32 / 2.0;
+        self.put(row.round().max(0.0) as usize, left.round().max(0.0) as usize,
+                 text, style);
+    }
+
+    /// Escape sequences drawing the canvas over the whole terminal
+    fn render(&self, palette: Palette) -> String {
+        let mut text = "\x1b[H".to_string();
+        for row in &self.cells {
+            let mut current = None;
+            for (c, style) in row {
+                if current != Some(*style) {
+                    text.push_str(&style.escape(palette));
+                    current = Some(*style);
+                }
+                text.push(*c);
+            }
+            text.push_str("\x1b[0m\x1b[K\r\n");
+        }
+        text.push_str("\x1b[J");
+        text
+    }
+}
+
+/// Point at `angle` degrees clockwise from the top, `distance` times the
+/// radius of the ring away from its centre
+fn ring_point(angle: f32, distance: f32) -> (f32, f32) {
+    let theta = angle.to_radians();
+    (CENTRE.0 + RADII.0 * distance * theta.sin(),
+     CENTRE.1 - RADII.1 * distance * theta.cos())
+}
+
+/// Keys read from the terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Char(char),
+}
+
+/// The next byte read from the terminal, `None` if none came within the
+/// `READ_TIMEOUT` it is set to
+fn next(stdin: &mut io::StdinLock) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    Ok(if stdin.read(&mut byte)? == 1 { Some(byte[0]) } else { None })
+}
+
+/// Reads the keys pressed on a background thread
+fn read_keys() -> Receiver<Key> {
+    let (keys, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            let byte = match next(&mut stdin) {
+                Ok(Some(byte)) => byte,
+                Ok(None) => continue,
+                Err(_) => break
+            };
+            let key = match byte {
+                // A lone escape is ignored, without waiting for what follows
+                0x1b => match next(&mut stdin) {
+                    Ok(Some(b'[')) => match next(&mut stdin) {
+                        Ok(Some(b'A')) => Key::Up,
+                        Ok(Some(b'B')) => Key::Down,
+                        Ok(Some(b'C')) => Key::Right,
+                        Ok(Some(b'D')) => Key::Left,
+                        _ => continue
+                    },
+                    _ => continue
+                },
+                b'\r' | b'\n' => Key::Enter,
+                byte => Key::Char(byte as char),
+            };
+            if keys.send(key).is_err() {
+                break;
+            }
+        }
+    });
+    received
+}
+
+/// Runs `stty` on the terminal, returning what it prints
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("stty").args(args)
+                                     .stdin(Stdio::inherit())
+                                     .output()
+                                     .map_err(|e| format!("stty: {}", e))?;
+    if !output.status.success() {
+        return Err("the standard input is not a terminal".to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The terminal, set up to read keys as they are pressed and to draw on an
+/// alternate screen until dropped.
+struct Terminal {
+    /// Settings to restore, as given by `stty -g`
+    saved: String,
+}
+
+impl Terminal {
+    fn enter() -> Result<Self, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time",
+               READ_TIMEOUT])?;
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// The games played in the terminal, and what is shown of them.
+struct Tui {
+    state: GameState,
+    spawn: SpawnModel,
+    seed: u64,
+    /// Number of the game being played
+    game: u64,
+    /// Number of changes of the state so far
+    version: u64,
+    /// Index of the move the cursor is on, among the legal ones
+    cursor: usize,
+    hinter: Hinter,
+    /// Version of the game the hint asked for is about, if any
+    hint_version: Option<u64>,
+    hint: Option<Hint>,
+    autoplay: Option<Autoplay>,
+    theme: Theme,
+    palette: Palette,
+    /// Where the games finished are recorded, if it could be read
+    history: Option<History>,
+    /// How the games are played, as recorded
+    mode: String,
+    /// Whether the game over has been recorded
+    recorded: bool,
+    /// Outcome of recording the last game
+    message: String,
+}
+
+impl Tui {
+    /// The state the next game starts from
+    fn next_start(&self) -> GameState {
+        let seed = agent::game_seed(self.seed, self.game + 1);
+        GameState::start_game_seeded(seed, self.spawn.clone())
+    }
+
+    /// Notes that the state has changed, which drops the hint
+    fn changed(&mut self) {
+        self.version += 1;
+        self.hint = None;
+        self.hint_version = None;
+        self.hinter.cancel();
+        let moves = self.state.legal_moves().len();
+        self.cursor = self.cursor.min(moves.saturating_sub(1));
+    }
+
+    /// Starts the next game, from `state`
+    fn restart(&mut self, state: GameState) {
+        self.game += 1;
+        self.recorded = false;
+        self.message.clear();
+        self.state = state;
+        self.changed();
+    }
+
+    /// Plays `mv` if it is legal
+    fn play(&mut self, mv: Move) {
+        if self.state.legal_moves().contains(&mv) {
+            self.state.apply(mv);
+            self.changed();
+        }
+    }
+
+    /// Reacts to `key`, returning `false` to quit
+    fn key(&mut self, key: Key) -> bool {
+        if key == Key::Char('q') || key == Key::Char('\u{3}') {
+            return false;
+        }
+        if let Some(autoplay) = &mut self.autoplay {
+            let control = match key {
+                Key::Char(' ') => Control::Pause,
+                Key::Char('s') | Key::Right => Control::Step,
+                Key::Char('+') | Key::Up => Control::Faster,
+                Key::Char('-') | Key::Down => Control::Slower,
+                _ => return true
+            };
+            autoplay.control(control);
+            return true;
+        }
+
+        let moves = self.state.legal_moves();
+        match key {
+            Key::Left | Key::Char('a') if !moves.is_empty() => {
+                self.cursor = (self.cursor + moves.len() - 1) % moves.len();
+            },
+            Key::Right | Key::Char('d') if !moves.is_empty() => {
+                self.cursor = (self.cursor + 1) % moves.len();
+            },
+            Key::Enter | Key::Char(' ') => {
+                if let Some(mv) = moves.get(self.cursor) {
+                    self.play(*mv);
+                }
+            },
+            Key::Char('c') => self.play(Move::Convert),
+            Key::Char('h') if !moves.is_empty() => {
+                self.hinter.request(&self.state);
+                self.hint_version = Some(self.version);
+            },
+            Key::Char('r') => self.restart(self.next_start()),
+            _ => {}
+        }
+        true
+    }
+
+    /// Moves the games `seconds` forward: picks up the hint once found, and
+    /// lets the autoplay play
+    fn update(&mut self, seconds: f32) {
+        if self.state.is_over() && !self.recorded {
+            self.record();
+        }
+        if self.hint_version == Some(self.version) {
+            if let Some(hint) = self.hinter.poll() {
+                self.hint = Some(hint);
+                self.hint_version = None;
+            }
+        }
+        let turn = match &mut self.autoplay {
+            Some(autoplay) => {
+                autoplay.update(&self.state, self.version, seconds)
+            },
+            None => return
+        };
+        match turn {
+            Some(Turn::Play(mv)) => self.play(mv),
+            Some(Turn::Restart(state)) => self.restart(*state),
+            None => {}
+        }
+    }
+
+    /// Records the game over among the games finished
+    fn record(&mut self) {
+        self.recorded = true;
+        let history = match &mut self.history {
+            Some(history) => history,
+            None => return
+        };
+        let seed = match &self.autoplay {
+            Some(autoplay) => autoplay.seed(),
+            None => agent::game_seed(self.seed, self.game),
+        };
+        let game = GameSummary::new(&self.state, &self.mode, Some(seed));
+        self.message = match history.record(game) {
+            Ok(Some(rank)) => format!("high score #{}", rank + 1),
+            Ok(None) => String::new(),
+            Err(e) => format!("cannot save the scores: {}", e),
+        };
+    }
+
+    /// Where `mv` is played: the gap the atom enters, the atom picked or
+    /// the centre
+    fn move_point(&self, mv: Move) -> (f32, f32) {
+        let n = self.state.atoms.len();
+        match mv {
+            Move::Place(i) => ring_point(Layout::gap_angle(i, n), 1.0),
+            Move::Pick(i) => ring_point(Layout::slot_angle(i, n), 1.0),
+            Move::Convert => ring_point(0.0, 0.0),
+        }
+    }
+
+    /// Draws the ring, the scores and what the keys do
+    fn draw(&self) -> String {
+        let mut canvas = Canvas::new();
+        let state = &self.state;
+        let n = state.atoms.len();
+        let header = format!("score {}   best {}   time {}", state.score,
+                             ATOMS_NAMES.get(state.best as usize)
+                                        .copied().unwrap_or("?"),
+                             state.time);
+        canvas.put(0, 1, &header, Style::fg(self.theme.title));
+
+        let cursor = match &self.autoplay {
+            Some(_) => None,
+            None => state.legal_moves().get(self.cursor).copied()
+        };
+        let best = self.hint.as_ref()
+                            .and_then(|h| h.moves.first().map(|(mv, _)| *mv));
+        for mv in best.iter().chain(cursor.iter()) {
+            let color = if Some(*mv) == cursor { CURSOR_COLOR }
+                        else { self.theme.hint };
+            if let Move::Place(_) = mv {
+                canvas.put_centred(self.move_point(*mv), "*",
+                                   Style::fg(color));
+            }
+        }
+
+        for (i, atom) in state.atoms.iter().enumerate() {
+            let angle = Layout::slot_angle(i, n);
+            let (bg, fg) = atom_colors(&self.theme, &atom.t);
+            let mut style = Style { fg: Some(fg), bg: Some(bg), bold: true };
+            if cursor == Some(Move::Pick(i)) {
+                style.bg = Some(CURSOR_COLOR);
+            } else if best == Some(Move::Pick(i)) {
+                style.bg = Some(self.theme.hint);
+            }
+            let text = format!(" {} ", atom_symbol(&atom.t));
+            canvas.put_centred(ring_point(angle, 1.0), &text, style);
+        }
+        if state.incoming.t != AtomType::None {
+            let (bg, fg) = atom_colors(&self.theme, &state.incoming.t);
+            let mut style = Style { fg: Some(fg), bg: Some(bg), bold: true };
+            if cursor == Some(Move::Convert) {
+                style.bg = Some(CURSOR_COLOR);
+            }
+            let text = format!(" {} ", atom_symbol(&state.incoming.t));
+            canvas.put_centred(ring_point(0.0, 0.0), &text, style);
+        }
+
+        let mut row = PANEL_ROW;
+        let mut line = |canvas: &mut Canvas, text: &str, style: Style| {
+            canvas.put(row, 1, text, style);
+            row += 1;
+        };
+        if let Some(mv) = cursor {
+            let run = state.dry_run(mv);
+            let text = format!("{}  +{}", move_notation(mv), run.score_delta);
+            line(&mut canvas, &text, Style::fg(CURSOR_COLOR));
+        }
+        if let Some(hint) = &self.hint {
+            for (rank, (mv, value)) in hint.moves.iter().enumerate() {
+                let text = format!("{}. {}  {:.1}", rank + 1,
+                                   move_notation(*mv), value);
+                line(&mut canvas, &text, Style::fg(self.theme.hint));
+            }
+        }
+        if state.is_over() {
+            line(&mut canvas, "game over", Style::fg(WHITE));
+        }
+        if !self.message.is_empty() {
+            line(&mut canvas, &self.message, Style::fg(self.theme.hint));
+        }
+        match &self.autoplay {
+            Some(autoplay) => {
+                for text in autoplay.status() {
+                    line(&mut canvas, &text, Style::fg(WHITE));
+                }
+                line(&mut canvas, "space pause  s step  +/- speed  q quit",
+                     Style::default());
+            },
+            None => {
+                line(&mut canvas, "left/right move  enter play  c convert",
+                     Style::default());
+                line(&mut canvas, "h hint  r new game  q quit",
+                     Style::default());
+            }
+        }
+        canvas.render(self.palette)
+    }
+}
+
+/// `tui`: plays in the terminal, without any window, e.g. over SSH.
+///
+/// Options: `--hint <agent>` (the agent whose recommendation H shows,
+/// `heuristic` by default), `--autoplay <agent>` to watch an agent play
+/// games one after the other, `--seed <n>` (random by default, 0 with
+/// `--autoplay`) and `--spawn <model>`.
+///
+/// The games finished are recorded with the high scores, see
+/// `history::History`.
+pub fn tui(options: &Options) -> Result<(), String> {
+    let hint_agent = agent::from_spec(options.get("hint")
+                                             .unwrap_or("heuristic"), 0)?;
+    let spec = options.get("autoplay");
+    let default_seed = if spec.is_some() { 0 } else { rand::random() };
+    let seed = options.parse_or("seed", default_seed)?;
+    let autoplay = match spec {
+        Some(spec) => Some(Autoplay::new(agent::from_spec(spec, seed)?,
+                                         options.spawn()?, seed)),
+        None => None
+    };
+    let spawn = options.spawn()?;
+    let dir = history::data_dir();
+    let (history, message) = match History::open(&dir) {
+        Ok(history) => (Some(history), String::new()),
+        Err(e) => (None, format!("scores not recorded, cannot read {}: {}",
+                                 dir.display(), e)),
+    };
+    let mut tui = Tui {
+        state: GameState::start_game_seeded(agent::game_seed(seed, 0),
+                                            spawn.clone()),
+        spawn,
+        seed,
+        game: 0,
+        version: 0,
+        cursor: 0,
+        hinter: Hinter::new(hint_agent),
+        hint_version: None,
+        hint: None,
+        autoplay,
+        theme: Theme::default(),
+        palette: Palette::detect(),
+        history,
+        mode: match spec {
+            Some(spec) => format!("autoplay {}", spec),
+            None => "tui".to_string()
+        },
+        recorded: false,
+        message,
+    };
+
+    let _terminal = Terminal::enter()?;
+    let keys = read_keys();
+    let mut out = io::stdout();
+    let mut last = Instant::now();
+    loop {
+        out.write_all(tui.draw().as_bytes())
+           .and_then(|()| out.flush())
+           .map_err(|e| e.to_string())?;
+        match keys.recv_timeout(FRAME) {
+            Ok(key) => {
+                if !tui.key(key) {
+                    break;
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let now = Instant::now();
+        tui.update((now - last).as_secs_f32());
+        last = now;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Animations of moves last as long as their steps, end on the ring the move
+//! leads to, and count the score up.
+
+use iatomas::agent::{Agent, HeuristicAgent};
+use iatomas::game::{APPEAR_DURATION, Animation, AtomType, FUSION_DURATION,
+                    GameState, Move, SLIDE_DURATION, SpawnModel, Sprite,
+                    parse_position};
+
+/// Time step the animations are sampled at
+const TICK: f32 = 0.01;
+
+/// Seconds the animation of `mv` should take, leading to `after` through
+/// `fusions` fusions
+fn duration(mv: Move, fusions: usize, after: &GameState) -> f32 {
+    let fusions = fusions as f32 * FUSION_DURATION;
+    match mv {
+        Move::Place(_) if after.incoming.t != AtomType::None => {
+            SLIDE_DURATION + fusions + APPEAR_DURATION
+        },
+        Move::Place(_) | Move::Pick(_) => SLIDE_DURATION + fusions,
+        Move::Convert => 0.0,
+    }
+}
+
+/// Whether `sprite` is drawn as `atom` at `angle` on the ring
+fn is_at(sprite: &Sprite, atom: &AtomType, angle: f32) -> bool {
+    let turn = (sprite.angle - angle + 540.0).rem_euclid(360.0) - 180.0;
+    sprite.atom == *atom && turn.abs() < 0.5
+        && (sprite.distance - 1.0).abs() < 0.01
+        && (sprite.scale - 1.0).abs() < 0.01
+}
+
+/// Whether `sprite` is drawn as `atom` in the centre
+fn is_centre(sprite: &Sprite, atom: &AtomType) -> bool {
+    sprite.atom == *atom && sprite.distance.abs() < 0.01
+        && (sprite.scale - 1.0).abs() < 0.01
+}
+
+/// Plays `mv` in `before`, checking its animation, and returns the number
+/// of fusions it caused
+fn check_move(before: &GameState, mv: Move) -> usize {
+    let mut after = before.clone();
+    let (_, fusions) = after.apply_traced(mv);
+    let mut animation = Animation::new(before, mv, &fusions, &after);
+    let total = duration(mv, fusions.len(), &after);
+
+    // The score counts up from the one before to the one after
+    let mut score = animation.score();
+    assert_eq!(score, before.score);
+    let mut elapsed = 0.0;
+    while elapsed + TICK < total - 1e-3 {
+        animation.advance(TICK);
+        elapsed += TICK;
+        assert!(!animation.is_done(), "{:?} over after {}s of {}s", mv,
+                elapsed, total);
+        assert!(animation.score() >= score);
+        score = animation.score();
+    }
+    assert!(score <= after.score);
+
+    // Right before its end, the atoms are where they go
+    animation.advance(total - 1e-4 - elapsed);
+    if total > 0.0 {
+        assert!(!animation.is_done());
+        let sprites: Vec<Sprite> = animation.sprites().into_iter()
+                                            .filter(|s| s.scale > 0.5)
+                                            .collect();
+        let n = after.atoms.len();
+        let on_ring = sprites.iter().filter(|s| s.distance > 0.5).count();
+        assert_eq!(on_ring, n, "{:?} ends with {:?}", mv, sprites);
+        for (i, atom) in after.atoms.iter().enumerate() {
+            let angle = iatomas::game::Layout::slot_angle(i, n);
+            assert!(sprites.iter().any(|s| is_at(s, &atom.t, angle)),
+                    "{:?}: no {:?} at {} in {:?}", mv, atom.t, angle,
+                    sprites);
+        }
+        if after.incoming.t != AtomType::None {
+            assert!(sprites.iter().any(|s| is_centre(s, &after.incoming.t)),
+                    "{:?}: no {:?} in the centre", mv, after.incoming.t);
+        }
+    }
+    animation.advance(2e-4);
+    assert!(animation.is_done());
+    assert!(animation.sprites().is_empty());
+    assert_eq!(animation.score(), after.score);
+    fusions.len()
+}
+
+/// A Plus between two Hydrogens fuses them, then the Heliums and the
+/// Lithiums around
+#[test]
+fn fusion_chain() {
+    let before = parse_position("B,Li,He,H,H,He,Li + 10 5", 0,
+                                SpawnModel::default()).unwrap();
+    assert!(check_move(&before, Move::Place(4)) >= 3);
+}
+
+#[test]
+fn minus_and_neutrino() {
+    let positions = ["H,He,Li,He - 0 5", "H,He,Li,He Nu 800 5",
+                     "Li,He,+,He,H - 0 5"];
+    for pos in positions.iter() {
+        let before = parse_position(pos, 0, SpawnModel::default()).unwrap();
+        check_move(&before, Move::Pick(1));
+        check_move(&before, Move::Pick(2));
+    }
+    let before = parse_position("H,He H 0 5 m", 0, SpawnModel::default())
+                     .unwrap();
+    check_move(&before, Move::Convert);
+}
+
+/// Every move of whole games is animated as it should
+#[test]
+fn moves_of_games() {
+    let mut fusions = 0;
+    for seed in 0..3 {
+        let mut agent = HeuristicAgent::default();
+        let mut state = GameState::start_game_seeded(seed,
+                                                     SpawnModel::default());
+        let mut moves = 0;
+        while !state.is_over() && moves < 300 {
+            let mv = agent.choose(&state);
+            fusions += check_move(&state, mv);
+            state.apply(mv);
+            moves += 1;
+        }
+    }
+    assert!(fusions > 20);
+}
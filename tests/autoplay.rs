@@ -0,0 +1,167 @@
+//! The autoplay plays at its speed, pauses, steps and restarts the games
+//! once they are over.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use iatomas::agent::{Agent, HeuristicAgent};
+use iatomas::autoplay::{Autoplay, Control, MAX_SPEED, MIN_SPEED, MOVE_DELAY,
+                        RESTART_DELAY, Turn};
+use iatomas::game::{GameState, MAX_ATOMS, Move, SpawnModel, parse_position,
+                    position};
+
+/// Time given to the agent to find a move
+const TURN: Duration = Duration::from_secs(5);
+/// Time waited for a move that should not come
+const NO_TURN: Duration = Duration::from_millis(300);
+
+/// Finds no move, whatever the game
+struct StuckAgent;
+
+impl Agent for StuckAgent {
+    fn rank_moves(&mut self, _state: &GameState) -> Vec<(Move, f64)> {
+        Vec::new()
+    }
+}
+
+fn autoplay() -> Autoplay {
+    Autoplay::new(Box::new(HeuristicAgent::default()), SpawnModel::default(),
+                  7)
+}
+
+/// What the autoplay asks once the agent has thought about `state` for at
+/// most `timeout`, no time passing for the game
+fn wait_turn(autoplay: &mut Autoplay, state: &GameState, version: u64,
+             timeout: Duration) -> Option<Turn> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(turn) = autoplay.update(state, version, 0.0) {
+            return Some(turn);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+/// A game that is over, its ring full
+fn game_over() -> GameState {
+    let ring = vec!["H"; MAX_ATOMS + 1].join(",");
+    let state = parse_position(&format!("{} He 321 40", ring), 0,
+                               SpawnModel::default()).unwrap();
+    assert!(state.is_over());
+    state
+}
+
+#[test]
+fn moves_after_delay() {
+    let mut autoplay = autoplay();
+    let state = autoplay.start();
+    assert_eq!(position(&state), position(&autoplay.start()));
+
+    // Nothing happens before the delay between two moves
+    assert!(autoplay.update(&state, 0, MOVE_DELAY / 2.0).is_none());
+    assert!(wait_turn(&mut autoplay, &state, 0, NO_TURN).is_none());
+    assert!(autoplay.update(&state, 0, MOVE_DELAY / 2.0).is_none());
+    match wait_turn(&mut autoplay, &state, 0, TURN) {
+        Some(Turn::Play(mv)) => assert!(state.legal_moves().contains(&mv)),
+        turn => panic!("expected a move, got {:?}", turn),
+    }
+
+    // The search for a game that has changed since is dropped, and the
+    // game shown is thought about instead
+    assert!(autoplay.update(&state, 0, MOVE_DELAY).is_none());
+    assert!(autoplay.update(&state, 1, 0.0).is_none());
+    assert!(matches!(wait_turn(&mut autoplay, &state, 1, TURN),
+                     Some(Turn::Play(_))));
+}
+
+#[test]
+fn pause_and_step() {
+    let mut autoplay = autoplay();
+    let state = autoplay.start();
+    autoplay.control(Control::Pause);
+    assert!(autoplay.status()[0].ends_with("paused"));
+    assert!(autoplay.update(&state, 0, 10.0 * MOVE_DELAY).is_none());
+    assert!(wait_turn(&mut autoplay, &state, 0, NO_TURN).is_none());
+
+    // A step plays a single move, at once
+    autoplay.control(Control::Step);
+    assert!(matches!(wait_turn(&mut autoplay, &state, 0, TURN),
+                     Some(Turn::Play(_))));
+    let mut next = state.clone();
+    next.apply(next.legal_moves()[0]);
+    assert!(wait_turn(&mut autoplay, &next, 1, NO_TURN).is_none());
+
+    // Steps are only taken while paused
+    autoplay.control(Control::Pause);
+    assert!(!autoplay.status()[0].ends_with("paused"));
+    autoplay.control(Control::Step);
+    autoplay.control(Control::Pause);
+    assert!(autoplay.update(&next, 1, 10.0 * MOVE_DELAY).is_none());
+    assert!(wait_turn(&mut autoplay, &next, 1, NO_TURN).is_none());
+}
+
+#[test]
+fn speed_is_bounded() {
+    let mut autoplay = autoplay();
+    assert_eq!(autoplay.speed(), 1.0);
+    autoplay.control(Control::Faster);
+    assert_eq!(autoplay.speed(), 2.0);
+    assert!(autoplay.status()[0].contains("x2"));
+    for _ in 0..10 {
+        autoplay.control(Control::Faster);
+    }
+    assert_eq!(autoplay.speed(), MAX_SPEED);
+    for _ in 0..20 {
+        autoplay.control(Control::Slower);
+    }
+    assert_eq!(autoplay.speed(), MIN_SPEED);
+
+    // Time passes faster for the games
+    for _ in 0..3 {
+        autoplay.control(Control::Faster);
+    }
+    assert_eq!(autoplay.speed(), 2.0);
+    let state = autoplay.start();
+    assert!(autoplay.update(&state, 0, MOVE_DELAY / 2.0).is_none());
+    assert!(matches!(wait_turn(&mut autoplay, &state, 0, TURN),
+                     Some(Turn::Play(_))));
+}
+
+#[test]
+fn restarts_after_game_over() {
+    let mut autoplay = autoplay();
+    let first = autoplay.seed();
+    let over = game_over();
+    assert!(autoplay.update(&over, 3, 0.0).is_none());
+    assert!(autoplay.status()[1].starts_with("1 games: mean 321"));
+
+    // Not while paused, nor before the delay
+    autoplay.control(Control::Pause);
+    assert!(autoplay.update(&over, 3, RESTART_DELAY).is_none());
+    autoplay.control(Control::Pause);
+    autoplay.control(Control::Faster);
+    match autoplay.update(&over, 3, 0.0) {
+        Some(Turn::Restart(state)) => {
+            assert_ne!(autoplay.seed(), first);
+            assert_eq!(position(&state), position(&autoplay.start()));
+        },
+        turn => panic!("expected a restart, got {:?}", turn),
+    }
+    assert!(autoplay.status()[0].starts_with("game 2 "));
+    assert_eq!(autoplay.status()[1], "1 games: mean 321, best 321");
+}
+
+/// A game in which the agent finds no move ends, and the next one starts
+#[test]
+fn restarts_when_stuck() {
+    let mut autoplay = Autoplay::new(Box::new(StuckAgent),
+                                     SpawnModel::default(), 7);
+    let state = autoplay.start();
+    assert!(autoplay.update(&state, 0, MOVE_DELAY).is_none());
+    assert!(wait_turn(&mut autoplay, &state, 0, NO_TURN).is_none());
+    assert_eq!(autoplay.status().len(), 2);
+    assert!(matches!(autoplay.update(&state, 0, RESTART_DELAY),
+                     Some(Turn::Restart(_))));
+    assert!(autoplay.status()[0].starts_with("game 2 "));
+}
@@ -0,0 +1,72 @@
+//! A game saved by `SavedGame` comes back as it was, and damaged or
+//! inconsistent saves are refused.
+
+use iatomas::agent::game_seed;
+use iatomas::autosave::{SAVE_VERSION, SavedGame};
+use iatomas::game::{GameState, Move, SpawnModel, position};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const GAMES: u64 = 10;
+const MOVES: usize = 200;
+
+/// A game of random moves, saved after `moves` of them
+fn saved_game(seed: u64, moves: usize) -> SavedGame {
+    let mut chooser = ChaCha8Rng::seed_from_u64(seed);
+    let mut state = GameState::start_game_seeded(seed, SpawnModel::default());
+    let mut played = Vec::new();
+    for _ in 0..moves {
+        let legal = state.legal_moves();
+        if legal.is_empty() {
+            break;
+        }
+        let mv = legal[chooser.gen_range(0..legal.len())];
+        state.apply(mv);
+        played.push(mv);
+    }
+    SavedGame {
+        state,
+        spawn: "simple".to_string(),
+        seed,
+        edited: false,
+        moves: played,
+    }
+}
+
+/// The position, and the atoms coming next, are those saved
+#[test]
+fn saved_game_resumes() {
+    for game in 0..GAMES {
+        let saved = saved_game(game_seed(1, game), MOVES);
+        let mut loaded = SavedGame::parse(&saved.to_text()).unwrap();
+        let mut state = saved.state.clone();
+        assert_eq!(position(&loaded.state), position(&state));
+        assert_eq!(loaded.state.best, state.best);
+        assert_eq!(loaded.moves, saved.moves);
+        assert_eq!(loaded.state.rng.gen::<u64>(), state.rng.gen::<u64>());
+    }
+}
+
+#[test]
+fn damaged_save_is_refused() {
+    let text = saved_game(5, 50).to_text();
+    let damaged = text.replacen("position ", "position H,", 1);
+    assert!(SavedGame::parse(&damaged).is_err());
+    assert!(SavedGame::parse(&text[..text.len() / 2]).is_err());
+    let newer = text.replacen(&format!("iatomas-save {}", SAVE_VERSION),
+                              &format!("iatomas-save {}", SAVE_VERSION + 1),
+                              1);
+    assert!(SavedGame::parse(&newer).is_err());
+}
+
+/// Moves that do not lead to the position are refused, unless it has been
+/// edited
+#[test]
+fn moves_must_match_position() {
+    let mut saved = saved_game(9, 50);
+    saved.moves.push(Move::Place(0));
+    assert!(SavedGame::parse(&saved.to_text()).is_err());
+    saved.edited = true;
+    assert!(SavedGame::parse(&saved.to_text()).is_ok());
+}
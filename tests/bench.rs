@@ -0,0 +1,177 @@
+//! The statistics of benchmarks on runs whose answers are known, and the
+//! files they are exported to.
+
+mod common;
+
+use std::time::Duration;
+
+use iatomas::bench::{AgentRun, BenchConfig, BenchGame, paired_difference,
+                     percentile, save_charts, summarise, write_csv};
+use common::scratch_dir;
+
+/// A run whose `i`-th game, of seed `seeds[i]`, scores `scores[i]`
+fn run(scores: &[u32], seeds: &[u64]) -> AgentRun {
+    let games = scores.iter().zip(seeds).enumerate()
+        .map(|(i, (&score, &seed))| BenchGame {
+            index: i as u64,
+            seed,
+            score,
+            best: (score % 5) as u8,
+            moves: 10 * score + 1,
+            time: Duration::from_millis(1),
+        }).collect();
+    AgentRun {
+        agent: "test".to_string(),
+        games,
+        elapsed: Duration::from_secs(2),
+    }
+}
+
+fn config(confidence: f64) -> BenchConfig {
+    BenchConfig { confidence, ..BenchConfig::default() }
+}
+
+#[test]
+fn percentile_takes_nearest_value() {
+    let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+    assert_eq!(percentile(&values, 0.0), 1.0);
+    assert_eq!(percentile(&values, 25.0), 2.0);
+    assert_eq!(percentile(&values, 50.0), 3.0);
+    assert_eq!(percentile(&values, 60.0), 3.0);
+    assert_eq!(percentile(&values, 65.0), 4.0);
+    assert_eq!(percentile(&values, 100.0), 5.0);
+    assert_eq!(percentile(&[7.0], 90.0), 7.0);
+}
+
+#[test]
+fn summary_of_known_run() {
+    let scores: Vec<u32> = (0..=10).rev().collect();
+    let seeds: Vec<u64> = (0..11).collect();
+    let s = summarise(&run(&scores, &seeds));
+    assert_eq!(s.games, 11);
+    assert_eq!((s.mean, s.median, s.p10, s.p90, s.max),
+               (5.0, 5.0, 1.0, 9.0, 10.0));
+    assert_eq!((s.mean_moves, s.median_moves, s.p10_moves, s.p90_moves,
+                s.max_moves), (51.0, 51.0, 11.0, 91.0, 101.0));
+    assert_eq!(s.games_per_second, 5.5);
+    assert_eq!(s.best_atoms, vec![(0, 3), (1, 2), (2, 2), (3, 2), (4, 2)]);
+
+    let empty = summarise(&run(&[], &[]));
+    assert_eq!((empty.games, empty.median, empty.max_moves), (0, 0.0, 0.0));
+}
+
+#[test]
+fn constant_difference_has_no_spread() {
+    let seeds: Vec<u64> = (0..50).collect();
+    let a: Vec<u32> = seeds.iter().map(|s| (s * 37 % 101) as u32).collect();
+    let b: Vec<u32> = a.iter().map(|s| s + 3).collect();
+    let d = paired_difference(&run(&a, &seeds), &run(&b, &seeds),
+                              &config(0.95));
+    assert_eq!((d.mean, d.low, d.high), (3.0, 3.0, 3.0));
+
+    // Games of different seeds are not compared
+    let others: Vec<u64> = (100..150).collect();
+    let d = paired_difference(&run(&a, &seeds), &run(&b, &others),
+                              &config(0.95));
+    assert_eq!((d.mean, d.low, d.high), (0.0, 0.0, 0.0));
+}
+
+/// Resampling the differences 0 and 10 gives means of 0, 5 and 10 with
+/// probabilities 1/4, 1/2 and 1/4
+#[test]
+fn bootstrap_of_two_pairs() {
+    let seeds = [1, 2];
+    let a = run(&[10, 10], &seeds);
+    let b = run(&[10, 20], &seeds);
+    let d = paired_difference(&a, &b, &config(0.9));
+    assert_eq!((d.mean, d.low, d.high), (5.0, 0.0, 10.0));
+    let d = paired_difference(&a, &b, &config(0.4));
+    assert_eq!((d.mean, d.low, d.high), (5.0, 5.0, 5.0));
+}
+
+/// With many pairs, the interval is close to the normal one: the mean plus
+/// or minus 1.96 standard errors
+#[test]
+fn bootstrap_matches_normal_interval() {
+    let n = 400;
+    let seeds: Vec<u64> = (0..n).collect();
+    let a = run(&vec![10; n as usize], &seeds);
+    // Differences of -1 and +1 in turn: a standard deviation of 1
+    let scores: Vec<u32> = seeds.iter().map(|s| 9 + 2 * (s % 2) as u32)
+                                .collect();
+    let d = paired_difference(&a, &run(&scores, &seeds), &config(0.95));
+    let half = 1.96 / (n as f64).sqrt();
+    assert_eq!(d.mean, 0.0);
+    assert!((d.low + half).abs() < 0.1 * half, "{:?}", d);
+    assert!((d.high - half).abs() < 0.1 * half, "{:?}", d);
+}
+
+/// Whether the tags of `svg` are balanced, a single `svg` element holding
+/// all the others
+fn is_well_formed(svg: &str) -> bool {
+    let mut open = Vec::new();
+    let mut rest = svg.trim();
+    if !rest.starts_with("<svg") {
+        return false;
+    }
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => return false
+        };
+        let tag = &rest[start + 1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            if open.pop() != Some(name.to_string()) {
+                return false;
+            }
+        } else if !tag.ends_with('/') {
+            let name = tag.split_whitespace().next().unwrap_or("");
+            if open.is_empty() && name != "svg" {
+                return false;
+            }
+            open.push(name.to_string());
+        }
+        rest = &rest[end + 1..];
+        if open.is_empty() {
+            return rest.trim().is_empty();
+        }
+    }
+    false
+}
+
+#[test]
+fn results_are_exported() {
+    let mut first = run(&[10, 20, 30], &[7, 8, 9]);
+    first.agent = "mcts:a,b.net:100".to_string();
+    let second = run(&[5, 40, 15], &[7, 8, 9]);
+    let runs = [first, second];
+    let dir = scratch_dir("bench");
+
+    let path = dir.join("games.csv");
+    write_csv(&runs, std::fs::File::create(&path).unwrap()).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "seed,agent,score,best,moves,time_ms");
+    assert_eq!(lines.len(), 1 + 6);
+    assert_eq!(lines[1], "7,\"mcts:a,b.net:100\",10,H,101,1.000");
+    assert_eq!(lines[6], "9,test,15,H,151,1.000");
+
+    let charts = dir.join("charts");
+    save_charts(&runs, &charts).unwrap();
+    for name in ["scores.svg", "survival.svg", "best-atoms-0.svg",
+                 "best-atoms-1.svg"].iter() {
+        let svg = std::fs::read_to_string(charts.join(name)).unwrap();
+        assert!(is_well_formed(&svg), "{} is not well formed", name);
+    }
+    // A curve and a name in the legend by agent
+    let survival = std::fs::read_to_string(charts.join("survival.svg"))
+                           .unwrap();
+    assert_eq!(survival.matches("stroke-width=\"2\"").count(), runs.len());
+    let scores = std::fs::read_to_string(charts.join("scores.svg")).unwrap();
+    for (i, color) in ["#1f77b4", "#d62728"].iter().enumerate() {
+        assert!(scores.contains(&format!("fill=\"{}\" fill-opacity", color)));
+        assert!(scores.contains(&format!(">{}</text>", runs[i].agent)));
+    }
+    assert!(!scores.contains("#2ca02c"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
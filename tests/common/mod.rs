@@ -0,0 +1,15 @@
+//! Helpers shared by the tests.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// An empty directory of its own for the test `name`, in the temporary
+/// directory
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("iatomas-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
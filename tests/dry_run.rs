@@ -0,0 +1,61 @@
+//! `GameState::dry_run` tells what a move does without playing it.
+
+use iatomas::agent::game_seed;
+use iatomas::game::{Atom, AtomType, GameState, Move, SpawnModel};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const GAMES: u64 = 20;
+const MOVES: usize = 300;
+
+fn types(atoms: &[Atom]) -> Vec<AtomType> {
+    atoms.iter().map(|a| a.t.clone()).collect()
+}
+
+/// Every legal move of random games, dry-run then played
+#[test]
+fn dry_run_matches_play() {
+    let mut chooser = ChaCha8Rng::seed_from_u64(7);
+    for game in 0..GAMES {
+        let mut state = GameState::start_game_seeded(game_seed(0, game),
+                                                     SpawnModel::default());
+        for _ in 0..MOVES {
+            let moves = state.legal_moves();
+            if moves.is_empty() {
+                break;
+            }
+            for &mv in &moves {
+                let run = state.dry_run(mv);
+                let mut played = state.clone();
+                let (max, fusions) = played.apply_traced(mv);
+                assert_eq!(types(&run.atoms), types(&played.atoms));
+                assert_eq!(run.score_delta, played.score - state.score);
+                assert_eq!(run.max, max);
+                assert_eq!(run.fusions.len(), fusions.len());
+                assert_eq!(run.minused, played.minused);
+            }
+            let mv = moves[chooser.gen_range(0..moves.len())];
+            state.apply(mv);
+        }
+    }
+}
+
+/// Neither the time nor the generator move, so the same atoms come next
+#[test]
+fn dry_run_leaves_state_untouched() {
+    let mut state = GameState::start_game_seeded(3, SpawnModel::default());
+    let time = state.time;
+    let atoms = types(&state.atoms);
+    let mut next = state.rng.clone();
+
+    for mv in state.legal_moves() {
+        let run = state.dry_run(mv);
+        if let Move::Place(_) = mv {
+            assert_eq!(run.incoming.t, AtomType::None);
+        }
+    }
+    assert_eq!(state.time, time);
+    assert_eq!(types(&state.atoms), atoms);
+    assert_eq!(state.rng.gen::<u64>(), next.gen::<u64>());
+}
@@ -0,0 +1,143 @@
+//! Stepping games together in a `BatchEnv` gives what stepping each of them
+//! with `Env::step` gives, whatever the number of threads.
+
+use iatomas::agent::game_seed;
+use iatomas::env::{ACTION_SPACE, BatchEnv, Env, EnvConfig, OBSERVATION_SIZE,
+                   Reward};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const GAMES: usize = 7;
+const STEPS: usize = 300;
+const SEED: u64 = 42;
+
+fn config() -> EnvConfig {
+    EnvConfig {
+        reward: Reward::Shaped(0.5),
+        // Short episodes, for games to be reset along the way
+        max_moves: 40,
+        ..EnvConfig::default()
+    }
+}
+
+/// The games of a `BatchEnv`, stepped one by one, and reset as it does
+struct Sequential {
+    envs: Vec<Env>,
+    episodes: Vec<u64>,
+}
+
+impl Sequential {
+    fn new() -> Self {
+        let mut envs: Vec<Env> = (0..GAMES).map(|_| Env::new(config()))
+                                           .collect();
+        for (i, env) in envs.iter_mut().enumerate() {
+            env.reset(game_seed(game_seed(SEED, i as u64), 0));
+        }
+        Self { envs, episodes: vec![1; GAMES] }
+    }
+
+    fn step(&mut self, actions: &[usize]) -> (Vec<f32>, Vec<f32>, Vec<bool>) {
+        let (mut observations, mut rewards, mut dones) = (vec![], vec![],
+                                                          vec![]);
+        for (i, env) in self.envs.iter_mut().enumerate() {
+            let step = env.step(actions[i]).unwrap();
+            rewards.push(step.reward as f32);
+            dones.push(step.done);
+            if step.done {
+                let seed = game_seed(game_seed(SEED, i as u64),
+                                     self.episodes[i]);
+                self.episodes[i] += 1;
+                observations.extend(env.reset(seed));
+            } else {
+                observations.extend(step.observation);
+            }
+        }
+        (observations, rewards, dones)
+    }
+}
+
+fn batch_matches_sequential(threads: usize) {
+    let mut batch = BatchEnv::new(GAMES, config(), SEED, threads);
+    let mut sequential = Sequential::new();
+    let mut rng = ChaCha8Rng::seed_from_u64(threads as u64);
+    let mut observations = vec![0.0; GAMES * OBSERVATION_SIZE];
+    let mut rewards = vec![0.0; GAMES];
+    let mut dones = vec![false; GAMES];
+    let mut masks = vec![false; GAMES * ACTION_SPACE];
+    batch.reset(&mut observations);
+    let first: Vec<f32> = sequential.envs.iter()
+                                    .flat_map(|e| e.observation()).collect();
+    assert_eq!(observations, first);
+
+    let mut ended = 0;
+    for _ in 0..STEPS {
+        batch.action_masks(&mut masks);
+        let actions: Vec<usize> = masks.chunks(ACTION_SPACE).map(|mask| {
+            let legal: Vec<usize> = (0..ACTION_SPACE).filter(|&a| mask[a])
+                                                     .collect();
+            legal[rng.gen_range(0..legal.len())]
+        }).collect();
+        batch.step(&actions, &mut observations, &mut rewards, &mut dones)
+             .unwrap();
+        let expected = sequential.step(&actions);
+        assert_eq!((&observations, &rewards, &dones),
+                   (&expected.0, &expected.1, &expected.2));
+        ended += dones.iter().filter(|&&d| d).count();
+    }
+    assert!(ended > GAMES);
+    for (i, env) in sequential.envs.iter().enumerate() {
+        assert_eq!(batch.env(i).state().score, env.state().score);
+    }
+}
+
+#[test]
+fn batch_matches_sequential_on_one_thread() {
+    batch_matches_sequential(1);
+}
+
+#[test]
+fn batch_matches_sequential_on_threads() {
+    batch_matches_sequential(3);
+    batch_matches_sequential(GAMES + 2);
+}
+
+#[test]
+fn illegal_action_plays_nothing() {
+    let mut batch = BatchEnv::new(GAMES, config(), SEED, 2);
+    let mut observations = vec![0.0; GAMES * OBSERVATION_SIZE];
+    let mut rewards = vec![0.0; GAMES];
+    let mut dones = vec![false; GAMES];
+    batch.reset(&mut observations);
+    let before = observations.clone();
+    let mut actions = vec![0; GAMES];
+    actions[GAMES - 1] = ACTION_SPACE;
+    assert!(batch.step(&actions, &mut observations, &mut rewards,
+                       &mut dones).is_err());
+    assert_eq!(observations, before);
+    assert!(batch.infos().iter().all(|info| info.moves == 0));
+}
+
+/// `Env::action_move` allows the actions of the action mask, and only those
+#[test]
+fn action_move_follows_mask() {
+    let mut env = Env::new(config());
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    for game in 0..20 {
+        env.reset(game);
+        while !env.is_done() {
+            let mask = env.action_mask();
+            for (action, &legal) in mask.iter().enumerate() {
+                let mv = env.action_move(action);
+                assert_eq!(mv.is_some(), legal, "action {}", action);
+                if let Some(mv) = mv {
+                    assert!(env.state().legal_moves().contains(&mv));
+                }
+            }
+            assert!(env.action_move(ACTION_SPACE).is_none());
+            let legal: Vec<usize> = (0..ACTION_SPACE).filter(|&a| mask[a])
+                                                     .collect();
+            env.step(legal[rng.gen_range(0..legal.len())]).unwrap();
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Hints are found in the background, and a cancelled search stops.
+
+mod common;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use iatomas::agent::{self, HINT_MOVES, HeuristicAgent, Hinter, SearchLimit};
+use iatomas::game::{ACTIONS, ENCODING_SIZE, GameState, SpawnModel};
+use iatomas::nn::PolicyValueNet;
+use common::scratch_dir;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The hint of the last request, waiting for it at most `timeout`
+fn wait_hint(hinter: &mut Hinter, timeout: Duration)
+                                            -> Option<agent::Hint> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(hint) = hinter.poll() {
+            return Some(hint);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+#[test]
+fn hint_on_last_request() {
+    let mut hinter = Hinter::new(Box::new(HeuristicAgent::default()));
+    let state = GameState::start_game_seeded(1, SpawnModel::Simple);
+    hinter.request(&state);
+    let hint = wait_hint(&mut hinter, Duration::from_secs(10))
+        .expect("a hint comes");
+    assert!(!hint.moves.is_empty() && hint.moves.len() <= HINT_MOVES);
+    let legal = state.legal_moves();
+    assert!(hint.moves.iter().all(|(mv, _)| legal.contains(mv)));
+
+    // The hint of a cancelled request is never given
+    hinter.request(&state);
+    hinter.cancel();
+    assert!(wait_hint(&mut hinter, Duration::from_millis(300)).is_none());
+}
+
+/// A search whose result is no longer wanted stops, however many
+/// simulations it was set to run
+#[test]
+fn cancelled_search_stops() {
+    let dir = scratch_dir("hint");
+    let path = dir.join("mcts.net");
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    PolicyValueNet::new(ENCODING_SIZE, &[64, 64], ACTIONS, &mut rng)
+        .save(&path).unwrap();
+    let spec = format!("mcts:{}:100000000", path.display());
+    let mut agent = agent::from_spec(&spec, 0).unwrap();
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let limit = SearchLimit::cancelled_by(Arc::clone(&cancel));
+    let flag = Arc::clone(&cancel);
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        flag.store(true, Ordering::SeqCst);
+    });
+    let state = GameState::start_game_seeded(1, SpawnModel::Simple);
+    let start = Instant::now();
+    let moves = agent.rank_moves_within(&state, &limit);
+    let elapsed = start.elapsed();
+    canceller.join().unwrap();
+    assert!(!moves.is_empty());
+    assert!(elapsed < Duration::from_secs(5), "stopped after {:?}",
+            elapsed);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,89 @@
+//! High scores and histories are saved and read back as they were, ranked
+//! and cut at `TOP_SCORES`, and dates are written in UTC.
+
+mod common;
+
+use std::fs;
+
+use iatomas::history::{GameSummary, History, TOP_SCORES, format_date};
+use common::scratch_dir;
+
+/// A game ending with `score`, its best atom Lithium
+fn game(date: u64, mode: &str, seed: Option<u64>, score: u32)
+                                                        -> GameSummary {
+    GameSummary {
+        date,
+        mode: mode.to_string(),
+        seed,
+        score,
+        best: 2,
+        time: 40,
+    }
+}
+
+#[test]
+fn history_round_trip() {
+    let dir = scratch_dir("history-round-trip");
+    let mut history = History::open(&dir).unwrap();
+    assert!(history.top.is_empty() && history.games.is_empty());
+    let games = [
+        game(1_700_000_000, "window", Some(7), 1200),
+        game(1_700_000_100, "autoplay mcts:a,b.net:200", Some(u64::MAX), 900),
+        game(1_700_000_200, "say \"hi\", twice", Some(0), 3000),
+        game(1_700_000_300, "tui", Some(1), 0),
+    ];
+    for game in &games {
+        history.record(game.clone()).unwrap();
+    }
+
+    let read = History::open(&dir).unwrap();
+    assert_eq!(read.games, games);
+    assert_eq!(read.top, history.top);
+    let scores: Vec<u32> = read.top.iter().map(|g| g.score).collect();
+    assert_eq!(scores, [3000, 1200, 900, 0]);
+
+    fs::write(dir.join("scores.csv"), "date,mode,seed,score,best,time\n\
+                                       1,window,x,10,H,3\n").unwrap();
+    assert!(History::open(&dir).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scores_are_ranked() {
+    let dir = scratch_dir("history-ranks");
+    let mut history = History::open(&dir).unwrap();
+    let mut ranks = Vec::new();
+    for (i, score) in [50, 80, 50, 10, 90, 70, 60, 40, 30, 20, 100, 5, 65]
+                          .iter().enumerate() {
+        ranks.push(history.record(game(i as u64, "tui", Some(i as u64),
+                                       *score)).unwrap());
+    }
+    // Ties go to the earlier games, and those below the table are not
+    // ranked
+    assert_eq!(ranks[..4], [Some(0), Some(0), Some(2), Some(3)]);
+    assert_eq!(ranks[10], Some(0));
+    assert_eq!(ranks[11], None);
+    assert_eq!(ranks[12], Some(4));
+    assert_eq!(history.top.len(), TOP_SCORES);
+    let scores: Vec<u32> = history.top.iter().map(|g| g.score).collect();
+    assert_eq!(scores, [100, 90, 80, 70, 65, 60, 50, 50, 40, 30]);
+    assert_eq!(history.top[6].seed, Some(0));
+    assert_eq!(history.games.len(), 13);
+
+    // Edited games are kept in the history only
+    let edited = game(20, "window edited", None, 1_000_000);
+    assert_eq!(history.record(edited.clone()).unwrap(), None);
+    assert_eq!(history.top.len(), TOP_SCORES);
+    assert_eq!(history.top[0].score, 100);
+    assert_eq!(history.games.last(), Some(&edited));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dates_in_utc() {
+    assert_eq!(format_date(0), "1970-01-01 00:00");
+    assert_eq!(format_date(59), "1970-01-01 00:00");
+    assert_eq!(format_date(951_782_400), "2000-02-29 00:00");
+    assert_eq!(format_date(1_700_000_000), "2023-11-14 22:13");
+    assert_eq!(format_date(4_107_542_399), "2100-02-28 23:59");
+}
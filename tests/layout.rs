@@ -0,0 +1,93 @@
+//! Clicks land on what is drawn where they are, whatever the size of the
+//! window.
+
+use iatomas::game::{AtomType, GameState, Layout, MIN_TEXT_SIZE, Move,
+                    SpawnModel, atom_symbol, parse_position};
+
+/// Window sizes, square or not, and screen densities
+const WINDOWS: [(f32, f32, f32); 6] = [
+    (400.0, 400.0, 1.0),
+    (1200.0, 500.0, 1.0),
+    (300.0, 900.0, 1.0),
+    (800.0, 600.0, 2.0),
+    (100.0, 100.0, 1.0),
+    (2560.0, 1440.0, 1.5),
+];
+const MAX_ATOMS: usize = 18;
+
+/// A ring of `n` Hydrogens, `incoming` coming
+fn ring(n: usize, incoming: AtomType) -> GameState {
+    let atoms = if n == 0 { ".".to_string() } else { vec!["H"; n].join(",") };
+    let text = format!("{} {} 0 0", atoms, atom_symbol(&incoming));
+    parse_position(&text, 0, SpawnModel::default()).unwrap()
+}
+
+#[test]
+fn atoms_are_found_where_drawn() {
+    for &(width, height, scale) in WINDOWS.iter() {
+        let layout = Layout::new(width, height, scale);
+        for n in 0..=MAX_ATOMS {
+            let minus = ring(n, AtomType::Minus);
+            for i in 0..n {
+                let (x, y) = layout.nth_atom_coord(i, n);
+                assert_eq!(layout.atom_at(x, y, n), Some(i),
+                           "atom {} of {} in {}x{}", i, n, width, height);
+                assert_eq!(layout.target(x, y, &minus), Some(Move::Pick(i)));
+            }
+            let (x, y) = layout.centre;
+            assert_eq!(layout.atom_at(x, y, n), None);
+        }
+    }
+}
+
+#[test]
+fn gaps_are_found_where_drawn() {
+    for &(width, height, scale) in WINDOWS.iter() {
+        let layout = Layout::new(width, height, scale);
+        for n in 0..=MAX_ATOMS {
+            let mut state = ring(n, AtomType::Atom(1));
+            for i in 0..n.max(1) {
+                let (x, y) = layout.ring_point(Layout::gap_angle(i, n), 1.0);
+                assert_eq!(layout.gap_at(x, y, n), Some(i),
+                           "gap {} of {} in {}x{}", i, n, width, height);
+                assert_eq!(layout.target(x, y, &state), Some(Move::Place(i)));
+                // Places are counted from the first atom, drawn `shift`
+                // slots further
+                if n > 0 {
+                    state.shift = 1;
+                    assert_eq!(layout.target(x, y, &state),
+                               Some(Move::Place((i + n - 1) % n)));
+                    state.shift = 0;
+                }
+            }
+            let (x, y) = (layout.centre.0 + 2.0 * layout.circle_radius,
+                          layout.centre.1);
+            assert_eq!(layout.gap_at(x, y, n), None);
+        }
+    }
+}
+
+#[test]
+fn centre_and_texts() {
+    for &(width, height, scale) in WINDOWS.iter() {
+        let layout = Layout::new(width, height, scale);
+        let (x, y) = layout.centre;
+        assert!(layout.in_centre(x, y));
+        assert!(!layout.in_centre(x, y - layout.circle_radius));
+        let mut state = ring(4, AtomType::Minus);
+        state.minused = true;
+        assert_eq!(layout.target(x, y, &state), Some(Move::Convert));
+
+        // The ring fits in the window
+        let reach = layout.circle_radius + layout.atom_radius;
+        assert!(x - reach >= 0.0 && x + reach <= width);
+        assert!(y - reach >= 0.0 && y + reach <= height);
+
+        let smallest = (MIN_TEXT_SIZE * scale).round() as u32;
+        for layout in [layout.clone(), layout.with_text_sizes(1.0, 2.0)]
+                          .iter() {
+            assert!(layout.symbol_size >= smallest);
+            assert!(layout.title_size >= smallest);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! The gradients of `PolicyValueNet` match finite differences of its
+//! losses, and networks come back from their files as they were saved.
+
+mod common;
+
+use std::fs;
+use std::io;
+
+use iatomas::nn::PolicyValueNet;
+use common::scratch_dir;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+const INPUTS: usize = 6;
+const ACTIONS: usize = 5;
+const BATCH: usize = 4;
+
+/// A random batch: inputs, masks, target policies and target values
+struct Batch {
+    x: Vec<f32>,
+    masks: Vec<bool>,
+    policies: Vec<f32>,
+    values: Vec<f32>,
+}
+
+impl Batch {
+    fn new<R: Rng>(rng: &mut R) -> Self {
+        let x = (0..BATCH * INPUTS).map(|_| rng.gen_range(-1.0..1.0))
+                                   .collect();
+        let mut masks = Vec::new();
+        let mut policies = Vec::new();
+        for _ in 0..BATCH {
+            let row: Vec<bool> = (0..ACTIONS).map(|a| a == 0 || rng.gen())
+                                             .collect();
+            let weights: Vec<f32> = row.iter()
+                .map(|&m| if m { rng.gen_range(0.1..1.0) } else { 0.0 })
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            policies.extend(weights.iter().map(|w| w / sum));
+            masks.extend(row);
+        }
+        let values = (0..BATCH).map(|_| rng.gen_range(-2.0..2.0)).collect();
+        Self { x, masks, policies, values }
+    }
+
+    fn loss(&self, net: &PolicyValueNet) -> f32 {
+        let (losses, _) = net.gradients(&self.x, &self.masks, &self.policies,
+                                        &self.values, BATCH);
+        losses.value + losses.policy
+    }
+}
+
+#[test]
+fn gradients_match_finite_differences() {
+    let mut rng = ChaCha8Rng::seed_from_u64(7);
+    let net = PolicyValueNet::new(INPUTS, &[8, 8], ACTIONS, &mut rng);
+    let batch = Batch::new(&mut rng);
+    let (_, grad) = net.gradients(&batch.x, &batch.masks, &batch.policies,
+                                  &batch.values, BATCH);
+    let eps = 1e-3;
+    let layers = grad.trunk.len() + 2;
+    let mut checked = 0;
+    for l in 0..layers {
+        let analytic = match l {
+            l if l < grad.trunk.len() => &grad.trunk[l],
+            l if l == grad.trunk.len() => &grad.value,
+            _ => &grad.policy,
+        };
+        let params = analytic.weights.len() + analytic.biases.len();
+        for i in 0..params {
+            let moved = |delta: f32| {
+                let mut net = net.clone();
+                let layer = match l {
+                    l if l < net.trunk.len() => &mut net.trunk[l],
+                    l if l == net.trunk.len() => &mut net.value,
+                    _ => &mut net.policy,
+                };
+                let n = layer.weights.len();
+                if i < n {
+                    layer.weights[i] += delta;
+                } else {
+                    layer.biases[i - n] += delta;
+                }
+                batch.loss(&net)
+            };
+            let numeric = (moved(eps) - moved(-eps)) / (2.0 * eps);
+            let n = analytic.weights.len();
+            let exact = if i < n { analytic.weights[i] }
+                        else { analytic.biases[i - n] };
+            assert!((numeric - exact).abs() <= 2e-3 + 2e-2 * exact.abs(),
+                    "layer {}, parameter {}: {} by finite differences, {}",
+                    l, i, numeric, exact);
+            checked += 1;
+        }
+    }
+    assert!(checked > 100);
+}
+
+#[test]
+fn training_lowers_the_loss() {
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+    let mut net = PolicyValueNet::new(INPUTS, &[16], ACTIONS, &mut rng);
+    let batch = Batch::new(&mut rng);
+    let before = batch.loss(&net);
+    let mut adam = iatomas::nn::Adam::new(1e-2);
+    for _ in 0..200 {
+        net.train_batch(&mut adam, &batch.x, &batch.masks, &batch.policies,
+                        &batch.values, BATCH);
+    }
+    assert!(batch.loss(&net) < before / 2.0);
+}
+
+#[test]
+fn network_round_trip() {
+    let mut rng = ChaCha8Rng::seed_from_u64(3);
+    let net = PolicyValueNet::new(INPUTS, &[8, 4], ACTIONS, &mut rng);
+    let path = scratch_dir("nn-round-trip").join("round-trip.net");
+    net.save(&path).unwrap();
+    let loaded = PolicyValueNet::load(&path).unwrap();
+    assert_eq!(loaded.trunk.len(), net.trunk.len());
+    for (a, b) in loaded.trunk.iter().chain([&loaded.value, &loaded.policy])
+                        .zip(net.trunk.iter().chain([&net.value, &net.policy]))
+    {
+        assert_eq!((a.inputs, a.outputs), (b.inputs, b.outputs));
+        assert_eq!(a.weights, b.weights);
+        assert_eq!(a.biases, b.biases);
+    }
+    let x: Vec<f32> = (0..INPUTS).map(|i| i as f32 / 3.0 - 1.0).collect();
+    assert_eq!(loaded.forward(&x, 1), net.forward(&x, 1));
+}
+
+#[test]
+fn oversized_layers_are_refused() {
+    let mut rng = ChaCha8Rng::seed_from_u64(5);
+    let net = PolicyValueNet::new(INPUTS, &[], ACTIONS, &mut rng);
+    let path = scratch_dir("nn-oversized").join("oversized.net");
+    net.save(&path).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    // Magic, version and layer count come first, then the shape of a layer
+    for (at, value) in [(8, u32::MAX), (12, u32::MAX), (16, u32::MAX),
+                        (12, 0), (12, 1 << 20)] {
+        let mut bad = bytes.clone();
+        bad[at..at + 4].copy_from_slice(&value.to_le_bytes());
+        fs::write(&path, bad).unwrap();
+        let error = PolicyValueNet::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData,
+                   "{} at {}: {}", value, at, error);
+    }
+}
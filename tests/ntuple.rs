@@ -0,0 +1,83 @@
+//! N-tuple networks come back from their files as they were saved, and
+//! files describing impossible tuples are refused.
+
+mod common;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use iatomas::agent::{Anchor, NTupleNetwork, Tuple};
+use iatomas::game::{GameState, SpawnModel};
+use common::scratch_dir;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Positions met playing random moves
+fn positions(seed: u64) -> Vec<GameState> {
+    let mut chooser = ChaCha8Rng::seed_from_u64(seed);
+    let mut state = GameState::start_game_seeded(seed, SpawnModel::default());
+    let mut out = Vec::new();
+    while !state.is_over() && out.len() < 300 {
+        let legal = state.legal_moves();
+        state.apply(legal[chooser.gen_range(0..legal.len())]);
+        out.push(state.clone());
+    }
+    out
+}
+
+#[test]
+fn network_round_trip() {
+    let mut network = NTupleNetwork::default();
+    let states = positions(3);
+    for (i, state) in states.iter().enumerate() {
+        let lookups = network.lookups(state);
+        network.update(&lookups, i as f64 % 7.0 - 3.0);
+    }
+    let path = scratch_dir("ntuple-round-trip").join("round-trip.ntuple");
+    network.save(&path).unwrap();
+    let loaded = NTupleNetwork::load(&path).unwrap();
+    assert_eq!(loaded.tuples.len(), network.tuples.len());
+    for (a, b) in loaded.tuples.iter().zip(&network.tuples) {
+        assert_eq!(a.anchor, b.anchor);
+        assert_eq!(a.offsets, b.offsets);
+        assert_eq!(a.weights, b.weights);
+    }
+    assert_eq!(loaded.size, network.size);
+    for state in &states {
+        assert_eq!(loaded.value(state), network.value(state));
+    }
+}
+
+/// A file holding one tuple of `len` slots at offset `offset`, with no
+/// weights after it
+fn tuple_file(name: &str, len: u8, offset: i32) -> PathBuf {
+    let network = NTupleNetwork::new(vec![Tuple::new(Anchor::Every, vec![0])]);
+    let path = scratch_dir("ntuple-refused").join(name);
+    network.save(&path).unwrap();
+    let mut bytes = fs::read(&path).unwrap();
+    // Magic, version and count come first, then the anchor and the length
+    bytes[13] = len;
+    bytes.truncate(14);
+    for _ in 0..len {
+        bytes.extend_from_slice(&offset.to_le_bytes());
+    }
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn impossible_tuples_are_refused() {
+    for &(len, offset) in &[(0, 0), (8, 1), (20, 1), (255, 1), (2, 19),
+                            (2, -19), (2, i32::MIN)] {
+        let path = tuple_file("bad.ntuple", len, offset);
+        let error = NTupleNetwork::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData,
+                   "{} slots at {}: {}", len, offset, error);
+    }
+    // A valid header is only let down by the missing table
+    let path = tuple_file("short.ntuple", 2, 1);
+    let error = NTupleNetwork::load(&path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,195 @@
+//! Conformance of the reference engine to the external agent protocol.
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use iatomas::agent::{self, HeuristicAgent};
+use iatomas::game::{ACTIONS, ENCODING_SIZE, SpawnModel, parse_position};
+use iatomas::nn::PolicyValueNet;
+use iatomas::protocol::{self, EngineProcess, MatchConfig, Reply};
+use common::scratch_dir;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const ENGINE: &str = env!("CARGO_BIN_EXE_iatomas");
+
+/// A raw conversation with the reference engine
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Session {
+    fn start() -> Self {
+        let mut child = Command::new(ENGINE).arg("engine")
+                                            .stdin(Stdio::piped())
+                                            .stdout(Stdio::piped())
+                                            .spawn()
+                                            .expect("the engine starts");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Self { child, stdin, stdout }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stdin, "{}", line).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Reply {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        Reply::parse(&line).expect("a valid reply").expect("a non-empty line")
+    }
+
+    fn handshake(&mut self) -> Vec<Reply> {
+        self.send("iap");
+        let mut replies = Vec::new();
+        loop {
+            let reply = self.receive();
+            if reply == Reply::IapOk {
+                return replies;
+            }
+            replies.push(reply);
+        }
+    }
+
+    fn best_move(&mut self, position: &str) -> Reply {
+        self.send(&format!("position {}", position));
+        self.send("go budget=1000");
+        self.receive()
+    }
+}
+
+#[test]
+fn handshake_names_engine_and_options() {
+    let mut session = Session::start();
+    let replies = session.handshake();
+    assert!(replies.contains(&Reply::Id { key: "name".to_string(),
+                                          value: "iatomas".to_string() }));
+    assert!(replies.iter().any(|r| matches!(r, Reply::Option { name, .. }
+                                                if name == "agent")));
+    session.send("isready");
+    assert_eq!(session.receive(), Reply::ReadyOk);
+}
+
+#[test]
+fn best_moves_are_legal() {
+    let mut session = Session::start();
+    session.handshake();
+    for position in &["H,H,H,H He 0 0", "H,He,+,He Li 42 17",
+                      "H,He,Li - 10 8", "H,He,Li Nu 10 8", "H,He,Li Be 4 9 m",
+                      ". + 0 5"] {
+        let state = parse_position(position, 0, SpawnModel::default())
+                        .unwrap();
+        match session.best_move(position) {
+            Reply::BestMove(mv) => {
+                assert!(state.legal_moves().contains(&mv),
+                        "illegal move {:?} in {}", mv, position);
+            },
+            reply => panic!("`{}` instead of a move in {}", reply, position)
+        }
+    }
+}
+
+#[test]
+fn errors_are_reported_and_survived() {
+    let mut session = Session::start();
+    session.handshake();
+    for bad in &["frobnicate", "position H,Xx H 0 0", "go budget=soon",
+                 "setoption name nope value 1"] {
+        session.send(bad);
+        assert!(matches!(session.receive(), Reply::Error(_)), "{}", bad);
+    }
+    session.send("newgame");
+    session.send("go budget=100");
+    assert!(matches!(session.receive(), Reply::Error(_)));
+    assert!(matches!(session.best_move("H,H H 0 0"), Reply::BestMove(_)));
+}
+
+/// A search set to run far longer than the budget of the move is cut short
+#[test]
+fn budget_is_kept() {
+    let dir = scratch_dir("protocol-budget");
+    let path = dir.join("mcts.net");
+    let mut rng = ChaCha8Rng::seed_from_u64(0);
+    PolicyValueNet::new(ENCODING_SIZE, &[64, 64], ACTIONS, &mut rng)
+        .save(&path).unwrap();
+
+    let mut session = Session::start();
+    session.handshake();
+    session.send(&format!("setoption name agent value mcts:{}:10000",
+                          path.display()));
+    session.send("isready");
+    assert_eq!(session.receive(), Reply::ReadyOk);
+    for budget in [50, 200] {
+        session.send("position H,He,Li,H,He + 10 8");
+        let start = Instant::now();
+        session.send(&format!("go budget={}", budget));
+        let reply = session.receive();
+        let elapsed = start.elapsed();
+        assert!(matches!(reply, Reply::BestMove(_)), "{}", reply);
+        assert!(elapsed <= Duration::from_millis(budget),
+                "answered in {:?} for a budget of {} ms", elapsed, budget);
+    }
+}
+
+#[test]
+fn quit_stops_engine() {
+    let mut session = Session::start();
+    session.handshake();
+    session.send("quit");
+    assert!(session.child.wait().unwrap().success());
+}
+
+#[test]
+fn driver_plays_like_the_agent() {
+    let spawn = SpawnModel::default();
+    let mut engine = EngineProcess::launch(&format!("{} engine", ENGINE))
+                                   .unwrap();
+    assert_eq!(engine.name, "iatomas");
+    engine.set_option("agent", "heuristic").unwrap();
+    engine.ready().unwrap();
+    for seed in 0..3 {
+        let remote = engine.play_game(seed, &spawn, Duration::from_secs(1))
+                           .unwrap();
+        let local = agent::play_game(&mut HeuristicAgent::default(), seed,
+                                     &spawn);
+        assert_eq!((remote.score, remote.moves), (local.score, local.moves));
+    }
+    engine.quit().unwrap();
+}
+
+/// An engine is not left running when its match fails
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_match_stops_engine() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch_dir("protocol-driver");
+    let (script, pid) = (dir.join("engine.sh"), dir.join("engine.pid"));
+    let content = format!("#!/bin/sh\necho $$ > {}\nexec {} engine\n",
+                          pid.display(), ENGINE);
+    std::fs::write(&script, content).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+        .unwrap();
+
+    let config = MatchConfig {
+        games: 1,
+        seed: 0,
+        spawn: SpawnModel::default(),
+        budget: Duration::from_millis(100),
+        options: vec![("agent".to_string(), "nonsense".to_string())],
+    };
+    let command = script.display().to_string();
+    assert!(protocol::play_engine(&command, &config).is_err());
+    let pid = std::fs::read_to_string(&pid).unwrap();
+    assert!(!std::path::Path::new(&format!("/proc/{}", pid.trim())).exists(),
+            "the engine {} is still running", pid.trim());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,81 @@
+//! Reaction and spawn rules the self-play tuning relies on: reactions end
+//! on small rings instead of panicking, Dark Pluses react, and the default
+//! spawn model draws the atoms the game always drew.
+
+use iatomas::game::{AtomType, GameState, Move, SpawnModel, parse_position,
+                    position};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The position reached by playing `mv` in `pos`, and the score
+fn play(pos: &str, mv: Move) -> (String, u32) {
+    let mut state = parse_position(pos, 0, SpawnModel::default()).unwrap();
+    state.apply(mv);
+    let after = position(&state);
+    let ring = after.split_whitespace().next().unwrap().to_string();
+    (ring, state.score)
+}
+
+/// Fusing the last two atoms leaves one, which does not fuse with itself
+#[test]
+fn two_atom_ring_fuses_once() {
+    assert_eq!(play("H,H + 0 0", Move::Place(1)), ("He".to_string(), 1));
+}
+
+/// A chain running down to two atoms used to panic
+#[test]
+fn chain_on_small_ring_ends() {
+    for k in 0..4 {
+        let mut state = parse_position("H,H,H,H + 0 0", 0,
+                                       SpawnModel::default()).unwrap();
+        state.apply(Move::Place(k));
+        assert!(!state.atoms.is_empty());
+    }
+}
+
+/// A Dark Plus fuses different atoms, into the larger one plus three
+#[test]
+fn dark_plus_reacts() {
+    assert_eq!(play("H,He D+ 0 0", Move::Place(1)), ("B".to_string(), 3));
+}
+
+/// Pluses one time out of five, then Minuses one time out of five, else an
+/// element of Bin(7, 1/2), and never a Dark Plus or a Neutrino
+#[test]
+fn simple_spawn_draws_as_before() {
+    const DRAWS: u32 = 100_000;
+    let mut rng = ChaCha8Rng::seed_from_u64(11);
+    let mut model = SpawnModel::Simple;
+    let (mut plus, mut minus, mut sum) = (0, 0, 0);
+    for time in 0..DRAWS {
+        match model.draw(time, 10_000, &mut rng) {
+            AtomType::Plus => plus += 1,
+            AtomType::Minus => minus += 1,
+            AtomType::Atom(z) => {
+                assert!(z <= 7);
+                sum += z as u32;
+            },
+            t => panic!("drew {:?}", t),
+        }
+    }
+    let rate = |n: u32| n as f64 / DRAWS as f64;
+    assert!((rate(plus) - 0.2).abs() < 0.005);
+    assert!((rate(minus) - 0.16).abs() < 0.005);
+    let atoms = (DRAWS - plus - minus) as f64;
+    assert!((sum as f64 / atoms - 3.5).abs() < 0.02);
+}
+
+/// Random games of the default model play out without panicking
+#[test]
+fn random_games_play_out() {
+    let mut chooser = ChaCha8Rng::seed_from_u64(5);
+    for seed in 0..200 {
+        let mut state = GameState::start_game_seeded(seed,
+                                                     SpawnModel::default());
+        while !state.is_over() && state.time < 2_000 {
+            let moves = state.legal_moves();
+            state.apply(moves[chooser.gen_range(0..moves.len())]);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Spawn logs are written and read back as they were, replayed in order,
+//! and those drawn from known parameters give them back, pass the check of
+//! their model and draw its posterior near them.
+
+use iatomas::agent::{Agent, HeuristicAgent};
+use iatomas::game::{AtomType, GameState, Move, SPAWN_PARAM_NAMES, SpawnModel,
+                    SpawnParams};
+use iatomas::spawn::{CheckConfig, PosteriorConfig, SpawnPosterior,
+                     SpawnRecord, check_spawn, fit_spawn, parse_spawn_log,
+                     replay_model, write_spawn_log};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Atoms of `games` games of `length` atoms drawn by `model`, the score
+/// growing by `points` a move
+fn draw_log(model: &SpawnModel, games: u64, length: u32, points: u32)
+                                                        -> Vec<SpawnRecord> {
+    let mut records = Vec::new();
+    for game in 0..games {
+        let mut model = model.clone();
+        let mut rng = ChaCha8Rng::seed_from_u64(game);
+        for time in 0..length {
+            let score = time * points;
+            records.push(SpawnRecord {
+                time,
+                score: Some(score),
+                atom: model.draw(time, score, &mut rng),
+            });
+        }
+    }
+    records
+}
+
+/// The fit lands within a few standard errors of the parameters the log was
+/// drawn with. Those of regular atoms come from a smoothed likelihood and
+/// understate the spread of the fit a little, hence five of them.
+#[test]
+fn fit_recovers_parameters() {
+    let truth = SpawnParams {
+        shift_slope: 0.015,
+        shift_intercept: 1.6,
+        n_slope: 0.02,
+        n_intercept: 2.4,
+        p: 0.4,
+        plus: 0.2,
+        minus: 0.06,
+        dark_plus: 0.02,
+        neutrino: 0.03,
+    };
+    let records = draw_log(&SpawnModel::Drawing(truth.clone()), 4, 400, 5);
+    let fit = fit_spawn(&records).unwrap();
+    assert_eq!(fit.atoms, records.len());
+    for ((name, (value, error)), expected) in SPAWN_PARAM_NAMES.iter()
+        .zip(fit.params.values().iter().zip(fit.std_errors.values()))
+        .zip(truth.values())
+    {
+        assert!(error.is_finite() && error > 0.0, "{}: error {}", name,
+                error);
+        assert!((value - expected).abs() <= 5.0 * error,
+                "{}: {} ± {}, drawn with {}", name, value, error, expected);
+    }
+}
+
+/// Atoms that could not come are not given a standard error
+#[test]
+fn fit_without_eligible_atoms() {
+    let records = draw_log(&SpawnModel::Drawing(SpawnParams::default()), 4,
+                           200, 1);
+    assert!(records.iter().all(|r| !matches!(r.atom, AtomType::DarkPlus |
+                                                     AtomType::Neutrino)));
+    let fit = fit_spawn(&records).unwrap();
+    assert!(fit.std_errors.dark_plus.is_nan());
+    assert!(fit.std_errors.neutrino.is_nan());
+    assert!(fit.std_errors.plus.is_finite());
+}
+
+#[test]
+fn log_round_trip() {
+    let mut records = draw_log(&SpawnModel::Drawing(SpawnParams::default()),
+                               3, 500, 4);
+    // Unknown scores, gaps in time, and atoms beyond the named ones
+    records[5].score = None;
+    records[40].time += 3;
+    records[41].atom = AtomType::Atom(200);
+    records[42].atom = AtomType::Atom(u8::MAX);
+    assert!(records.iter().any(|r| r.atom == AtomType::DarkPlus));
+    assert!(records.iter().any(|r| r.atom == AtomType::Neutrino));
+
+    let mut text = Vec::new();
+    write_spawn_log(&records, &mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert_eq!(parse_spawn_log(&text).unwrap(), records);
+}
+
+#[test]
+fn log_in_both_notations() {
+    let records = parse_spawn_log("# estimate.py\n2 2 1 -2:0 2,3  # end\n\
+                                   Li@10:42 Nu D+ -").unwrap();
+    let atoms: Vec<AtomType> = records.iter().map(|r| r.atom.clone())
+                                      .collect();
+    assert_eq!(atoms, [AtomType::Atom(1), AtomType::Atom(1), AtomType::Atom(0),
+                       AtomType::DarkPlus, AtomType::Atom(1),
+                       AtomType::Atom(2), AtomType::Atom(2),
+                       AtomType::Neutrino, AtomType::DarkPlus,
+                       AtomType::Minus]);
+    let times: Vec<u32> = records.iter().map(|r| r.time).collect();
+    assert_eq!(times, [0, 1, 2, 3, 4, 5, 10, 11, 12, 13]);
+    assert_eq!(records[3].score, Some(0));
+    assert_eq!(records[6].score, Some(42));
+    assert_eq!(records[7].score, None);
+    for bad in ["Xx", "H@soon", "H:lots", "-4", "0@1:2:3"] {
+        assert!(parse_spawn_log(bad).is_err(), "{}", bad);
+    }
+}
+
+/// A game on a replayed log is dealt the logged atoms, in order, then goes
+/// on with the simple model
+#[test]
+fn replay_deals_logged_atoms() {
+    let records = draw_log(&SpawnModel::Simple, 1, 300, 10);
+    let model = replay_model(&records).unwrap();
+    let mut agent = HeuristicAgent::default();
+    let mut state = GameState::start_game_seeded(3, model);
+    let mut dealt = vec![state.incoming.t.clone()];
+    while !state.is_over() && dealt.len() < records.len() + 20 {
+        let mv = agent.choose(&state);
+        state.apply(mv);
+        if let Move::Place(_) = mv {
+            dealt.push(state.incoming.t.clone());
+        }
+    }
+    let logged: Vec<AtomType> = records.iter().map(|r| r.atom.clone())
+                                       .collect();
+    let n = dealt.len().min(logged.len());
+    assert!(n > 50, "the game ended after {} atoms", n);
+    assert_eq!(dealt[..n], logged[..n]);
+
+    // Logs with gaps or twice the same time cannot be replayed
+    let mut gap = records.clone();
+    gap.remove(10);
+    assert!(replay_model(&gap).is_err());
+    let mut twice = records;
+    twice[11].time = 10;
+    assert!(replay_model(&twice).is_err());
+}
+
+/// Atoms drawn by a model pass its goodness-of-fit check, and fail that of
+/// another model
+#[test]
+fn drawn_atoms_pass_their_check() {
+    let config = CheckConfig::default();
+    let drawing = SpawnModel::Drawing(SpawnParams::default());
+    for model in [SpawnModel::default(), drawing.clone()] {
+        let records = draw_log(&model, 3, 600, 4);
+        let check = check_spawn(&model, &records, &config);
+        assert!(!check.is_off(), "{:?}: {:#?}", model, check);
+        assert_eq!(check.windows.len(), 6);
+        assert_eq!(check.specials.len(), 4);
+    }
+
+    let records = draw_log(&SpawnModel::default(), 3, 600, 4);
+    assert!(check_spawn(&drawing, &records, &config).is_off());
+}
+
+/// The posterior narrows around the parameters the atoms were drawn with,
+/// resampling whenever its weights degenerate
+#[test]
+fn posterior_follows_drawn_atoms() {
+    let truth = SpawnParams { plus: 0.25, minus: 0.03, ..Default::default() };
+    let records = draw_log(&SpawnModel::Drawing(truth.clone()), 1, 400, 5);
+    let config = PosteriorConfig { particles: 500, ..Default::default() };
+    let mut posterior = SpawnPosterior::new(&config);
+    let prior_std = posterior.std_dev();
+    for record in &records {
+        posterior.observe(record);
+        let total: f64 = posterior.weights().iter().sum();
+        assert!((total - 1.0).abs() < 1e-9, "weights sum to {}", total);
+        assert!(posterior.effective_size() >= 250.0);
+    }
+    assert_eq!(posterior.observed(), records.len());
+
+    let (mean, std) = (posterior.mean(), posterior.std_dev());
+    assert!(std.plus < prior_std.plus / 2.0);
+    assert!((mean.plus - truth.plus).abs() <= 3.0 * std.plus,
+            "plus: {} ± {}, drawn with {}", mean.plus, std.plus, truth.plus);
+    assert!((mean.minus - truth.minus).abs() <= 3.0 * std.minus,
+            "minus: {} ± {}, drawn with {}", mean.minus, std.minus,
+            truth.minus);
+
+    let last = records.last().unwrap();
+    let predictive = posterior.predictive(last.time + 1, 0);
+    let total: f64 = predictive.iter().map(|(_, c)| c).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+    assert!(!predictive.iter().any(|(a, _)| *a == AtomType::DarkPlus));
+}
+
+/// An atom no particle can explain leaves the posterior as it was
+#[test]
+fn posterior_leaves_out_impossible_atoms() {
+    let records = draw_log(&SpawnModel::Drawing(SpawnParams::default()), 1,
+                           50, 1);
+    let config = PosteriorConfig { particles: 200, ..Default::default() };
+    let mut posterior = SpawnPosterior::new(&config);
+    for record in &records {
+        posterior.observe(record);
+    }
+    let (weights, mean) = (posterior.weights(), posterior.mean());
+    posterior.observe(&SpawnRecord {
+        time: 50,
+        score: Some(0),
+        atom: AtomType::DarkPlus,
+    });
+    assert_eq!(posterior.observed(), records.len());
+    assert_eq!(posterior.weights(), weights);
+    assert_eq!(posterior.mean(), mean);
+    assert!(weights.iter().all(|w| w.is_finite()));
+
+    // Later atoms are still taken into account
+    posterior.observe(&records[0]);
+    assert_eq!(posterior.observed(), records.len() + 1);
+    assert!(posterior.weights().iter().all(|w| w.is_finite()));
+}
+
+/// The incoming atom of a game is observed once, however many times its
+/// state is looked at
+#[test]
+fn posterior_observes_states_once() {
+    let mut posterior = SpawnPosterior::new(&PosteriorConfig {
+        particles: 100,
+        ..Default::default()
+    });
+    let mut state = GameState::start_game_seeded(1, SpawnModel::Simple);
+    let mut agent = HeuristicAgent::default();
+    let mut placed = 0;
+    while !state.is_over() && placed < 30 {
+        posterior.observe_state(&state);
+        posterior.observe_state(&state);
+        let mv = agent.choose(&state);
+        state.apply(mv);
+        if let Move::Place(_) = mv {
+            placed += 1;
+        }
+    }
+    assert_eq!(posterior.observed(), placed);
+    let next = posterior.predict_next(&state);
+    let total: f64 = next.iter().map(|(_, c)| c).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+}
@@ -0,0 +1,94 @@
+//! Theme files are read over the default theme, and the bundled ones are
+//! valid.
+
+use std::fs;
+
+use iatomas::game::{ATOMS_SYMBOLS, AtomType, Theme};
+
+#[test]
+fn bundled_themes_parse() {
+    let mut names = Vec::new();
+    for entry in fs::read_dir("resources/themes").unwrap() {
+        let path = entry.unwrap().path();
+        let theme = Theme::load(&path).unwrap_or_else(|e| {
+            panic!("{}: {}", path.display(), e)
+        });
+        assert!(theme.font.is_file(), "{}: no font", path.display());
+        assert_eq!(theme.elements.len(), ATOMS_SYMBOLS.len());
+        names.push(theme.name);
+    }
+    assert!(names.len() >= 2);
+}
+
+#[test]
+fn theme_overrides_default() {
+    let theme = Theme::parse(r##"
+        # Comments and blank lines are ignored
+        name = "test"   # even after a value
+        title_size = 24
+
+        [colors]
+        background = "#102030"
+        plus = "#00ff00"
+
+        [elements]
+        He = "#abcdef"
+        "3" = "#010203"
+
+        [symbols]
+        neutrino = "Nu"
+        H = "h"
+    "##).unwrap();
+    let default = Theme::default();
+    assert_eq!(theme.name, "test");
+    assert_eq!(theme.title_size, 24.0);
+    assert_eq!(theme.symbol_size, default.symbol_size);
+    assert_eq!(theme.background, (0x10, 0x20, 0x30));
+    assert_eq!(theme.ring, default.ring);
+    assert_eq!(theme.atom(&AtomType::Plus).color, (0, 255, 0));
+    assert_eq!(theme.atom(&AtomType::Plus).symbol, "+");
+    assert_eq!(theme.atom(&AtomType::Atom(1)).color, (0xab, 0xcd, 0xef));
+    assert_eq!(theme.atom(&AtomType::Atom(2)).color, (1, 2, 3));
+    assert_eq!(theme.atom(&AtomType::Neutrino).symbol, "Nu");
+    assert_eq!(theme.atom(&AtomType::Atom(0)).symbol, "h");
+    assert_eq!(theme.atom(&AtomType::Atom(0)).color,
+               default.atom(&AtomType::Atom(0)).color);
+}
+
+#[test]
+fn bad_themes_are_refused() {
+    for content in [
+        "[colors]\nbackground = \"black\"",
+        "[colors]\nborder = \"#000000\"",
+        "[elements]\nXx = \"#000000\"",
+        "[fonts]\npath = \"a.ttf\"",
+        "title_size = \"big\"",
+        "name = \"unterminated",
+        "name \"no equals\"",
+    ] {
+        assert!(Theme::parse(content).is_err(), "{}", content);
+    }
+}
+
+/// Errors tell the line, and what of TOML theme files do not read
+#[test]
+fn errors_tell_what_is_wrong() {
+    for (content, expected) in [
+        ("name = \"a\"\n\n[colors]\nbackground = \"black\"",
+         "line 4: `background` in [colors] should be a colour"),
+        ("title_size = \"big\"", "line 1: `title_size` should be a positive"),
+        ("[colors]\nborder = \"#000000\"",
+         "line 2: unknown key `border` in [colors]"),
+        ("name = \"unterminated", "line 1: name: string without its closing"),
+        ("name \"no equals\"", "line 1: expected `=` after `name`"),
+        ("name = dusk", "expected a string in double quotes or a number"),
+        ("name = 'dusk'", "literal strings are not read"),
+        ("sizes = [12, 20]", "arrays are not read"),
+        ("colors.ring = \"#000000\"", "dotted keys are not read"),
+        ("[[themes]]", "arrays of tables are not read"),
+        ("[colors", "expected `]` after the table `colors`"),
+    ] {
+        let error = Theme::parse(content).unwrap_err().to_string();
+        assert!(error.contains(expected), "{:?}: {}", content, error);
+    }
+}
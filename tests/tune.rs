@@ -0,0 +1,76 @@
+//! Tuning checkpoints are read back as written, and resuming from them goes
+//! on as if the run had not stopped.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use iatomas::agent::{FEATURES, Generation, Individual, TuneConfig, Weights,
+                     tune};
+use common::scratch_dir;
+
+/// A run small enough for a test
+fn config(out_dir: PathBuf, generations: usize) -> TuneConfig {
+    TuneConfig {
+        population: 4,
+        generations,
+        games: 2,
+        elites: 1,
+        threads: 2,
+        out_dir,
+        ..TuneConfig::default()
+    }
+}
+
+#[test]
+fn checkpoint_round_trip() {
+    let dir = scratch_dir("tune-checkpoint");
+    let mut weights = Weights::default();
+    weights.values[0] = 0.1 + 0.2;
+    weights.values[3] = -1e-17;
+    let generation = Generation {
+        index: 7,
+        individuals: vec![
+            Individual { weights: weights.clone(), fitness: 1234.5 },
+            Individual { weights: Weights::default(), fitness: 1.0 / 3.0 },
+        ],
+    };
+    let path = dir.join("gen-0007.txt");
+    generation.save(&path).unwrap();
+    let loaded = Generation::load(&path).unwrap();
+    assert_eq!(loaded.index, 7);
+    assert_eq!(loaded.individuals.len(), 2);
+    for (a, b) in loaded.individuals.iter().zip(&generation.individuals) {
+        assert_eq!(a.weights, b.weights);
+        assert_eq!(a.fitness, b.fitness);
+    }
+    assert_eq!(loaded.best().fitness, 1234.5);
+
+    fs::write(&path, "generation 1\n1.0 2.0\n").unwrap();
+    assert!(Generation::load(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Stopping after a generation and resuming gives the same weights as a run
+/// that did not stop, and resuming a finished run gives its best weights
+#[test]
+fn resume_continues_the_run() {
+    // Weights that mutations are sure to beat, so that the best ones differ
+    let init = Weights { values: [0.0; FEATURES] };
+    let whole_dir = scratch_dir("tune-whole");
+    let whole = tune(&config(whole_dir.clone(), 2), &init, false).unwrap();
+
+    let split_dir = scratch_dir("tune-split");
+    let first = tune(&config(split_dir.clone(), 1), &init, false).unwrap();
+    assert_ne!(first, init);
+    let again = tune(&config(split_dir.clone(), 1), &init, true).unwrap();
+    assert_eq!(again, first);
+    let resumed = tune(&config(split_dir.clone(), 2), &init, true).unwrap();
+    assert_eq!(resumed, whole);
+    assert_eq!(Weights::load(split_dir.join("best.weights")).unwrap(),
+               whole);
+
+    fs::remove_dir_all(&whole_dir).unwrap();
+    fs::remove_dir_all(&split_dir).unwrap();
+}